@@ -0,0 +1,1268 @@
+// 管理员写入 API
+//
+// 所有写入都落到可写覆盖库（OVERLAY_DATABASE_PATH），上游 database.db 保持只读。
+// 修改上游命令、分类（连同分组和一行命令）或提示时先将其复制到覆盖库（同名遮蔽），
+// 再修改副本；复制与修改在同一事务中，修改失败时不会留下副本。遮蔽上游的条目不能改名，
+// 否则上游条目会重新出现。删除只作用于覆盖库条目。
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::MutexGuard;
+use utoipa::ToSchema;
+
+use crate::overlay::{self, Source, OVERLAY_ID_OFFSET};
+use crate::{catalog, revisions, suggestions};
+use crate::{
+    category_description, load_command_detail, load_tip_sections, ApiResponse, AppError, AppState, BasicCategory,
+    BasicCommand, BasicGroup, CommandDetail, CommandSection, ErrorResponse, Tip, TipSection,
+};
+
+const MAX_NAME_LEN: usize = 128;
+const MAX_DESCRIPTION_LEN: usize = 4096;
+const MAX_CONTENT_LEN: usize = 256 * 1024;
+const MAX_AUTHOR_LEN: usize = 64;
+
+// 请求模型
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CommandInput {
+    name: String,
+    category: i64,
+    description: String,
+    // None 表示保留现有章节，Some 表示整体替换
+    sections: Option<Vec<CommandSection>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SectionInput {
+    content: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CategoryInput {
+    title: String,
+    position: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GroupInput {
+    category_id: i64,
+    position: i64,
+    description: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BasicCommandInput {
+    group_id: i64,
+    command: String,
+    #[serde(default)]
+    mans: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TipInput {
+    title: String,
+    position: i64,
+    #[serde(default)]
+    sections: Vec<TipSection>,
+}
+
+// 输入校验
+
+pub fn require_text(field: &str, value: &str, max_len: usize) -> Result<(), AppError> {
+    if value.trim().is_empty() {
+        return Err(AppError::InvalidInput(format!("{} cannot be empty", field)));
+    }
+    if value.len() > max_len {
+        return Err(AppError::InvalidInput(format!("{} exceeds {} bytes", field, max_len)));
+    }
+    Ok(())
+}
+
+fn require_single_line(field: &str, value: &str) -> Result<(), AppError> {
+    if value.contains(['\n', '\r']) {
+        return Err(AppError::InvalidInput(format!("{} must be a single line", field)));
+    }
+    Ok(())
+}
+
+fn validate_section_title(title: &str) -> Result<(), AppError> {
+    require_text("Section title", title, MAX_NAME_LEN)?;
+    require_single_line("Section title", title)
+}
+
+impl CommandInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Command name", &self.name, MAX_NAME_LEN)?;
+        require_single_line("Command name", &self.name)?;
+        if self.name.trim() != self.name {
+            return Err(AppError::InvalidInput("Command name must not have surrounding whitespace".to_string()));
+        }
+        require_text("Command description", &self.description, MAX_DESCRIPTION_LEN)?;
+        if self.category < 0 {
+            return Err(AppError::InvalidInput("Command category must not be negative".to_string()));
+        }
+        for section in self.sections.iter().flatten() {
+            validate_section_title(&section.title)?;
+            require_text("Section content", &section.content, MAX_CONTENT_LEN)?;
+        }
+        Ok(())
+    }
+}
+
+impl CategoryInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Category title", &self.title, MAX_NAME_LEN)?;
+        require_single_line("Category title", &self.title)
+    }
+}
+
+impl GroupInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Group description", &self.description, MAX_DESCRIPTION_LEN)
+    }
+}
+
+impl BasicCommandInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Command", &self.command, MAX_CONTENT_LEN)
+    }
+}
+
+impl TipInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Tip title", &self.title, MAX_NAME_LEN)?;
+        if self.sections.is_empty() {
+            return Err(AppError::InvalidInput("Tip must have at least one section".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// 鉴权
+
+// 读取 `Authorization: Bearer <token>` 中的 token
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+// 常量时间比较，避免通过响应时间猜测 token
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn require_admin(req: &HttpRequest, data: &AppState) -> Result<(), AppError> {
+    let expected = match &data.admin_token {
+        Some(token) => token,
+        None => {
+            warn!("Admin request rejected: ADMIN_TOKEN is not configured");
+            return Err(AppError::Unauthorized);
+        }
+    };
+
+    match bearer_token(req) {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => {
+            warn!("Admin request rejected: missing or invalid bearer token");
+            Err(AppError::Unauthorized)
+        }
+    }
+}
+
+// 修订记录的作者：取自 `X-Author` 请求头，缺省为 "admin"
+pub fn request_author(req: &HttpRequest) -> String {
+    req.headers()
+        .get("X-Author")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().chars().filter(|c| !c.is_control()).take(MAX_AUTHOR_LEN).collect::<String>())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "admin".to_string())
+}
+
+// 覆盖库辅助函数
+
+fn lock_writable(data: &AppState) -> Result<MutexGuard<'_, Connection>, AppError> {
+    data.lock_overlay()?
+        .ok_or_else(|| AppError::InternalError("Overlay database is not configured".to_string()))
+}
+
+// 写入上游条目需要同时读取上游库；先锁上游库再锁覆盖库，与其他同时持有两个锁的代码顺序一致
+fn lock_both(data: &AppState) -> Result<(MutexGuard<'_, Connection>, MutexGuard<'_, Connection>), AppError> {
+    let base = data.lock_db()?;
+    Ok((base, lock_writable(data)?))
+}
+
+// 将 API id 转换为覆盖库 id；用于删除。上游库只读且没有墓碑记录，上游条目不能删除
+fn overlay_only(public_id: i64, what: &str) -> Result<i64, AppError> {
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => Ok(id),
+        (Source::Base, _) => Err(AppError::InvalidInput(format!(
+            "{} {} belongs to the upstream database and is read-only",
+            what, public_id
+        ))),
+    }
+}
+
+fn ensure_exists(conn: &Connection, table: &str, id: i64, what: &str) -> Result<(), AppError> {
+    let exists: Option<i64> = conn
+        .query_row(&format!("SELECT id FROM {} WHERE id = ?1", table), params![id], |row| row.get(0))
+        .optional()?;
+    match exists {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound(format!("{} {} not found", what, id))),
+    }
+}
+
+// 覆盖库中的名称必须唯一，否则列表中出现两条同名条目，而详情与遮蔽只使用 id 最小的一条；
+// except 为正在修改的条目
+fn ensure_unique(conn: &Connection, table: &str, column: &str, value: &str, except: Option<i64>, what: &str) -> Result<(), AppError> {
+    let existing: Option<i64> = conn
+        .query_row(
+            &format!("SELECT id FROM {} WHERE {} = ?1 AND id IS NOT ?2 LIMIT 1", table, column),
+            params![value, except],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match existing {
+        Some(id) => Err(AppError::InvalidInput(format!(
+            "{} '{}' already exists in the overlay as {}",
+            what,
+            value,
+            id + OVERLAY_ID_OFFSET
+        ))),
+        None => Ok(()),
+    }
+}
+
+// 覆盖库条目按名称遮蔽上游条目：遮蔽上游的条目改名后上游条目会重新出现，
+// 再写入上游 id 时又会另建一份副本，因此拒绝改名
+fn keep_shadow_name(base: &Connection, tx: &Transaction, table: &str, column: &str, id: i64, name: &str, what: &str) -> Result<(), AppError> {
+    let current: String = tx.query_row(&format!("SELECT {} FROM {} WHERE id = ?1", column, table), params![id], |row| row.get(0))?;
+    if current == name || !overlay::table_exists(base, table)? {
+        return Ok(());
+    }
+    let shadowed: Option<i64> = base
+        .query_row(&format!("SELECT id FROM {} WHERE {} = ?1 LIMIT 1", table, column), params![&current], |row| row.get(0))
+        .optional()?;
+    match shadowed {
+        Some(_) => Err(AppError::InvalidInput(format!("{} '{}' shadows an upstream entry and cannot be renamed", what, current))),
+        None => Ok(()),
+    }
+}
+
+// 将上游命令（连同全部章节）复制到覆盖库；若覆盖库已有同名命令则直接返回其 id。
+// 复制写入调用方的事务，与随后的修改一起提交或回滚
+pub fn copy_on_write(base: &Connection, tx: &Transaction, base_id: i64) -> Result<i64, AppError> {
+    let command: Option<(String, i64, String)> = base
+        .query_row(
+            "SELECT name, category, description FROM Command WHERE id = ?1",
+            params![base_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let (name, category, description) = command.ok_or(AppError::CommandNotFound)?;
+
+    let shadow_id: Option<i64> = tx
+        .query_row("SELECT id FROM Command WHERE name = ?1 ORDER BY id LIMIT 1", params![&name], |row| row.get(0))
+        .optional()?;
+    if let Some(id) = shadow_id {
+        return Ok(id);
+    }
+
+    let mut stmt = base.prepare("SELECT title, content FROM CommandSection WHERE command_id = ?1 ORDER BY id")?;
+    let sections: Vec<(String, String)> = stmt
+        .query_map(params![base_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    tx.execute(
+        "INSERT INTO Command (category, name, description) VALUES (?1, ?2, ?3)",
+        params![category, &name, &description],
+    )?;
+    let overlay_id = tx.last_insert_rowid();
+    for (title, content) in &sections {
+        tx.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![title, content, overlay_id],
+        )?;
+    }
+    revisions::record(tx, overlay_id, "upstream", "import")?;
+
+    info!("Copied upstream command '{}' ({}) to overlay as {}", name, base_id, overlay_id);
+    Ok(overlay_id)
+}
+
+// 解析可写命令 id：覆盖库命令直接使用，上游命令先复制到覆盖库
+fn writable_command_id(base: &Connection, tx: &Transaction, public_id: i64) -> Result<i64, AppError> {
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => {
+            ensure_exists(tx, "Command", id, "Command").map_err(|_| AppError::CommandNotFound)?;
+            Ok(id)
+        }
+        (Source::Base, id) => copy_on_write(base, tx, id),
+    }
+}
+
+// 将上游分类（连同分组和一行命令）复制到覆盖库；若覆盖库已有同名分类则直接返回其 id。
+// 分组与一行命令的 id 对应关系记录在 UpstreamCopy 中，复制后上游 id 仍可用于写入
+fn copy_category_on_write(base: &Connection, tx: &Transaction, base_id: i64) -> Result<i64, AppError> {
+    let category: Option<(i64, String)> = base
+        .query_row(
+            "SELECT position, title FROM BasicCategory WHERE id = ?1",
+            params![base_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (position, title) = category.ok_or_else(|| AppError::NotFound(format!("Category {} not found", base_id)))?;
+
+    let shadow_id: Option<i64> = tx
+        .query_row("SELECT id FROM BasicCategory WHERE title = ?1 ORDER BY id LIMIT 1", params![&title], |row| row.get(0))
+        .optional()?;
+    if let Some(id) = shadow_id {
+        return Ok(id);
+    }
+
+    // 上游库可能只有 BasicCategory 而没有分组数据
+    let has_groups = overlay::table_exists(base, "BasicGroup")? && overlay::table_exists(base, "BasicCommand")?;
+    let mut groups: Vec<(i64, i64, String)> = vec![];
+    let mut commands: Vec<(i64, i64, String, String)> = vec![];
+    if has_groups {
+        let mut stmt = base.prepare("SELECT id, position, description FROM BasicGroup WHERE category_id = ?1 ORDER BY id")?;
+        groups = stmt
+            .query_map(params![base_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+        let mut stmt = base.prepare(
+            "SELECT bc.id, bc.group_id, bc.command, bc.mans FROM BasicCommand bc
+             JOIN BasicGroup bg ON bc.group_id = bg.id WHERE bg.category_id = ?1 ORDER BY bc.id",
+        )?;
+        commands = stmt
+            .query_map(params![base_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<Result<_, _>>()?;
+    }
+
+    tx.execute("INSERT INTO BasicCategory (position, title) VALUES (?1, ?2)", params![position, &title])?;
+    let overlay_id = tx.last_insert_rowid();
+    let mut group_ids = HashMap::new();
+    for (group_id, position, description) in &groups {
+        tx.execute(
+            "INSERT INTO BasicGroup (position, description, category_id) VALUES (?1, ?2, ?3)",
+            params![position, description, overlay_id],
+        )?;
+        group_ids.insert(*group_id, tx.last_insert_rowid());
+        record_copy(tx, "BasicGroup", *group_id, tx.last_insert_rowid())?;
+    }
+    for (command_id, group_id, command, mans) in &commands {
+        tx.execute(
+            "INSERT INTO BasicCommand (command, mans, group_id) VALUES (?1, ?2, ?3)",
+            params![command, mans, group_ids[group_id]],
+        )?;
+        record_copy(tx, "BasicCommand", *command_id, tx.last_insert_rowid())?;
+    }
+
+    info!(
+        "Copied upstream category '{}' ({}) to overlay as {} with {} groups and {} commands",
+        title, base_id, overlay_id, groups.len(), commands.len()
+    );
+    Ok(overlay_id)
+}
+
+fn record_copy(conn: &Connection, table: &str, base_id: i64, overlay_id: i64) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO UpstreamCopy (kind, base_id, overlay_id) VALUES (?1, ?2, ?3)",
+        params![table, base_id, overlay_id],
+    )?;
+    Ok(())
+}
+
+// 上游条目在覆盖库中仍然存在的副本
+fn copied_id(overlay: &Connection, table: &str, base_id: i64) -> Result<Option<i64>, AppError> {
+    let sql = format!(
+        "SELECT c.overlay_id FROM UpstreamCopy c JOIN {} t ON t.id = c.overlay_id WHERE c.kind = ?1 AND c.base_id = ?2",
+        table
+    );
+    Ok(overlay.query_row(&sql, params![table, base_id], |row| row.get(0)).optional()?)
+}
+
+// 解析可写分类 id：上游分类先复制到覆盖库
+fn writable_category_id(base: &Connection, tx: &Transaction, public_id: i64) -> Result<i64, AppError> {
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => {
+            ensure_exists(tx, "BasicCategory", id, "Category")?;
+            Ok(id)
+        }
+        (Source::Base, id) => copy_category_on_write(base, tx, id),
+    }
+}
+
+// 解析上游分组或一行命令的可写 id：连同所属分类复制到覆盖库。
+// 分类被管理员另建的同名分类遮蔽时上游条目不可写，需改用覆盖库中的条目
+fn writable_grouped_id(base: &Connection, tx: &Transaction, table: &str, what: &str, public_id: i64) -> Result<i64, AppError> {
+    let id = match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => {
+            ensure_exists(tx, table, id, what)?;
+            return Ok(id);
+        }
+        (Source::Base, id) => id,
+    };
+
+    if let Some(copy) = copied_id(tx, table, id)? {
+        return Ok(copy);
+    }
+
+    let sql = match table {
+        "BasicGroup" => "SELECT category_id FROM BasicGroup WHERE id = ?1",
+        _ => "SELECT bg.category_id FROM BasicCommand bc JOIN BasicGroup bg ON bc.group_id = bg.id WHERE bc.id = ?1",
+    };
+    let has_groups = overlay::table_exists(base, "BasicGroup")? && overlay::table_exists(base, "BasicCommand")?;
+    let category_id: Option<i64> = match has_groups {
+        true => base.query_row(sql, params![id], |row| row.get(0)).optional()?,
+        false => None,
+    };
+    let category_id = category_id.ok_or_else(|| AppError::NotFound(format!("{} {} not found", what, id)))?;
+
+    let copy = copy_category_on_write(base, tx, category_id)?;
+    copied_id(tx, table, id)?.ok_or_else(|| {
+        AppError::NotFound(format!("{} {} is shadowed by overlay category {}", what, id, copy + OVERLAY_ID_OFFSET))
+    })
+}
+
+fn writable_group_id(base: &Connection, tx: &Transaction, public_id: i64) -> Result<i64, AppError> {
+    writable_grouped_id(base, tx, "BasicGroup", "Group", public_id)
+}
+
+fn writable_basic_command_id(base: &Connection, tx: &Transaction, public_id: i64) -> Result<i64, AppError> {
+    writable_grouped_id(base, tx, "BasicCommand", "Basic command", public_id)
+}
+
+// 将上游提示（连同章节）复制到覆盖库；若覆盖库已有同名提示则直接返回其 id
+fn copy_tip_on_write(base: &Connection, tx: &Transaction, base_id: i64) -> Result<i64, AppError> {
+    let tip: Option<(String, i64)> = base
+        .query_row("SELECT title, position FROM Tip WHERE id = ?1", params![base_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+    let (title, position) = tip.ok_or_else(|| AppError::NotFound(format!("Tip {} not found", base_id)))?;
+
+    let shadow_id: Option<i64> = tx
+        .query_row("SELECT id FROM Tip WHERE title = ?1 ORDER BY id LIMIT 1", params![&title], |row| row.get(0))
+        .optional()?;
+    if let Some(id) = shadow_id {
+        return Ok(id);
+    }
+
+    let sections = load_tip_sections(base, base_id)?;
+    tx.execute("INSERT INTO Tip (title, position) VALUES (?1, ?2)", params![&title, position])?;
+    let overlay_id = tx.last_insert_rowid();
+    for (position, section) in sections.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO TipSection (tip_id, position, type, data1, data2, extra) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![overlay_id, position as i64, section.section_type, &section.data1, &section.data2, &section.extra],
+        )?;
+    }
+
+    info!("Copied upstream tip '{}' ({}) to overlay as {}", title, base_id, overlay_id);
+    Ok(overlay_id)
+}
+
+fn writable_tip_id(base: &Connection, tx: &Transaction, public_id: i64) -> Result<i64, AppError> {
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => {
+            ensure_exists(tx, "Tip", id, "Tip")?;
+            Ok(id)
+        }
+        (Source::Base, id) => copy_tip_on_write(base, tx, id),
+    }
+}
+
+fn overlay_command_detail(overlay: &Connection, id: i64) -> Result<CommandDetail, AppError> {
+    load_command_detail(overlay, id)?
+        .map(overlay::mark_detail)
+        .ok_or(AppError::CommandNotFound)
+}
+
+fn replace_sections(conn: &Connection, command_id: i64, sections: &[CommandSection]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM CommandSection WHERE command_id = ?1", params![command_id])?;
+    for section in sections {
+        conn.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![&section.title, &section.content, command_id],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn ok<T: serde::Serialize>(data: T) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(data),
+        message: None,
+    })
+}
+
+fn deleted(what: &str, public_id: i64) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::<String> {
+        success: true,
+        data: None,
+        message: Some(format!("{} {} deleted", what, public_id)),
+    })
+}
+
+// 命令
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/commands",
+    tag = "admin",
+    request_body = CommandInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The new overlay command", body = ApiResponse<CommandDetail>),
+        (status = 400, description = "Invalid field, or the name is already taken in the overlay (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn create_command(
+    req: HttpRequest,
+    body: web::Json<CommandInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_unique(&overlay, "Command", "name", &body.name, None, "Command")?;
+    let tx = overlay.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO Command (category, name, description) VALUES (?1, ?2, ?3)",
+        params![body.category, &body.name, &body.description],
+    )?;
+    let id = tx.last_insert_rowid();
+    replace_sections(&tx, id, body.sections.as_deref().unwrap_or_default())?;
+    revisions::record(&tx, id, &request_author(&req), "create")?;
+    tx.commit()?;
+
+    info!("Admin created command '{}' ({})", body.name, id + OVERLAY_ID_OFFSET);
+    Ok(ok(overlay_command_detail(&overlay, id)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/commands/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Command id; upstream ids are copied to the overlay before the write")),
+    request_body = CommandInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The updated overlay command", body = ApiResponse<CommandDetail>),
+        (status = 400, description = "Invalid field, a taken name, or a rename of a command shadowing an upstream one (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn update_command(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<CommandInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_command_id(&base, &tx, *path)?;
+    keep_shadow_name(&base, &tx, "Command", "name", id, &body.name, "Command")?;
+    ensure_unique(&tx, "Command", "name", &body.name, Some(id), "Command")?;
+    tx.execute(
+        "UPDATE Command SET category = ?1, name = ?2, description = ?3 WHERE id = ?4",
+        params![body.category, &body.name, &body.description, id],
+    )?;
+    if let Some(sections) = &body.sections {
+        replace_sections(&tx, id, sections)?;
+    }
+    revisions::record(&tx, id, &request_author(&req), "update")?;
+    tx.commit()?;
+
+    info!("Admin updated command '{}' ({})", body.name, id + OVERLAY_ID_OFFSET);
+    Ok(ok(overlay_command_detail(&overlay, id)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/commands/{id}",
+    tag = "admin",
+    description = "Deletes an overlay command. Upstream commands are read-only and cannot be deleted; deleting the overlay copy of an edited upstream command makes the upstream one visible again.",
+    params(("id" = i64, Path, description = "Overlay command id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Command deleted", body = ApiResponse<String>),
+        (status = 400, description = "Upstream id; upstream entries are read-only (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_command(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let id = overlay_only(*path, "Command")?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_exists(&overlay, "Command", id, "Command").map_err(|_| AppError::CommandNotFound)?;
+    let tx = overlay.unchecked_transaction()?;
+    // 删除前记录最后状态，便于回滚恢复
+    revisions::record(&tx, id, &request_author(&req), "delete")?;
+    tx.execute("DELETE FROM CommandSection WHERE command_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Command WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    info!("Admin deleted command {}", *path);
+    Ok(deleted("Command", *path))
+}
+
+// 命令章节（按标题定位）
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/commands/{id}/sections/{title}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Command id; upstream ids are copied to the overlay before the write"), ("title" = String, Path, description = "Section title, e.g. `EXAMPLES`")),
+    request_body = SectionInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Command with the section created or replaced", body = ApiResponse<CommandDetail>),
+        (status = 400, description = "Invalid section title or content (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn put_section(
+    req: HttpRequest,
+    path: web::Path<(i64, String)>,
+    body: web::Json<SectionInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let (public_id, title) = path.into_inner();
+    validate_section_title(&title)?;
+    require_text("Section content", &body.content, MAX_CONTENT_LEN)?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_command_id(&base, &tx, public_id)?;
+    let updated = tx.execute(
+        "UPDATE CommandSection SET content = ?1 WHERE command_id = ?2 AND title = ?3",
+        params![&body.content, id, &title],
+    )?;
+    if updated == 0 {
+        tx.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![&title, &body.content, id],
+        )?;
+    }
+    revisions::record(&tx, id, &request_author(&req), &format!("section:{}", title))?;
+    tx.commit()?;
+
+    info!("Admin wrote section '{}' of command {}", title, id + OVERLAY_ID_OFFSET);
+    Ok(ok(overlay_command_detail(&overlay, id)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/commands/{id}/sections/{title}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Command id; upstream ids are copied to the overlay before the write"), ("title" = String, Path, description = "Section title, e.g. `EXAMPLES`")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Command without the section", body = ApiResponse<CommandDetail>),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command or section not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_section(
+    req: HttpRequest,
+    path: web::Path<(i64, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let (public_id, title) = path.into_inner();
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_command_id(&base, &tx, public_id)?;
+    // 章节不存在时事务回滚，上游命令不会留下副本
+    let removed = tx.execute(
+        "DELETE FROM CommandSection WHERE command_id = ?1 AND title = ?2",
+        params![id, &title],
+    )?;
+    if removed == 0 {
+        return Err(AppError::NotFound(format!("Section '{}' not found", title)));
+    }
+    revisions::record(&tx, id, &request_author(&req), &format!("delete-section:{}", title))?;
+    tx.commit()?;
+
+    info!("Admin deleted section '{}' of command {}", title, id + OVERLAY_ID_OFFSET);
+    Ok(ok(overlay_command_detail(&overlay, id)?))
+}
+
+// 分类
+
+fn overlay_category(id: i64, input: &CategoryInput) -> BasicCategory {
+    BasicCategory {
+        id: id + OVERLAY_ID_OFFSET,
+        title: input.title.clone(),
+        position: input.position,
+        description: category_description(&input.title),
+        icon: None,
+        source: Source::Overlay,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/categories",
+    tag = "admin",
+    request_body = CategoryInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The new overlay category", body = ApiResponse<BasicCategory>),
+        (status = 400, description = "Invalid field, or the title is already taken in the overlay (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn create_category(
+    req: HttpRequest,
+    body: web::Json<CategoryInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_unique(&overlay, "BasicCategory", "title", &body.title, None, "Category")?;
+    overlay.execute(
+        "INSERT INTO BasicCategory (position, title) VALUES (?1, ?2)",
+        params![body.position, &body.title],
+    )?;
+    let id = overlay.last_insert_rowid();
+
+    info!("Admin created category '{}'", body.title);
+    Ok(ok(overlay_category(id, &body)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/categories/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Category id; upstream ids are copied to the overlay before the write")),
+    request_body = CategoryInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The updated overlay category", body = ApiResponse<BasicCategory>),
+        (status = 400, description = "Invalid field, a taken title, or a rename of a category shadowing an upstream one (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Category not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn update_category(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<CategoryInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_category_id(&base, &tx, *path)?;
+    keep_shadow_name(&base, &tx, "BasicCategory", "title", id, &body.title, "Category")?;
+    ensure_unique(&tx, "BasicCategory", "title", &body.title, Some(id), "Category")?;
+    tx.execute(
+        "UPDATE BasicCategory SET position = ?1, title = ?2 WHERE id = ?3",
+        params![body.position, &body.title, id],
+    )?;
+    tx.commit()?;
+
+    info!("Admin updated category '{}'", body.title);
+    Ok(ok(overlay_category(id, &body)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/categories/{id}",
+    tag = "admin",
+    description = "Deletes an overlay category with its groups and one-liners. Upstream categories are read-only and cannot be deleted; deleting the overlay copy of an edited upstream category makes the upstream one visible again.",
+    params(("id" = i64, Path, description = "Overlay category id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Category deleted", body = ApiResponse<String>),
+        (status = 400, description = "Upstream id; upstream entries are read-only (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Category not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_category(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let id = overlay_only(*path, "Category")?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_exists(&overlay, "BasicCategory", id, "Category")?;
+    let tx = overlay.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM BasicCommand WHERE group_id IN (SELECT id FROM BasicGroup WHERE category_id = ?1)",
+        params![id],
+    )?;
+    tx.execute("DELETE FROM BasicGroup WHERE category_id = ?1", params![id])?;
+    tx.execute("DELETE FROM BasicCategory WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    info!("Admin deleted category {}", *path);
+    Ok(deleted("Category", *path))
+}
+
+// 分组
+
+// category_id 为已解析的覆盖库分类 id
+fn write_group(overlay: &Connection, id: Option<i64>, category_id: i64, input: &GroupInput) -> Result<BasicGroup, AppError> {
+    let id = match id {
+        Some(id) => {
+            overlay.execute(
+                "UPDATE BasicGroup SET position = ?1, description = ?2, category_id = ?3 WHERE id = ?4",
+                params![input.position, &input.description, category_id, id],
+            )?;
+            id
+        }
+        None => {
+            overlay.execute(
+                "INSERT INTO BasicGroup (position, description, category_id) VALUES (?1, ?2, ?3)",
+                params![input.position, &input.description, category_id],
+            )?;
+            overlay.last_insert_rowid()
+        }
+    };
+
+    Ok(BasicGroup {
+        id: id + OVERLAY_ID_OFFSET,
+        category_id: category_id + OVERLAY_ID_OFFSET,
+        position: input.position,
+        description: input.description.clone(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/groups",
+    tag = "admin",
+    request_body = GroupInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The new overlay group; an upstream category is copied first", body = ApiResponse<BasicGroup>),
+        (status = 400, description = "Invalid field (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Category not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn create_group(
+    req: HttpRequest,
+    body: web::Json<GroupInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let category_id = writable_category_id(&base, &tx, body.category_id)?;
+    let group = write_group(&tx, None, category_id, &body)?;
+    tx.commit()?;
+
+    info!("Admin created group {}", group.id);
+    Ok(ok(group))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/groups/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Group id; upstream ids are copied to the overlay before the write")),
+    request_body = GroupInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The updated overlay group", body = ApiResponse<BasicGroup>),
+        (status = 400, description = "Invalid field (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Group or category not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn update_group(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<GroupInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_group_id(&base, &tx, *path)?;
+    let category_id = writable_category_id(&base, &tx, body.category_id)?;
+    let group = write_group(&tx, Some(id), category_id, &body)?;
+    tx.commit()?;
+
+    info!("Admin updated group {}", group.id);
+    Ok(ok(group))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/groups/{id}",
+    tag = "admin",
+    description = "Deletes an overlay group with its one-liners. Upstream groups are read-only and cannot be deleted; delete the overlay copy of their category to bring the upstream category back.",
+    params(("id" = i64, Path, description = "Overlay group id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Group deleted", body = ApiResponse<String>),
+        (status = 400, description = "Upstream id; upstream entries are read-only (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Group not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_group(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let id = overlay_only(*path, "Group")?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_exists(&overlay, "BasicGroup", id, "Group")?;
+    let tx = overlay.unchecked_transaction()?;
+    tx.execute("DELETE FROM BasicCommand WHERE group_id = ?1", params![id])?;
+    tx.execute("DELETE FROM BasicGroup WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    info!("Admin deleted group {}", *path);
+    Ok(deleted("Group", *path))
+}
+
+// 分类下的一行命令
+
+// group_id 为已解析的覆盖库分组 id
+fn write_basic_command(
+    overlay: &Connection,
+    id: Option<i64>,
+    group_id: i64,
+    input: &BasicCommandInput,
+) -> Result<BasicCommand, AppError> {
+    let id = match id {
+        Some(id) => {
+            overlay.execute(
+                "UPDATE BasicCommand SET command = ?1, mans = ?2, group_id = ?3 WHERE id = ?4",
+                params![&input.command, &input.mans, group_id, id],
+            )?;
+            id
+        }
+        None => {
+            overlay.execute(
+                "INSERT INTO BasicCommand (command, mans, group_id) VALUES (?1, ?2, ?3)",
+                params![&input.command, &input.mans, group_id],
+            )?;
+            overlay.last_insert_rowid()
+        }
+    };
+
+    Ok(BasicCommand {
+        id: id + OVERLAY_ID_OFFSET,
+        group_id: group_id + OVERLAY_ID_OFFSET,
+        command: input.command.clone(),
+        mans: input.mans.clone(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/basic-commands",
+    tag = "admin",
+    request_body = BasicCommandInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The new overlay one-liner; an upstream group is copied first", body = ApiResponse<BasicCommand>),
+        (status = 400, description = "Invalid field (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Group not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn create_basic_command(
+    req: HttpRequest,
+    body: web::Json<BasicCommandInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let group_id = writable_group_id(&base, &tx, body.group_id)?;
+    let command = write_basic_command(&tx, None, group_id, &body)?;
+    tx.commit()?;
+
+    info!("Admin created basic command {}", command.id);
+    Ok(ok(command))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/basic-commands/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "One-liner id; upstream ids are copied to the overlay before the write")),
+    request_body = BasicCommandInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The updated overlay one-liner", body = ApiResponse<BasicCommand>),
+        (status = 400, description = "Invalid field (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "One-liner or group not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn update_basic_command(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<BasicCommandInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_basic_command_id(&base, &tx, *path)?;
+    let group_id = writable_group_id(&base, &tx, body.group_id)?;
+    let command = write_basic_command(&tx, Some(id), group_id, &body)?;
+    tx.commit()?;
+
+    info!("Admin updated basic command {}", command.id);
+    Ok(ok(command))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/basic-commands/{id}",
+    tag = "admin",
+    description = "Deletes an overlay one-liner. Upstream one-liners are read-only and cannot be deleted; delete the overlay copy of their category to bring the upstream category back.",
+    params(("id" = i64, Path, description = "Overlay basic command id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Basic command deleted", body = ApiResponse<String>),
+        (status = 400, description = "Upstream id; upstream entries are read-only (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Basic command not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_basic_command(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let id = overlay_only(*path, "Basic command")?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_exists(&overlay, "BasicCommand", id, "Basic command")?;
+    let tx = overlay.unchecked_transaction()?;
+    tx.execute("DELETE FROM BasicCommand WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    info!("Admin deleted basic command {}", *path);
+    Ok(deleted("Basic command", *path))
+}
+
+// 提示
+
+fn write_tip(tx: &Transaction, id: Option<i64>, input: TipInput) -> Result<Tip, AppError> {
+    let id = match id {
+        Some(id) => {
+            tx.execute(
+                "UPDATE Tip SET title = ?1, position = ?2 WHERE id = ?3",
+                params![&input.title, input.position, id],
+            )?;
+            tx.execute("DELETE FROM TipSection WHERE tip_id = ?1", params![id])?;
+            id
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO Tip (title, position) VALUES (?1, ?2)",
+                params![&input.title, input.position],
+            )?;
+            tx.last_insert_rowid()
+        }
+    };
+    for (position, section) in input.sections.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO TipSection (tip_id, position, type, data1, data2, extra) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, position as i64, section.section_type, &section.data1, &section.data2, &section.extra],
+        )?;
+    }
+
+    Ok(Tip {
+        id: id + OVERLAY_ID_OFFSET,
+        title: input.title,
+        sections: input.sections,
+        source: Source::Overlay,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/tips",
+    tag = "admin",
+    request_body = TipInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The new overlay tip", body = ApiResponse<Tip>),
+        (status = 400, description = "Invalid field, or the title is already taken in the overlay (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn create_tip(
+    req: HttpRequest,
+    body: web::Json<TipInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_unique(&overlay, "Tip", "title", &body.title, None, "Tip")?;
+    let tx = overlay.unchecked_transaction()?;
+    let tip = write_tip(&tx, None, body.into_inner())?;
+    tx.commit()?;
+
+    info!("Admin created tip {}", tip.id);
+    Ok(ok(tip))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/tips/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "Tip id; upstream ids are copied to the overlay before the write")),
+    request_body = TipInput,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The updated overlay tip", body = ApiResponse<Tip>),
+        (status = 400, description = "Invalid field, a taken title, or a rename of a tip shadowing an upstream one (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Tip not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn update_tip(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<TipInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    body.validate()?;
+
+    let (base, overlay) = lock_both(&data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = writable_tip_id(&base, &tx, *path)?;
+    keep_shadow_name(&base, &tx, "Tip", "title", id, &body.title, "Tip")?;
+    ensure_unique(&tx, "Tip", "title", &body.title, Some(id), "Tip")?;
+    let tip = write_tip(&tx, Some(id), body.into_inner())?;
+    tx.commit()?;
+
+    info!("Admin updated tip {}", tip.id);
+    Ok(ok(tip))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/tips/{id}",
+    tag = "admin",
+    description = "Deletes an overlay tip. Upstream tips are read-only and cannot be deleted; deleting the overlay copy of an edited upstream tip makes the upstream one visible again.",
+    params(("id" = i64, Path, description = "Overlay tip id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Tip deleted", body = ApiResponse<String>),
+        (status = 400, description = "Upstream id; upstream entries are read-only (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Tip not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error, or no overlay database is configured", body = ErrorResponse),
+    )
+)]
+pub async fn delete_tip(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let id = overlay_only(*path, "Tip")?;
+
+    let overlay = lock_writable(&data)?;
+    ensure_exists(&overlay, "Tip", id, "Tip")?;
+    let tx = overlay.unchecked_transaction()?;
+    tx.execute("DELETE FROM TipSection WHERE tip_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Tip WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    info!("Admin deleted tip {}", *path);
+    Ok(deleted("Tip", *path))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .route("/commands", web::post().to(create_command))
+            .route("/commands/{id}", web::put().to(update_command))
+            .route("/commands/{id}", web::delete().to(delete_command))
+            .route("/commands/{id}/sections/{title}", web::put().to(put_section))
+            .route("/commands/{id}/sections/{title}", web::delete().to(delete_section))
+            .route("/commands/{id}/revisions/{rev}/revert", web::post().to(revisions::revert_revision))
+            .route("/suggestions", web::get().to(suggestions::list_suggestions))
+            .route("/suggestions/{id}/approve", web::post().to(suggestions::approve_suggestion))
+            .route("/suggestions/{id}/reject", web::post().to(suggestions::reject_suggestion))
+            .route("/categories", web::post().to(create_category))
+            .route("/categories/{id}", web::put().to(update_category))
+            .route("/categories/{id}", web::delete().to(delete_category))
+            .route("/groups", web::post().to(create_group))
+            .route("/groups/{id}", web::put().to(update_group))
+            .route("/groups/{id}", web::delete().to(delete_group))
+            .route("/basic-commands", web::post().to(create_basic_command))
+            .route("/basic-commands/{id}", web::put().to(update_basic_command))
+            .route("/basic-commands/{id}", web::delete().to(delete_basic_command))
+            .route("/tips", web::post().to(create_tip))
+            .route("/tips/{id}", web::put().to(update_tip))
+            .route("/tips/{id}", web::delete().to(delete_tip))
+            .route("/reload", web::post().to(catalog::reload)),
+    );
+}
@@ -0,0 +1,234 @@
+// 前端静态资源
+//
+// index.html 与 stylesheets、scripts、images 下的文件在编译时嵌入二进制，
+// 不依赖运行时的工作目录。启动时页面和样式表中对其他资源的引用被改写为
+// 带内容哈希的 `?v=`：版本匹配的请求返回一年的 immutable 缓存，其余请求
+// 返回 no-cache 与 ETag。嵌入的文本资源在启动时预先压缩，各编码共用一个弱 ETag。设置
+// features.assets_dir 时每次请求都从该目录读取且不改写引用，便于开发时
+// 修改前端后刷新即可看到效果。
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::{guard, web, HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+
+use crate::compression::Precompressed;
+use crate::http_cache::{hex, weak_eq};
+use crate::AppError;
+
+pub const INDEX: &str = "index.html";
+
+// 资源所在的子目录，与 URL 前缀一致
+pub const DIRS: &[&str] = &["stylesheets", "scripts", "images"];
+
+// 新增前端文件时需加入此表；测试会检查目录中的文件都已嵌入
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("index.html", include_bytes!("index.html")),
+    ("stylesheets/base.css", include_bytes!("stylesheets/base.css")),
+    ("stylesheets/theme-variables.css", include_bytes!("stylesheets/theme-variables.css")),
+    ("scripts/copy.js", include_bytes!("scripts/copy.js")),
+    ("scripts/man.js", include_bytes!("scripts/man.js")),
+    ("scripts/search.js", include_bytes!("scripts/search.js")),
+    ("images/icon-search.svg", include_bytes!("images/icon-search.svg")),
+];
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("ico", "image/x-icon"),
+    ("webp", "image/webp"),
+    ("woff2", "font/woff2"),
+    ("txt", "text/plain; charset=utf-8"),
+];
+
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+
+pub fn content_type(path: &str) -> &'static str {
+    let ext = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, mime)| *mime)
+        .unwrap_or("application/octet-stream")
+}
+
+// 图片等已压缩的格式不再压缩
+fn compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/") || matches!(content_type, "image/svg+xml" | "application/json")
+}
+
+// 会引用其他资源、需要改写的文件
+fn references_assets(path: &str) -> bool {
+    path.ends_with(".html") || path.ends_with(".css")
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub body: Precompressed,
+    pub content_type: &'static str,
+    pub hash: String,
+}
+
+impl Asset {
+    fn new(path: &str, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+        let hash = hex(&Sha256::digest(&body))[..16].to_string();
+        Self { body: Precompressed::plain(body), content_type: content_type(path), hash }
+    }
+
+    fn precompressed(path: &str, body: impl Into<Bytes>) -> Self {
+        let asset = Self::new(path, body);
+        if !compressible(asset.content_type) {
+            return asset;
+        }
+        Self { body: Precompressed::new(asset.body.identity().clone()), ..asset }
+    }
+}
+
+// 将 `"/path"`、`(/path)` 以及已带 `?v=...` 的引用改写为当前内容哈希
+pub fn rewrite(text: &str, versions: &HashMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for (path, hash) in versions {
+        let url = format!("/{}", path);
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        while let Some(at) = rest.find(&url) {
+            let (before, after) = (&rest[..at], &rest[at + url.len()..]);
+            out.push_str(before);
+            out.push_str(&url);
+            rest = after;
+            let quoted = before.ends_with(['"', '\'', '(']);
+            if !quoted || !after.starts_with(['?', '"', '\'', ')']) {
+                continue;
+            }
+            if let Some(version) = after.strip_prefix("?v=") {
+                let end = version.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(version.len());
+                rest = &version[end..];
+            } else if after.starts_with('?') {
+                continue;
+            }
+            out.push_str("?v=");
+            out.push_str(hash);
+        }
+        out.push_str(rest);
+        text = out;
+    }
+    text
+}
+
+// 先处理不引用其他资源的文件，再处理样式表，最后处理页面，
+// 这样图片变化会改变样式表的哈希，样式表变化会改变页面
+fn build(files: &[(&str, &[u8])]) -> HashMap<String, Asset> {
+    let mut files = files.to_vec();
+    files.sort_by_key(|(path, _)| (path.ends_with(".html"), path.ends_with(".css")));
+
+    let mut assets: HashMap<String, Asset> = HashMap::new();
+    for (path, body) in files {
+        let asset = match std::str::from_utf8(body) {
+            Ok(text) if references_assets(path) => {
+                let versions = assets.iter().map(|(path, asset)| (path.clone(), asset.hash.clone())).collect();
+                Asset::precompressed(path, rewrite(text, &versions))
+            }
+            _ => Asset::precompressed(path, Bytes::copy_from_slice(body)),
+        };
+        assets.insert(path.to_string(), asset);
+    }
+    assets
+}
+
+// 只接受 DIRS 下不含 `..` 和隐藏文件的相对路径
+fn safe_path(path: &str) -> bool {
+    let mut components = Path::new(path).components();
+    let in_dir = matches!(components.next(), Some(Component::Normal(dir)) if DIRS.iter().any(|d| dir == *d));
+    let rest: Vec<Component> = components.collect();
+    in_dir
+        && !rest.is_empty()
+        && rest.iter().all(|c| matches!(c, Component::Normal(name) if !name.to_string_lossy().starts_with('.')))
+}
+
+pub struct Assets {
+    dir: Option<PathBuf>,
+    embedded: HashMap<String, Asset>,
+}
+
+impl Assets {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir, embedded: build(EMBEDDED) }
+    }
+
+    pub fn embedded_paths() -> impl Iterator<Item = &'static str> {
+        EMBEDDED.iter().map(|(path, _)| *path)
+    }
+
+    // path 相对于资源根目录，例如 "stylesheets/base.css"
+    pub fn get(&self, path: &str) -> Result<Option<Asset>, AppError> {
+        if path != INDEX && !safe_path(path) {
+            return Ok(None);
+        }
+        let Some(dir) = &self.dir else {
+            return Ok(self.embedded.get(path).cloned());
+        };
+        match std::fs::read(dir.join(path)) {
+            Ok(body) => Ok(Some(Asset::new(path, body))),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound | std::io::ErrorKind::IsADirectory) => Ok(None),
+            Err(e) => Err(AppError::InternalError(format!("Cannot read asset {}: {}", path, e))),
+        }
+    }
+
+    fn cache_control(&self, path: &str, asset: &Asset, version: Option<&str>) -> &'static str {
+        match version {
+            Some(version) if self.dir.is_none() && path != INDEX && version == asset.hash => IMMUTABLE,
+            _ => REVALIDATE,
+        }
+    }
+}
+
+fn query_version(req: &HttpRequest) -> Option<&str> {
+    req.query_string().split('&').find_map(|pair| pair.strip_prefix("v="))
+}
+
+fn matches_etag(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|value| value.split(',').map(str::trim).any(|tag| tag == "*" || weak_eq(tag, etag)))
+}
+
+pub async fn serve(req: HttpRequest, assets: web::Data<Assets>) -> Result<HttpResponse, AppError> {
+    let path = match req.path().trim_start_matches('/') {
+        "" => INDEX,
+        path => path,
+    };
+    let Some(asset) = assets.get(path)? else {
+        return Err(AppError::NotFound(format!("asset /{}", path)));
+    };
+
+    let etag = format!("W/\"{}\"", asset.hash);
+    let cache_control = assets.cache_control(path, &asset, query_version(&req));
+    let mut res = if matches_etag(&req, &etag) {
+        HttpResponse::NotModified().finish()
+    } else {
+        asset.body.respond(&req, HttpResponse::Ok().content_type(asset.content_type))
+    };
+    let headers = res.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    Ok(res)
+}
+
+// 前端页面与静态资源路由
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let get = || web::route().guard(guard::Any(guard::Get()).or(guard::Head())).to(serve);
+    cfg.service(web::resource("/").route(get()))
+        .service(web::resource(format!("/{{dir:{}}}/{{path:.+}}", DIRS.join("|"))).route(get()));
+}
@@ -0,0 +1,437 @@
+// 预加载的只读目录快照
+//
+// 启用 PRELOAD_CATALOG 后，启动时把命令、章节、分类和提示全部读入内存，
+// 并建立按 id、名称和名称前缀的索引。读取端点直接使用快照，不访问 SQLite，
+// 也不争用数据库 Mutex。快照记录构建时的内容写入代数（见 content 模块），
+// 代数变化后下一次读取时重新构建并原子替换；POST /admin/reload 可强制重新加载。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use arc_swap::ArcSwap;
+use log::{debug, info, warn};
+use rand::seq::IndexedRandom;
+use rusqlite::Connection;
+
+use crate::admin::require_admin;
+use crate::overlay::{self, Source, OVERLAY_ID_OFFSET};
+use crate::suggestions;
+use crate::{
+    compute_stats, load_tip_sections, merged_basic_categories, query_commands, resolve_category_commands,
+    search_relevance, ApiResponse, AppError, AppState, AppStats, BasicCategory, Command, CommandDetail,
+    CommandSection, ErrorResponse, Tip,
+};
+
+// 热门命令的候选分类（与 SQL 版本一致）
+const POPULAR_CATEGORIES: &[i64] = &[1, 3, 5, 10];
+
+pub struct Catalog {
+    generation: u64,
+    // 合并后的命令，按名称排序；summaries 与 details 一一对应
+    details: Vec<CommandDetail>,
+    summaries: Vec<Command>,
+    // 小写名称与描述，用于不区分大小写的搜索
+    lowered: Vec<(String, String)>,
+    // API id -> 下标；被遮蔽的上游 id 指向覆盖库中的同名命令
+    by_id: HashMap<i64, usize>,
+    by_name: HashMap<String, usize>,
+    // (小写名称, 下标)，按小写名称排序，用于前缀查找
+    prefixes: Vec<(String, usize)>,
+    popular: Vec<usize>,
+    categories: Vec<BasicCategory>,
+    category_commands: HashMap<String, Vec<Command>>,
+    tips: Vec<Tip>,
+    stats: AppStats,
+}
+
+impl Catalog {
+    fn load(data: &AppState, generation: u64) -> Result<Self, AppError> {
+        let (base, base_tips) = {
+            let conn = data.lock_db()?;
+            (load_commands(&conn)?, load_tips(&conn)?)
+        };
+        let (overlay, examples, overlay_tips) = match data.lock_overlay()? {
+            Some(conn) => {
+                let commands = if overlay::table_exists(&conn, "Command")? { load_commands(&conn)? } else { vec![] };
+                let tips = if overlay::table_exists(&conn, "Tip")? { load_tips(&conn)? } else { vec![] };
+                (commands, suggestions::all_examples(&conn)?, tips)
+            }
+            None => (vec![], HashMap::new(), vec![]),
+        };
+
+        // 覆盖库中同名命令的最小 id 遮蔽上游命令
+        let mut shadows: HashMap<String, i64> = HashMap::new();
+        for detail in &overlay {
+            shadows.entry(detail.name.clone()).or_insert(detail.id + OVERLAY_ID_OFFSET);
+        }
+
+        let mut shadowed_ids = Vec::new();
+        let mut details: Vec<CommandDetail> = base
+            .into_iter()
+            .filter(|detail| match shadows.get(&detail.name) {
+                Some(&shadow) => {
+                    shadowed_ids.push((detail.id, shadow));
+                    false
+                }
+                None => true,
+            })
+            .chain(overlay.into_iter().map(overlay::mark_detail))
+            .collect();
+        details.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for detail in &mut details {
+            if let Some(examples) = examples.get(&detail.name) {
+                detail.examples = examples.clone();
+            }
+        }
+
+        let summaries: Vec<Command> = details
+            .iter()
+            .map(|d| Command {
+                id: d.id,
+                name: d.name.clone(),
+                category: d.category,
+                description: d.description.clone(),
+                source: d.source,
+            })
+            .collect();
+        let lowered = summaries
+            .iter()
+            .map(|c| (c.name.to_lowercase(), c.description.to_lowercase()))
+            .collect();
+
+        let mut by_id: HashMap<i64, usize> = summaries.iter().enumerate().map(|(i, c)| (c.id, i)).collect();
+        for (base_id, shadow) in shadowed_ids {
+            if let Some(&index) = by_id.get(&shadow) {
+                by_id.insert(base_id, index);
+            }
+        }
+
+        // 按名称查找时覆盖库优先，其次为最小 id
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        let mut order: Vec<usize> = (0..summaries.len()).collect();
+        order.sort_by_key(|&i| (summaries[i].source != Source::Overlay, summaries[i].id));
+        for i in order {
+            by_name.entry(summaries[i].name.clone()).or_insert(i);
+        }
+
+        let mut prefixes: Vec<(String, usize)> =
+            summaries.iter().enumerate().map(|(i, c)| (c.name.to_lowercase(), i)).collect();
+        prefixes.sort();
+
+        let popular = summaries
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| POPULAR_CATEGORIES.contains(&c.category))
+            .map(|(i, _)| i)
+            .collect();
+
+        let categories = merged_basic_categories(data)?;
+        let mut category_commands = HashMap::new();
+        for category in &categories {
+            if let Some(commands) = resolve_category_commands(data, &category.title)? {
+                category_commands.insert(category.title.clone(), commands);
+            }
+        }
+
+        // 覆盖库中的同名提示遮蔽上游提示
+        let shadowed_tips: HashSet<&str> = overlay_tips.iter().map(|tip| tip.title.as_str()).collect();
+        let base_tips: Vec<Tip> = base_tips.into_iter().filter(|tip| !shadowed_tips.contains(tip.title.as_str())).collect();
+        let tips = base_tips
+            .into_iter()
+            .chain(overlay_tips.into_iter().map(|mut tip| {
+                tip.id += OVERLAY_ID_OFFSET;
+                tip.source = Source::Overlay;
+                tip
+            }))
+            .collect();
+
+        let stats = compute_stats(data)?;
+
+        Ok(Self {
+            generation,
+            details,
+            summaries,
+            lowered,
+            by_id,
+            by_name,
+            prefixes,
+            popular,
+            categories,
+            category_commands,
+            tips,
+            stats,
+        })
+    }
+
+    pub fn stats(&self) -> &AppStats {
+        &self.stats
+    }
+
+    pub fn categories(&self) -> &[BasicCategory] {
+        &self.categories
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.summaries
+    }
+
+    pub fn detail(&self, public_id: i64) -> Option<&CommandDetail> {
+        self.by_id.get(&public_id).map(|&i| &self.details[i])
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&Command> {
+        self.by_name.get(name).map(|&i| &self.summaries[i])
+    }
+
+    pub fn category_commands(&self, title: &str) -> Option<&[Command]> {
+        self.category_commands.get(title).map(Vec::as_slice)
+    }
+
+    pub fn random_tip(&self) -> Option<&Tip> {
+        self.tips.choose(&mut rand::rng())
+    }
+
+    pub fn popular(&self, limit: usize) -> Vec<&Command> {
+        self.popular
+            .choose_multiple(&mut rand::rng(), limit)
+            .map(|&i| &self.summaries[i])
+            .collect()
+    }
+
+    // 名称以 prefix 开头（不区分大小写）的去重名称，按名称排序
+    pub fn suggestions(&self, prefix: &str, limit: usize) -> Vec<&str> {
+        let prefix = prefix.to_lowercase();
+        let start = self.prefixes.partition_point(|(name, _)| name.as_str() < prefix.as_str());
+        let mut names: Vec<&str> = self.prefixes[start..]
+            .iter()
+            .take_while(|(name, _)| name.starts_with(&prefix))
+            .map(|&(_, i)| self.summaries[i].name.as_str())
+            .collect();
+        names.sort();
+        names.dedup();
+        names.truncate(limit);
+        names
+    }
+
+    // 与 search_in 相同的相关性排序
+    pub fn search(&self, term: &str, category: Option<&String>, limit: i64) -> Vec<&Command> {
+        let category = match category {
+            Some(category) => match category.trim().parse::<i64>() {
+                Ok(category) => Some(category),
+                Err(_) => return vec![],
+            },
+            None => None,
+        };
+        let lowered_term = term.to_lowercase();
+
+        let mut results: Vec<(&Command, i64)> = self
+            .summaries
+            .iter()
+            .zip(&self.lowered)
+            .filter(|(cmd, _)| category.is_none_or(|c| cmd.category == c))
+            .filter(|(_, (name, description))| name.contains(&lowered_term) || description.contains(&lowered_term))
+            .map(|(cmd, _)| (cmd, search_relevance(&cmd.name, &cmd.description, term)))
+            .collect();
+        results.sort_by(|(a, ra), (b, rb)| rb.cmp(ra).then_with(|| a.name.cmp(&b.name)));
+        results.truncate(limit.max(0) as usize);
+        results.into_iter().map(|(cmd, _)| cmd).collect()
+    }
+}
+
+// 一次读取全部命令及其章节（排除 NAME 章节）
+fn load_commands(conn: &Connection) -> Result<Vec<CommandDetail>, AppError> {
+    let commands = query_commands(conn, "SELECT id, name, category, description FROM Command ORDER BY id", [], "catalog commands")?;
+
+    let mut sections: HashMap<i64, Vec<CommandSection>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT command_id, title, content FROM CommandSection WHERE title != 'NAME' ORDER BY id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, CommandSection { title: row.get(1)?, content: row.get(2)? }))
+    })?;
+    for row in rows {
+        let (command_id, section) = row?;
+        sections.entry(command_id).or_default().push(section);
+    }
+
+    Ok(commands
+        .into_iter()
+        .map(|cmd| {
+            let sections = sections.remove(&cmd.id).unwrap_or_default();
+            let tldr = sections.iter().find(|s| s.title == "TLDR").map(|s| s.content.clone());
+            CommandDetail {
+                id: cmd.id,
+                name: cmd.name,
+                category: cmd.category,
+                description: cmd.description,
+                sections,
+                tldr,
+                source: Source::Base,
+                examples: vec![],
+                notes: vec![],
+            }
+        })
+        .collect())
+}
+
+fn load_tips(conn: &Connection) -> Result<Vec<Tip>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, title FROM Tip ORDER BY id")?;
+    let tips: Vec<(i64, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    tips.into_iter()
+        .map(|(id, title)| {
+            Ok(Tip {
+                id,
+                title,
+                sections: load_tip_sections(conn, id)?,
+                source: Source::Base,
+            })
+        })
+        .collect()
+}
+
+// 当前快照
+pub struct CatalogHandle {
+    current: ArcSwap<Catalog>,
+    rebuild: Mutex<()>,
+}
+
+impl CatalogHandle {
+    pub fn load(data: &AppState) -> Result<Self, AppError> {
+        let catalog = Catalog::load(data, data.content.generation())?;
+        info!("Catalog preloaded: {} commands, {} tips", catalog.summaries.len(), catalog.tips.len());
+
+        Ok(Self {
+            current: ArcSwap::from_pointee(catalog),
+            rebuild: Mutex::new(()),
+        })
+    }
+
+    // 当前快照；过期时重新构建。另一个请求正在构建时等待其完成，避免在新的内容版本下
+    // 返回旧快照（响应缓存和 ETag 都以新版本为键）。构建失败时返回 None，由调用方回退到 SQL
+    pub fn snapshot(&self, data: &AppState) -> Option<Arc<Catalog>> {
+        let current = self.current.load_full();
+        if current.generation == data.content.generation() {
+            return Some(current);
+        }
+
+        let _guard = self.rebuild.lock().unwrap_or_else(|e| e.into_inner());
+        let current = self.current.load_full();
+        if current.generation == data.content.generation() {
+            return Some(current);
+        }
+        match self.reload(data) {
+            Ok(catalog) => Some(catalog),
+            Err(e) => {
+                warn!("Failed to rebuild catalog, falling back to the database: {}", e);
+                None
+            }
+        }
+    }
+
+    fn reload(&self, data: &AppState) -> Result<Arc<Catalog>, AppError> {
+        let generation = data.content.generation();
+        let catalog = Arc::new(Catalog::load(data, generation)?);
+        self.current.store(catalog.clone());
+        debug!("Catalog rebuilt at generation {}", generation);
+        Ok(catalog)
+    }
+}
+
+// POST /api/admin/reload：重新读取数据库并替换快照（例如上游 database.db 被替换后）
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/reload",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Statistics of the freshly loaded catalog", body = ApiResponse<AppStats>),
+        (status = 400, description = "Catalog preloading is not enabled (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn reload(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+
+    let handle = data
+        .catalog
+        .as_ref()
+        .ok_or_else(|| AppError::InvalidInput("Catalog preloading is not enabled".to_string()))?;
+    let catalog = {
+        let _guard = handle.rebuild.lock().map_err(|_| AppError::InternalError("Catalog lock error".to_string()))?;
+        // 预加载时内容版本不读取 data_version，增加写入代数使 ETag 与响应缓存失效
+        data.content.bump();
+        handle.reload(&data)?
+    };
+
+    info!("Admin reloaded catalog: {} commands", catalog.summaries.len());
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(catalog.stats()),
+        message: Some("Catalog reloaded".to_string()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::tests::test_app_state;
+
+    #[test]
+    fn test_content_version_does_not_lock_the_databases() {
+        let data = Arc::new(test_app_state("secret").with_catalog().unwrap());
+        let version = data.content_version().unwrap();
+
+        // 两个连接都被占用时仍能立即得到内容版本
+        {
+            let _db = data.lock_db().unwrap();
+            let _overlay = data.lock_overlay().unwrap();
+            let (sender, receiver) = mpsc::channel();
+            let reader = data.clone();
+            thread::spawn(move || sender.send(reader.content_version().unwrap()).unwrap());
+            assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), version);
+        }
+
+        data.content.bump();
+        assert_ne!(data.content_version().unwrap(), version);
+    }
+
+    #[test]
+    fn test_concurrent_readers_wait_for_rebuild_after_write() {
+        let data = test_app_state("secret").with_catalog().unwrap();
+        let handle = data.catalog.as_ref().unwrap();
+        assert!(data.catalog().unwrap().detail(1).unwrap().examples.is_empty());
+
+        // 模拟另一个请求正在重新构建快照
+        let guard = handle.rebuild.lock().unwrap();
+        data.lock_overlay()
+            .unwrap()
+            .unwrap()
+            .execute(
+                "INSERT INTO CommandExample (command_name, description, command, author) VALUES ('grep', 'Count', 'grep -c x', 'ann')",
+                [],
+            )
+            .unwrap();
+
+        thread::scope(|scope| {
+            let readers: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| data.catalog().unwrap().detail(1).unwrap().examples.len()))
+                .collect();
+
+            // 读取端等待构建完成，而不是在新的内容版本下返回旧快照
+            thread::sleep(Duration::from_millis(50));
+            assert!(readers.iter().all(|reader| !reader.is_finished()));
+            drop(guard);
+
+            for reader in readers {
+                assert_eq!(reader.join().unwrap(), 1);
+            }
+        });
+    }
+}
@@ -0,0 +1,811 @@
+// 分层配置
+//
+// 优先级从低到高：内置默认值 < TOML 配置文件（--config / CONFIG_FILE）
+// < 环境变量 < 命令行参数。环境变量与命令行参数由 clap 一并解析，
+// 布尔值接受 true/false、1/0、yes/no、on/off。加载完成后统一校验，
+// 有错误时启动失败并列出全部问题；--print-config 输出生效的配置后退出。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::builder::BoolishValueParser;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{assets, cors, docset, export, http_cache, man};
+use crate::listener::{parse_mode, ListenAddr};
+use crate::logging::LogFormat;
+use crate::rate_limit::IpNet;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Cannot read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+
+    #[error("Invalid config file {path}: {source}")]
+    Parse { path: PathBuf, source: Box<toml::de::Error> },
+
+    #[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub cors: CorsConfig,
+    pub cache: CacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub logging: LoggingConfig,
+    pub features: FeatureConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    // host:port 或 unix:/path/to/socket
+    pub addr: String,
+    // 未设置时使用 actix 默认值（CPU 核数）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    // 收到停机信号后就绪检查失败、但仍接受请求的秒数，供负载均衡摘除实例
+    pub shutdown_delay: u64,
+    // 停止接受连接后等待处理中请求完成的最长秒数
+    pub shutdown_timeout: u64,
+    // Unix 套接字的八进制权限，例如 "660"；也是 listeners 中未设置时的默认值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_mode: Option<String>,
+    // addr 上启用 TLS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    // 除 addr 外的其他监听地址
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<ListenerConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerConfig {
+    pub addr: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+// PEM 格式的证书链与私钥，收到 SIGHUP 时重新加载
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub enabled: bool,
+    // 精确来源、`https://*.example.com` 形式的子域名通配或 "*"；为空时不允许跨域
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    pub headers: Vec<String>,
+    // 浏览器脚本可读取的响应头
+    pub expose_headers: Vec<String>,
+    // 不能与 "*" 来源同时使用
+    pub allow_credentials: bool,
+    // 预检结果缓存时间（秒），未设置时不发送 Access-Control-Max-Age
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    // Cache-Control max-age（秒），0 表示每次都需重新验证
+    pub max_age: u32,
+    // 按路由覆盖 max_age，键为 http_cache 中的路由名称
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub route_max_age: BTreeMap<String, u32>,
+    // 进程内响应缓存的最大条目数，0 表示关闭
+    pub response_cache_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    // 允许读取 X-Forwarded-For 的代理，IP 地址或 CIDR 网段
+    pub trusted_proxies: Vec<String>,
+    // 各路由分组的限制，分组见 rate_limit::group_for
+    #[serde(default = "GroupLimit::search")]
+    pub search: GroupLimit,
+    #[serde(default = "GroupLimit::commands")]
+    pub commands: GroupLimit,
+    #[serde(default = "GroupLimit::api")]
+    pub api: GroupLimit,
+    #[serde(default = "GroupLimit::registration")]
+    pub registration: GroupLimit,
+    #[serde(default = "GroupLimit::submissions")]
+    pub submissions: GroupLimit,
+    #[serde(default = "GroupLimit::export")]
+    pub export: GroupLimit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupLimit {
+    // 每分钟补充的请求数，0 表示不限制该分组
+    pub per_minute: u32,
+    // 允许的突发请求数（桶容量）
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    pub preload_catalog: bool,
+    // 允许匿名注册用户；关闭时 POST /users 需要管理员 token
+    pub open_registration: bool,
+    // 管理员写入 API 的 bearer token，未设置时写入 API 关闭
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
+    // 开发用：从该目录读取前端文件（index.html、stylesheets 等），而不是使用嵌入的副本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets_dir: Option<String>,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            preload_catalog: false,
+            open_registration: true,
+            admin_token: None,
+            assets_dir: None,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: "0.0.0.0:8080".to_string(),
+            workers: None,
+            shutdown_delay: 0,
+            shutdown_timeout: 30,
+            socket_mode: None,
+            tls: None,
+            listeners: vec![],
+        }
+    }
+}
+
+impl ServerConfig {
+    // 所有监听地址，addr 在最前
+    pub fn all_listeners(&self) -> Vec<ListenerConfig> {
+        let primary = ListenerConfig {
+            addr: self.addr.clone(),
+            socket_mode: None,
+            tls: self.tls.clone(),
+        };
+        std::iter::once(primary)
+            .chain(self.listeners.iter().cloned())
+            .map(|mut listener| {
+                listener.socket_mode = listener.socket_mode.or_else(|| self.socket_mode.clone());
+                listener
+            })
+            .collect()
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "database.db".to_string(),
+            overlay_path: None,
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            origins: vec![],
+            methods: ["GET", "POST", "PUT", "DELETE"].map(String::from).to_vec(),
+            headers: ["Authorization", "Content-Type", "If-None-Match", "X-Request-Id"].map(String::from).to_vec(),
+            expose_headers: ["ETag", "X-Request-Id"].map(String::from).to_vec(),
+            allow_credentials: false,
+            max_age: Some(3600),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_age: http_cache::DEFAULT_MAX_AGE,
+            route_max_age: BTreeMap::new(),
+            response_cache_size: crate::response_cache::DEFAULT_CAPACITY,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trusted_proxies: vec![],
+            search: GroupLimit::search(),
+            commands: GroupLimit::commands(),
+            api: GroupLimit::api(),
+            registration: GroupLimit::registration(),
+            submissions: GroupLimit::submissions(),
+            export: GroupLimit::export(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    // 各分组的名称与限制，名称与 rate_limit::group_for 一致
+    pub fn groups(&self) -> [(&'static str, GroupLimit); 6] {
+        [
+            ("search", self.search),
+            ("commands", self.commands),
+            ("api", self.api),
+            ("registration", self.registration),
+            ("submissions", self.submissions),
+            ("export", self.export),
+        ]
+    }
+}
+
+impl GroupLimit {
+    fn search() -> Self {
+        Self { per_minute: 60, burst: 20 }
+    }
+
+    fn commands() -> Self {
+        Self { per_minute: 120, burst: 40 }
+    }
+
+    fn api() -> Self {
+        Self { per_minute: 300, burst: 100 }
+    }
+
+    fn registration() -> Self {
+        Self { per_minute: 1, burst: 5 }
+    }
+
+    fn submissions() -> Self {
+        Self { per_minute: 2, burst: 10 }
+    }
+
+    fn export() -> Self {
+        Self { per_minute: 2, burst: 3 }
+    }
+}
+
+/// Linux Command Library web server
+#[derive(Debug, Default, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML configuration file
+    #[arg(long, env = "CONFIG_FILE", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Bind address (host:port or unix:/path)
+    #[arg(long = "bind", env = "SERVER_ADDR", value_name = "ADDR")]
+    pub server_addr: Option<String>,
+
+    /// Additional listen addresses (host:port or unix:/path), comma separated
+    #[arg(long, env = "LISTEN", value_delimiter = ',', value_name = "ADDRS")]
+    pub listen: Option<Vec<String>>,
+
+    /// PEM certificate chain enabling TLS on the bind address
+    #[arg(long, env = "TLS_CERT_FILE", value_name = "PATH")]
+    pub tls_cert: Option<String>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, env = "TLS_KEY_FILE", value_name = "PATH")]
+    pub tls_key: Option<String>,
+
+    /// Octal permissions for Unix sockets, e.g. 660
+    #[arg(long, env = "SOCKET_MODE", value_name = "MODE")]
+    pub socket_mode: Option<String>,
+
+    /// Number of worker threads
+    #[arg(long, env = "WORKERS")]
+    pub workers: Option<usize>,
+
+    /// Seconds to keep serving with readiness failing after a shutdown signal
+    #[arg(long, env = "SHUTDOWN_DELAY", value_name = "SECONDS")]
+    pub shutdown_delay: Option<u64>,
+
+    /// Seconds to wait for in-flight requests during shutdown
+    #[arg(long, env = "SHUTDOWN_TIMEOUT", value_name = "SECONDS")]
+    pub shutdown_timeout: Option<u64>,
+
+    /// Path to the SQLite database
+    #[arg(long = "database", env = "DATABASE_PATH", value_name = "PATH")]
+    pub database_path: Option<String>,
+
+    /// Writable overlay database with team commands
+    #[arg(long = "overlay-database", env = "OVERLAY_DATABASE_PATH", value_name = "PATH")]
+    pub overlay_path: Option<String>,
+
+    /// Bearer token enabling the admin write API
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// Enable CORS
+    #[arg(long, env = "ENABLE_CORS", value_parser = BoolishValueParser::new(), value_name = "BOOL")]
+    pub enable_cors: Option<bool>,
+
+    /// Allowed CORS origins, comma separated
+    #[arg(long = "cors-origins", env = "CORS_ORIGINS", value_delimiter = ',', value_name = "ORIGINS")]
+    pub cors_origins: Option<Vec<String>>,
+
+    /// Allowed CORS methods, comma separated
+    #[arg(long = "cors-methods", env = "CORS_METHODS", value_delimiter = ',', value_name = "METHODS")]
+    pub cors_methods: Option<Vec<String>>,
+
+    /// Allowed CORS request headers, comma separated
+    #[arg(long = "cors-headers", env = "CORS_HEADERS", value_delimiter = ',', value_name = "HEADERS")]
+    pub cors_headers: Option<Vec<String>>,
+
+    /// Allow credentials (cookies, Authorization) in cross-origin requests
+    #[arg(long, env = "CORS_ALLOW_CREDENTIALS", value_parser = BoolishValueParser::new(), value_name = "BOOL")]
+    pub cors_allow_credentials: Option<bool>,
+
+    /// Preflight cache time in seconds
+    #[arg(long, env = "CORS_MAX_AGE", value_name = "SECONDS")]
+    pub cors_max_age: Option<usize>,
+
+    /// Cache-Control max-age in seconds
+    #[arg(long, env = "CACHE_MAX_AGE", value_name = "SECONDS")]
+    pub cache_max_age: Option<u32>,
+
+    /// Maximum number of entries in the response cache (0 disables it)
+    #[arg(long, env = "RESPONSE_CACHE_SIZE", value_name = "ENTRIES")]
+    pub response_cache_size: Option<usize>,
+
+    /// Enable per-IP rate limiting
+    #[arg(long, env = "RATE_LIMIT", value_parser = BoolishValueParser::new(), value_name = "BOOL")]
+    pub rate_limit: Option<bool>,
+
+    /// Proxies trusted to set X-Forwarded-For (IPs or CIDRs), comma separated
+    #[arg(long, env = "TRUSTED_PROXIES", value_delimiter = ',', value_name = "ADDRS")]
+    pub trusted_proxies: Option<Vec<String>>,
+
+    /// Log format: text or json
+    #[arg(long, env = "LOG_FORMAT", value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// Preload the catalog into memory at startup
+    #[arg(long, env = "PRELOAD_CATALOG", value_parser = BoolishValueParser::new(), value_name = "BOOL")]
+    pub preload_catalog: Option<bool>,
+
+    /// Allow anyone to register a user; when false registering requires the admin token
+    #[arg(long, env = "OPEN_REGISTRATION", value_parser = BoolishValueParser::new(), value_name = "BOOL")]
+    pub open_registration: Option<bool>,
+
+    /// Serve frontend assets from this directory instead of the embedded copies (development)
+    #[arg(long, env = "ASSETS_DIR", value_name = "DIR")]
+    pub assets_dir: Option<String>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum CliCommand {
+    /// Export the library (commands with sections, categories, one-liners and tips) and exit
+    Export(ExportArgs),
+    /// Build a Dash/Zeal docset (HTML pages per command and one-liner guide plus a search index) and exit
+    Docset(DocsetArgs),
+    /// Import TLDR sections from a local tldr-pages checkout into the overlay database and exit
+    TldrImport(TldrImportArgs),
+    /// Write TLDR sections and approved team examples as tldr-pages Markdown and exit
+    TldrExport(TldrExportArgs),
+    /// Import roff man pages (plain or gzip-compressed) into the overlay database and exit
+    ManImport(ManImportArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// Output format: ndjson or csv
+    #[arg(long, default_value = "ndjson")]
+    pub format: export::Format,
+
+    /// What to export: all, commands, categories, one-liners or tips (CSV needs a single kind)
+    #[arg(long, default_value = "all")]
+    pub kind: export::Kind,
+
+    /// Write to this file instead of standard output
+    #[arg(long, short, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DocsetArgs {
+    /// Docset directory to create
+    #[arg(long, short, value_name = "PATH", default_value = docset::DEFAULT_OUTPUT)]
+    pub output: PathBuf,
+
+    /// Replace the docset if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TldrImportArgs {
+    /// tldr-pages checkout; pages/common and pages/linux are imported
+    #[arg(value_name = "DIR")]
+    pub checkout: PathBuf,
+
+    /// Create overlay commands for pages without a matching command
+    #[arg(long)]
+    pub create_missing: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TldrExportArgs {
+    /// Output directory; pages are written to <DIR>/pages/<PLATFORM>/<name>.md
+    #[arg(long, short, value_name = "DIR")]
+    pub output: PathBuf,
+
+    /// tldr-pages platform directory
+    #[arg(long, default_value = "common")]
+    pub platform: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManImportArgs {
+    /// Man page tree such as /usr/share/man, or a single section directory such as /usr/share/man/man1
+    #[arg(value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// Man sections to import, comma separated
+    #[arg(long, value_delimiter = ',', default_value = man::DEFAULT_SECTIONS)]
+    pub sections: Vec<String>,
+
+    /// Replace overlay commands with the same name and shadow upstream ones instead of skipping them
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Write the pages that failed to parse (path and reason, tab separated) to this file instead of standard error
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+}
+
+// 逗号分隔的列表去掉空白和空项
+fn list(values: &[String]) -> Vec<String> {
+    values.iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+impl Config {
+    // 按优先级合并各层配置并校验
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        let env_errors = config.apply_route_env(|key| std::env::var(key).ok());
+        config.apply_cli(cli);
+        config.check(env_errors)?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    }
+
+    // CACHE_MAX_AGE_<ROUTE> 无法用固定的参数表达，单独读取；返回无效的值
+    pub fn apply_route_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut errors = Vec::new();
+        for route in http_cache::route_names() {
+            let key = format!("CACHE_MAX_AGE_{}", route.to_uppercase());
+            if let Some(value) = var(&key) {
+                match value.trim().parse() {
+                    Ok(seconds) => {
+                        self.cache.route_max_age.insert(route.to_string(), seconds);
+                    }
+                    Err(_) => errors.push(format!("{} must be a number of seconds, got {:?}", key, value)),
+                }
+            }
+        }
+        errors
+    }
+
+    // 环境变量已由 clap 合并到 Cli 中，命令行参数优先
+    pub fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(addr) = &cli.server_addr {
+            self.server.addr = addr.clone();
+        }
+        if let Some(listen) = &cli.listen {
+            self.server.listeners = list(listen)
+                .into_iter()
+                .map(|addr| ListenerConfig { addr, socket_mode: None, tls: None })
+                .collect();
+        }
+        // 只给出其中一个时保留另一个的文件配置，缺失时由 check 报告
+        if cli.tls_cert.is_some() || cli.tls_key.is_some() {
+            let tls = self.server.tls.get_or_insert_with(TlsConfig::default);
+            if let Some(cert) = &cli.tls_cert {
+                tls.cert = cert.clone();
+            }
+            if let Some(key) = &cli.tls_key {
+                tls.key = key.clone();
+            }
+        }
+        if let Some(mode) = &cli.socket_mode {
+            self.server.socket_mode = Some(mode.clone());
+        }
+        if let Some(workers) = cli.workers {
+            self.server.workers = Some(workers);
+        }
+        if let Some(delay) = cli.shutdown_delay {
+            self.server.shutdown_delay = delay;
+        }
+        if let Some(timeout) = cli.shutdown_timeout {
+            self.server.shutdown_timeout = timeout;
+        }
+        if let Some(path) = &cli.database_path {
+            self.database.path = path.clone();
+        }
+        if let Some(path) = &cli.overlay_path {
+            self.database.overlay_path = Some(path.clone());
+        }
+        if let Some(token) = &cli.admin_token {
+            self.features.admin_token = Some(token.clone());
+        }
+        if let Some(enabled) = cli.enable_cors {
+            self.cors.enabled = enabled;
+        }
+        if let Some(origins) = &cli.cors_origins {
+            self.cors.origins = list(origins);
+        }
+        if let Some(methods) = &cli.cors_methods {
+            self.cors.methods = list(methods);
+        }
+        if let Some(headers) = &cli.cors_headers {
+            self.cors.headers = list(headers);
+        }
+        if let Some(credentials) = cli.cors_allow_credentials {
+            self.cors.allow_credentials = credentials;
+        }
+        if let Some(max_age) = cli.cors_max_age {
+            self.cors.max_age = Some(max_age);
+        }
+        if let Some(max_age) = cli.cache_max_age {
+            self.cache.max_age = max_age;
+        }
+        if let Some(size) = cli.response_cache_size {
+            self.cache.response_cache_size = size;
+        }
+        if let Some(enabled) = cli.rate_limit {
+            self.rate_limit.enabled = enabled;
+        }
+        if let Some(proxies) = &cli.trusted_proxies {
+            self.rate_limit.trusted_proxies = list(proxies);
+        }
+        if let Some(format) = cli.log_format {
+            self.logging.format = format;
+        }
+        if let Some(preload) = cli.preload_catalog {
+            self.features.preload_catalog = preload;
+        }
+        if let Some(open) = cli.open_registration {
+            self.features.open_registration = open;
+        }
+        if let Some(dir) = &cli.assets_dir {
+            self.features.assets_dir = Some(dir.clone());
+        }
+    }
+
+    // 校验合并后的配置，`errors` 为加载阶段已发现的问题
+    pub fn check(&self, mut errors: Vec<String>) -> Result<(), ConfigError> {
+        if self.server.shutdown_delay.saturating_add(self.server.shutdown_timeout) > 3600 {
+            errors.push("server.shutdown_delay plus server.shutdown_timeout must not exceed 3600 seconds".to_string());
+        }
+
+        let mut seen = Vec::new();
+        for listener in self.server.all_listeners() {
+            let addr = match ListenAddr::parse(&listener.addr) {
+                Ok(addr) => addr,
+                Err(e) => {
+                    errors.push(format!("server: {}", e));
+                    continue;
+                }
+            };
+            if seen.contains(&addr) {
+                errors.push(format!("server: {} is configured more than once", addr));
+            }
+            match &addr {
+                ListenAddr::Unix(_) if cfg!(not(unix)) => errors.push(format!("server: {} requires a Unix platform", addr)),
+                ListenAddr::Unix(_) if listener.tls.is_some() => errors.push(format!("server: TLS is not supported on {}", addr)),
+                _ => {}
+            }
+            if let Some(tls) = &listener.tls {
+                if tls.cert.trim().is_empty() {
+                    errors.push(format!("server: TLS certificate path for {} must not be empty", addr));
+                }
+                if tls.key.trim().is_empty() {
+                    errors.push(format!("server: TLS key path for {} must not be empty", addr));
+                }
+            }
+            seen.push(addr);
+        }
+        for mode in self.server.socket_mode.iter().chain(self.server.listeners.iter().filter_map(|l| l.socket_mode.as_ref())) {
+            if let Err(e) = parse_mode(mode) {
+                errors.push(format!("server: {}", e));
+            }
+        }
+        if let Some(workers) = self.server.workers
+            && !(1..=1024).contains(&workers)
+        {
+            errors.push(format!("server.workers must be between 1 and 1024, got {}", workers));
+        }
+
+        if self.database.path.trim().is_empty() {
+            errors.push("database.path must not be empty".to_string());
+        }
+        if self.database.overlay_path.as_deref().is_some_and(|p| p.trim().is_empty()) {
+            errors.push("database.overlay_path must not be empty when set".to_string());
+        }
+        if self.features.admin_token.as_deref().is_some_and(|t| t.trim().is_empty()) {
+            errors.push("features.admin_token must not be empty when set".to_string());
+        }
+        if self.features.admin_token.is_some() && self.database.overlay_path.is_none() {
+            errors.push("features.admin_token requires database.overlay_path".to_string());
+        }
+        if let Some(dir) = &self.features.assets_dir
+            && !Path::new(dir).join(assets::INDEX).is_file()
+        {
+            errors.push(format!("features.assets_dir: {} does not contain {}", dir, assets::INDEX));
+        }
+
+        errors.extend(cors::validate(&self.cors));
+
+        for proxy in &self.rate_limit.trusted_proxies {
+            if let Err(e) = proxy.parse::<IpNet>() {
+                errors.push(format!("rate_limit.trusted_proxies: {}", e));
+            }
+        }
+        for (group, limit) in self.rate_limit.groups() {
+            if limit.per_minute > 0 && limit.burst == 0 {
+                errors.push(format!("rate_limit.{}.burst must be at least 1", group));
+            }
+        }
+
+        let routes = http_cache::route_names();
+        for route in self.cache.route_max_age.keys() {
+            if !routes.contains(&route.as_str()) {
+                errors.push(format!("cache.route_max_age: unknown route {} (expected one of {})", route, routes.join(", ")));
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(errors)) }
+    }
+
+    // 用于 --print-config；隐藏 token
+    pub fn to_toml(&self) -> String {
+        let mut shown = self.clone();
+        if shown.features.admin_token.is_some() {
+            shown.features.admin_token = Some("<redacted>".to_string());
+        }
+        toml::to_string_pretty(&shown).unwrap_or_else(|e| format!("# cannot serialize configuration: {}\n", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layered_config() {
+        let path = std::env::temp_dir().join(format!("lcl-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[server]\naddr = \"127.0.0.1:9000\"\nworkers = 2\n\n[cache]\nmax_age = 60\n\n[cache.route_max_age]\nsearch = 10\n",
+        ).unwrap();
+        let mut config = Config::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // 文件中未出现的字段保留默认值
+        assert_eq!(config.server.workers, Some(2));
+        assert_eq!(config.database.path, "database.db");
+        assert!(config.cors.enabled);
+
+        // 环境变量覆盖文件，命令行参数覆盖环境变量
+        let errors = config.apply_route_env(|key| match key {
+            "CACHE_MAX_AGE_COMMAND" => Some("30".to_string()),
+            "CACHE_MAX_AGE_STATS" => Some("soon".to_string()),
+            _ => None,
+        });
+        let cli = Cli::try_parse_from(["server", "--bind", "0.0.0.0:3000", "--enable-cors", "off", "--cors-origins", "https://a.example, https://b.example"]).unwrap();
+        config.apply_cli(&cli);
+
+        assert_eq!(config.server.addr, "0.0.0.0:3000");
+        assert_eq!(config.cache.max_age, 60);
+        assert_eq!(config.cache.route_max_age.get("search"), Some(&10));
+        assert_eq!(config.cache.route_max_age.get("command"), Some(&30));
+        assert!(!config.cors.enabled);
+        assert_eq!(config.cors.origins, vec!["https://a.example", "https://b.example"]);
+
+        // 校验一次报告所有问题
+        config.features.admin_token = Some("secret".to_string());
+        config.cache.route_max_age.insert("nope".to_string(), 1);
+        let message = config.check(errors).unwrap_err().to_string();
+        assert!(message.contains("CACHE_MAX_AGE_STATS"));
+        assert!(message.contains("requires database.overlay_path"));
+        assert!(message.contains("unknown route nope"));
+
+        assert!(Config::default().check(vec![]).is_ok());
+        assert!(config.to_toml().contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_rate_limit_validation() {
+        let mut config = Config::default();
+        config.rate_limit.search = GroupLimit { per_minute: 60, burst: 2 };
+        config.rate_limit.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        assert!(config.check(vec![]).is_ok());
+
+        config.rate_limit.trusted_proxies = vec!["10.0.0.0/33".to_string(), "proxy".to_string()];
+        config.rate_limit.api.burst = 0;
+        let message = config.check(vec![]).unwrap_err().to_string();
+        assert!(message.contains("10.0.0.0/33") && message.contains("proxy") && message.contains("rate_limit.api.burst"));
+    }
+
+    #[test]
+    fn test_listener_validation() {
+        let mut config = Config::default();
+        config.server.socket_mode = Some("660".to_string());
+        config.server.listeners = vec![
+            ListenerConfig { addr: "unix:/run/lcl.sock".to_string(), socket_mode: None, tls: None },
+            ListenerConfig {
+                addr: "0.0.0.0:8443".to_string(),
+                socket_mode: None,
+                tls: Some(TlsConfig { cert: "cert.pem".to_string(), key: "key.pem".to_string() }),
+            },
+        ];
+        assert!(config.check(vec![]).is_ok());
+        assert_eq!(config.server.all_listeners()[1].socket_mode.as_deref(), Some("660"));
+        config.server.socket_mode = Some("999".to_string());
+        config.server.listeners.push(ListenerConfig { addr: "unix:relative.sock".to_string(), socket_mode: None, tls: None });
+        config.server.listeners.push(ListenerConfig {
+            addr: "unix:/run/lcl.sock".to_string(),
+            socket_mode: None,
+            tls: Some(TlsConfig::default()),
+        });
+        let message = config.check(vec![]).unwrap_err().to_string();
+        for expected in ["\"999\"", "must be absolute", "more than once", "TLS is not supported", "TLS certificate path"] {
+            assert!(message.contains(expected), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// 公开内容的版本
+//
+// ETag、响应缓存、预压缩的命令列表和目录快照都以内容版本为键。内容版本由
+// 启动时的数据库文件哈希和写入代数组成：写入代数只在覆盖库的内容表（命令、
+// 分类、提示、示例）的写入提交后增加，回滚的写入以及用户、收藏、合集、笔记和
+// 建议等私人数据的写入不会使这些缓存失效。
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use rusqlite::Connection;
+
+// 写入这些表会改变公开内容
+pub const CONTENT_TABLES: &[&str] = &[
+    "Command",
+    "CommandSection",
+    "BasicCategory",
+    "BasicGroup",
+    "BasicCommand",
+    "Tip",
+    "TipSection",
+    "CommandExample",
+];
+
+pub struct ContentTracker {
+    hash: String,
+    generation: Arc<AtomicU64>,
+}
+
+impl ContentTracker {
+    pub fn new(hash: String) -> Self {
+        Self {
+            hash,
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // 在连接上注册写入、提交与回滚钩子：内容表的写入先记为待定，提交时才增加写入代数，
+    // 回滚时丢弃，失败的写入不会使缓存失效。一个连接每种钩子只有一个，后注册的会替换先前的
+    pub fn track(&self, conn: &Connection) {
+        let pending = Arc::new(AtomicBool::new(false));
+
+        let written = pending.clone();
+        conn.update_hook(Some(move |_action, _db: &str, table: &str, _rowid| {
+            if CONTENT_TABLES.contains(&table) {
+                written.store(true, Ordering::Relaxed);
+            }
+        }));
+
+        let committed = pending.clone();
+        let counter = self.generation.clone();
+        conn.commit_hook(Some(move || {
+            if committed.swap(false, Ordering::Relaxed) {
+                counter.fetch_add(1, Ordering::Release);
+            }
+            // 返回 true 会把提交变为回滚
+            false
+        }));
+
+        conn.rollback_hook(Some(move || pending.store(false, Ordering::Relaxed)));
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    // 内容在写入钩子之外发生变化（例如手动重新加载目录）时使缓存失效
+    pub fn bump(&self) {
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked() -> (ContentTracker, Connection) {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE Command (id INTEGER PRIMARY KEY, name TEXT); CREATE TABLE User (id INTEGER PRIMARY KEY);")
+            .unwrap();
+        let tracker = ContentTracker::new("hash".to_string());
+        tracker.track(&conn);
+        (tracker, conn)
+    }
+
+    #[test]
+    fn test_generation_counts_committed_content_writes() {
+        let (tracker, conn) = tracked();
+        conn.execute("INSERT INTO Command (name) VALUES ('a')", []).unwrap();
+        assert_eq!(tracker.generation(), 1);
+
+        // 一个事务中的多次写入只增加一次
+        let tx = conn.unchecked_transaction().unwrap();
+        tx.execute("INSERT INTO Command (name) VALUES ('b')", []).unwrap();
+        tx.execute("UPDATE Command SET name = 'c' WHERE name = 'b'", []).unwrap();
+        assert_eq!(tracker.generation(), 1);
+        tx.commit().unwrap();
+        assert_eq!(tracker.generation(), 2);
+
+        conn.execute("INSERT INTO User DEFAULT VALUES", []).unwrap();
+        assert_eq!(tracker.generation(), 2);
+    }
+
+    #[test]
+    fn test_rolled_back_writes_keep_the_generation() {
+        let (tracker, conn) = tracked();
+        {
+            let tx = conn.unchecked_transaction().unwrap();
+            tx.execute("INSERT INTO Command (name) VALUES ('a')", []).unwrap();
+            // 未提交的事务在 drop 时回滚
+        }
+        assert_eq!(tracker.generation(), 0);
+        conn.execute("INSERT INTO User DEFAULT VALUES", []).unwrap();
+        assert_eq!(tracker.generation(), 0);
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM Command", [], |row| row.get::<_, i64>(0)).unwrap(), 0);
+    }
+}
@@ -0,0 +1,158 @@
+// CORS 策略
+//
+// 允许的来源可以是精确的 `https://app.example.com`、匹配任意子域名的
+// `https://*.example.com`（不包括 example.com 本身），或 `*`（任意来源）。
+// 未配置来源时不允许任何跨域请求。方法、请求头、暴露的响应头、
+// 凭据与预检缓存时间均来自配置。
+
+use actix_cors::Cors;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::Method;
+
+use crate::config::CorsConfig;
+
+// 已解析的来源模式
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OriginPattern {
+    Any,
+    Exact(String),
+    // scheme（含 ://）与 `.example.com[:port]` 后缀
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim().trim_end_matches('/').to_ascii_lowercase();
+        if pattern == "*" {
+            return Ok(OriginPattern::Any);
+        }
+
+        let (scheme, host) = ["https://", "http://"]
+            .iter()
+            .find_map(|scheme| pattern.strip_prefix(scheme).map(|host| (*scheme, host)))
+            .ok_or_else(|| format!("origin {:?} must start with http:// or https://", pattern))?;
+        if host.is_empty() || host.contains('/') {
+            return Err(format!("origin {:?} must be scheme://host[:port] without a path", pattern));
+        }
+
+        match host.strip_prefix('*') {
+            Some(suffix) if suffix.starts_with('.') && suffix.len() > 1 && !suffix.contains('*') => {
+                Ok(OriginPattern::Subdomain { scheme: scheme.to_string(), suffix: suffix.to_string() })
+            }
+            _ if host.contains('*') => Err(format!("origin {:?} may only use a wildcard as the leading label, e.g. https://*.example.com", pattern)),
+            _ => Ok(OriginPattern::Exact(pattern)),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        match self {
+            OriginPattern::Any => true,
+            OriginPattern::Exact(exact) => origin == *exact,
+            OriginPattern::Subdomain { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|label| {
+                    !label.is_empty() && label.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.'))
+                }),
+        }
+    }
+}
+
+// 配置中的问题，由 Config::check 统一报告
+pub fn validate(config: &CorsConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let mut any = false;
+    for origin in &config.origins {
+        match OriginPattern::parse(origin) {
+            Ok(OriginPattern::Any) => any = true,
+            Ok(_) => {}
+            Err(e) => errors.push(format!("cors.origins: {}", e)),
+        }
+    }
+    if any && config.allow_credentials {
+        errors.push("cors.allow_credentials cannot be combined with the \"*\" origin".to_string());
+    }
+
+    for method in &config.methods {
+        if Method::from_bytes(method.trim().as_bytes()).is_err() {
+            errors.push(format!("cors.methods: invalid method {:?}", method));
+        }
+    }
+    for header in config.headers.iter().chain(&config.expose_headers) {
+        if HeaderName::from_bytes(header.trim().as_bytes()).is_err() {
+            errors.push(format!("cors: invalid header name {:?}", header));
+        }
+    }
+
+    errors
+}
+
+pub fn build(config: &CorsConfig) -> Cors {
+    if !config.enabled {
+        return Cors::default();
+    }
+
+    let mut cors = Cors::default()
+        .allowed_methods(config.methods.iter().filter_map(|m| Method::from_bytes(m.trim().to_ascii_uppercase().as_bytes()).ok()))
+        .allowed_headers(config.headers.iter().map(|h| h.trim()))
+        .expose_headers(config.expose_headers.iter().map(|h| h.trim()))
+        .max_age(config.max_age);
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+
+    let patterns: Vec<OriginPattern> = config.origins.iter().filter_map(|o| OriginPattern::parse(o).ok()).collect();
+    if patterns.contains(&OriginPattern::Any) {
+        return cors.allow_any_origin();
+    }
+    if patterns.is_empty() {
+        return cors;
+    }
+
+    cors.allowed_origin_fn(move |origin: &HeaderValue, _| {
+        origin.to_str().is_ok_and(|origin| patterns.iter().any(|p| p.matches(origin)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_patterns() {
+        let exact = OriginPattern::parse("https://App.example.com/").unwrap();
+        assert!(exact.matches("https://app.example.com"));
+        assert!(!exact.matches("http://app.example.com"));
+
+        // 子域名通配不包括裸域名，也不匹配后缀伪造
+        let subdomain = OriginPattern::parse("https://*.example.org").unwrap();
+        for origin in ["https://docs.example.org", "https://a.b.example.org", "https://DOCS.example.org"] {
+            assert!(subdomain.matches(origin), "{}", origin);
+        }
+        for origin in ["https://example.org", "https://.example.org", "https://app.example.org.evil.com", "http://docs.example.org", "https://a/b.example.org"] {
+            assert!(!subdomain.matches(origin), "{}", origin);
+        }
+        assert_eq!(OriginPattern::parse(" * "), Ok(OriginPattern::Any));
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = CorsConfig {
+            origins: vec!["https://app.example.com".to_string(), "https://*.example.org".to_string()],
+            allow_credentials: true,
+            max_age: Some(600),
+            ..Default::default()
+        };
+        assert!(validate(&valid).is_empty());
+
+        let invalid = CorsConfig {
+            origins: vec!["*".to_string(), "https://*example.com".to_string(), "example.com".to_string()],
+            methods: vec!["GET POST".to_string()],
+            allow_credentials: true,
+            ..Default::default()
+        };
+        assert_eq!(validate(&invalid).len(), 4);
+    }
+}
@@ -0,0 +1,306 @@
+// Dash / Zeal 文档集导出
+//
+// 生成离线文档浏览器使用的 `.docset` 目录：
+//
+//   LinuxCommandLibrary.docset/Contents/Info.plist
+//   LinuxCommandLibrary.docset/Contents/Resources/docSet.dsidx   搜索索引（SQLite）
+//   LinuxCommandLibrary.docset/Contents/Resources/Documents/      index.html、style.css、
+//                                                                commands/*.html、guides/*.html
+//
+// 每个命令（合并覆盖库，遮蔽的上游命令不导出）生成一个页面，索引类型为 Command；
+// 每个 BasicCategory 的一行命令生成一个页面，索引类型为 Guide。命令章节带 Dash
+// 目录锚点。先写到同级的 `.partial` 目录，完成后再改名，中途失败不会留下残缺的文档集。
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use rusqlite::{params, Connection};
+
+use crate::export::{self, read_one_liners};
+use crate::overlay::{Source, OVERLAY_ID_OFFSET};
+use crate::{category_name, merged_basic_categories, AppError, AppState, Command, CommandSection};
+
+pub const DEFAULT_OUTPUT: &str = "LinuxCommandLibrary.docset";
+
+// Dash 中的搜索关键字前缀，例如 `lcl:tar`
+const PLATFORM_FAMILY: &str = "lcl";
+
+// 章节内容来自上游手册页的 HTML，只保留这些格式标签，其余尖括号按文本显示
+const ALLOWED_TAGS: &[&str] = &["b", "i", "u", "em", "strong", "code", "pre"];
+
+const STYLE: &str = "body { font-family: -apple-system, 'Segoe UI', sans-serif; margin: 2em; line-height: 1.5; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.meta { color: #666; margin-top: 0; }
+h2 { font-size: 1.05em; text-transform: uppercase; letter-spacing: 0.5px; margin-top: 1.6em; }
+.section, pre { font-family: Menlo, 'Courier New', monospace; font-size: 0.9em; background: #f6f8fa;
+  border: 1px solid #ddd; border-radius: 4px; padding: 0.8em; white-space: pre-wrap; overflow-x: auto; }
+.tldr { border-left: 4px solid #2f6fde; }
+.mans { color: #666; font-size: 0.9em; }
+ul.commands { columns: 4 12em; padding-left: 1.2em; }
+";
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub commands: usize,
+    pub guides: usize,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// 与前端一致按 pre-wrap 显示；保留实体和 ALLOWED_TAGS 中的标签，<br> 换成换行
+fn format_content(content: &str) -> String {
+    let mut html = content.replace('<', "&lt;").replace('>', "&gt;");
+    for tag in ALLOWED_TAGS {
+        html = html
+            .replace(&format!("&lt;{}&gt;", tag), &format!("<{}>", tag))
+            .replace(&format!("&lt;/{}&gt;", tag), &format!("</{}>", tag));
+    }
+    for br in ["&lt;br&gt;", "&lt;br/&gt;", "&lt;br /&gt;"] {
+        html = html.replace(br, "\n");
+    }
+    html
+}
+
+// Dash 锚点名称中的非字母数字字符需要百分号编码
+fn anchor_name(title: &str) -> String {
+    title
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        stylesheet,
+        body
+    )
+}
+
+fn plist(name: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>CFBundleIdentifier</key>
+    <string>{family}</string>
+    <key>CFBundleName</key>
+    <string>{name}</string>
+    <key>DocSetPlatformFamily</key>
+    <string>{family}</string>
+    <key>DashDocSetFamily</key>
+    <string>dashtoc</string>
+    <key>dashIndexFilePath</key>
+    <string>index.html</string>
+    <key>isDashDocset</key>
+    <true/>
+    <key>isJavaScriptEnabled</key>
+    <false/>
+</dict>
+</plist>
+"#,
+        family = PLATFORM_FAMILY,
+        name = escape(name),
+    )
+}
+
+// 生成不区分大小写也不冲突的文件名（macOS 默认文件系统不区分大小写）
+struct FileNames {
+    used: HashSet<String>,
+}
+
+impl FileNames {
+    fn new() -> Self {
+        Self { used: HashSet::new() }
+    }
+
+    fn allocate(&mut self, name: &str) -> String {
+        let mut stem: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+') { c } else { '_' })
+            .collect();
+        if stem.trim_matches('.').is_empty() {
+            stem = format!("_{}", stem);
+        }
+        let mut file = format!("{}.html", stem);
+        let mut n = 2;
+        while !self.used.insert(file.to_lowercase()) {
+            file = format!("{}-{}.html", stem, n);
+            n += 1;
+        }
+        file
+    }
+}
+
+struct Writer {
+    documents: PathBuf,
+    index: Connection,
+    command_files: FileNames,
+    guide_files: FileNames,
+    // 命令名称到页面路径，用于一行命令中的手册页链接
+    command_paths: HashMap<String, String>,
+    summary: Summary,
+}
+
+impl Writer {
+    fn new(contents: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let documents = contents.join("Resources").join("Documents");
+        fs::create_dir_all(documents.join("commands"))?;
+        fs::create_dir_all(documents.join("guides"))?;
+        let index = Connection::open(contents.join("Resources").join("docSet.dsidx"))?;
+        index.execute_batch(
+            "CREATE TABLE searchIndex (id INTEGER PRIMARY KEY, name TEXT, type TEXT, path TEXT);
+             CREATE UNIQUE INDEX anchor ON searchIndex (name, type, path);
+             BEGIN;",
+        )?;
+        Ok(Self {
+            documents,
+            index,
+            command_files: FileNames::new(),
+            guide_files: FileNames::new(),
+            command_paths: HashMap::new(),
+            summary: Summary::default(),
+        })
+    }
+
+    fn add_entry(&self, name: &str, entry_type: &str, path: &str) -> Result<(), AppError> {
+        self.index.execute(
+            "INSERT OR IGNORE INTO searchIndex (name, type, path) VALUES (?1, ?2, ?3)",
+            params![name, entry_type, path],
+        )?;
+        Ok(())
+    }
+
+    fn command(&mut self, command: &Command, sections: &[CommandSection]) -> Result<(), Box<dyn std::error::Error>> {
+        let path = format!("commands/{}", self.command_files.allocate(&command.name));
+        let mut body = format!(
+            "<h1>{}</h1>\n<p class=\"meta\">{} &middot; {}</p>\n",
+            escape(&command.name),
+            escape(&command.description),
+            category_name(command.category)
+        );
+        for section in sections {
+            let class = if section.title == "TLDR" { "section tldr" } else { "section" };
+            let _ = write!(
+                body,
+                "<a name=\"//apple_ref/cpp/Section/{}\" class=\"dashAnchor\"></a>\n<h2>{}</h2>\n<div class=\"{}\">{}</div>\n",
+                anchor_name(&section.title),
+                escape(&section.title),
+                class,
+                format_content(&section.content)
+            );
+        }
+        fs::write(self.documents.join(&path), page(&command.name, "../style.css", &body))?;
+        self.add_entry(&command.name, "Command", &path)?;
+        self.command_paths.insert(command.name.clone(), path);
+        self.summary.commands += 1;
+        Ok(())
+    }
+
+    // 一个 BasicCategory 的全部一行命令，按分组列出
+    fn guide(&mut self, title: &str, rows: &[(String, String, String)]) -> Result<String, Box<dyn std::error::Error>> {
+        let path = format!("guides/{}", self.guide_files.allocate(title));
+        let mut body = format!("<h1>{}</h1>\n", escape(title));
+        let mut group = None;
+        for (description, command, mans) in rows {
+            if group != Some(description) {
+                let _ = write!(
+                    body,
+                    "<a name=\"//apple_ref/cpp/Section/{}\" class=\"dashAnchor\"></a>\n<h2>{}</h2>\n",
+                    anchor_name(description),
+                    escape(description)
+                );
+                group = Some(description);
+            }
+            let _ = writeln!(body, "<pre>{}</pre>", escape(command));
+            let links: Vec<String> = mans
+                .split([',', ' '])
+                .filter(|man| !man.is_empty())
+                .map(|man| match self.command_paths.get(man) {
+                    Some(path) => format!("<a href=\"../{}\">{}</a>", escape(path), escape(man)),
+                    None => escape(man),
+                })
+                .collect();
+            if !links.is_empty() {
+                let _ = writeln!(body, "<p class=\"mans\">{}</p>", links.join(", "));
+            }
+        }
+        fs::write(self.documents.join(&path), page(title, "../style.css", &body))?;
+        self.add_entry(title, "Guide", &path)?;
+        self.summary.guides += 1;
+        Ok(path)
+    }
+
+    fn finish(self, name: &str, guides: &[(String, String)]) -> Result<Summary, Box<dyn std::error::Error>> {
+        let mut body = format!("<h1>{}</h1>\n<h2>Guides</h2>\n<ul>\n", escape(name));
+        for (title, path) in guides {
+            let _ = writeln!(body, "<li><a href=\"{}\">{}</a></li>", escape(path), escape(title));
+        }
+        body.push_str("</ul>\n<h2>Commands</h2>\n<ul class=\"commands\">\n");
+        let mut commands: Vec<_> = self.command_paths.iter().collect();
+        commands.sort_by_key(|(name, _)| name.to_lowercase());
+        for (command, path) in commands {
+            let _ = writeln!(body, "<li><a href=\"{}\">{}</a></li>", escape(path), escape(command));
+        }
+        body.push_str("</ul>\n");
+        fs::write(self.documents.join("index.html"), page(name, "style.css", &body))?;
+        fs::write(self.documents.join("style.css"), STYLE)?;
+        self.index.execute_batch("COMMIT;")?;
+        Ok(self.summary)
+    }
+}
+
+// 生成文档集；output 已存在时需要 replace 才会替换
+pub fn build(data: &AppState, output: &Path, replace: bool) -> Result<Summary, Box<dyn std::error::Error>> {
+    if output.exists() && !replace {
+        return Err(format!("{} already exists (use --force to replace it)", output.display()).into());
+    }
+    let name = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| "LinuxCommandLibrary".to_string());
+    let staging = PathBuf::from(format!("{}.partial", output.display()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let contents = staging.join("Contents");
+
+    let mut writer = Writer::new(&contents)?;
+    export::for_each_command(data, |command, sections| writer.command(command, sections))?;
+    let mut guides = Vec::new();
+    for category in merged_basic_categories(data)? {
+        let rows = match category.source {
+            Source::Base => read_one_liners(&*data.lock_db()?, category.id)?,
+            Source::Overlay => match data.lock_overlay()? {
+                Some(conn) => read_one_liners(&conn, category.id - OVERLAY_ID_OFFSET)?,
+                None => vec![],
+            },
+        };
+        if rows.is_empty() {
+            continue;
+        }
+        let path = writer.guide(&category.title, &rows)?;
+        guides.push((category.title, path));
+    }
+    let summary = writer.finish(&name, &guides)?;
+    fs::write(contents.join("Info.plist"), plist(&name))?;
+
+    if output.exists() {
+        fs::remove_dir_all(output)?;
+    }
+    fs::rename(&staging, output)?;
+    info!("Docset written to {}: {} commands, {} guides", output.display(), summary.commands, summary.guides);
+    Ok(summary)
+}
@@ -0,0 +1,617 @@
+// 全量数据导出（NDJSON / CSV）
+//
+// 导出合并覆盖库后的全部命令（含章节）、分类、一行命令（BasicGroup /
+// BasicCommand）和提示。命令与提示按 id 分批读取，响应体每被拉取一次才读取
+// 下一批，不会把整个数据集放进内存，也不会长时间占用数据库锁；导出期间的写入
+// 可能只出现在部分批次中。NDJSON 每行一条记录，用 "type" 区分类型；CSV 每种
+// 数据的列不同，一次只能导出一种。命令行模式（`export` 子命令）使用同一实现。
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::web::{self, Bytes};
+use actix_web::HttpResponse;
+use log::{error, info};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::overlay::{self, Source, OVERLAY_ID_OFFSET};
+use crate::{
+    category_name, load_tip_sections, merged_basic_categories, overlay_command_names, query_commands, AppError,
+    AppState, BasicCategory, CommandSection, ErrorResponse, TipSection,
+};
+
+// 每批读取的命令 / 提示数量
+const BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            other => Err(format!("unknown export format {:?} (expected \"ndjson\" or \"csv\")", other)),
+        }
+    }
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Ndjson => "application/x-ndjson",
+            Format::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    #[default]
+    All,
+    Commands,
+    Categories,
+    OneLiners,
+    Tips,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "all" => Ok(Kind::All),
+            "commands" => Ok(Kind::Commands),
+            "categories" => Ok(Kind::Categories),
+            "one-liners" => Ok(Kind::OneLiners),
+            "tips" => Ok(Kind::Tips),
+            other => Err(format!(
+                "unknown export kind {:?} (expected all, commands, categories, one-liners or tips)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::All => "all",
+            Kind::Commands => "commands",
+            Kind::Categories => "categories",
+            Kind::OneLiners => "one-liners",
+            Kind::Tips => "tips",
+        })
+    }
+}
+
+impl Kind {
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Kind::All => &[],
+            Kind::Commands => &["id", "name", "category", "description", "source", "section", "content"],
+            Kind::Categories => &["id", "title", "position", "description", "source"],
+            Kind::OneLiners => &["category", "group", "command", "mans", "source"],
+            Kind::Tips => &["id", "title", "source", "type", "data1", "data2", "extra"],
+        }
+    }
+}
+
+// NDJSON 记录
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Command {
+        id: i64,
+        name: &'a str,
+        category: &'static str,
+        description: &'a str,
+        source: Source,
+        sections: &'a [CommandSection],
+    },
+    Category(&'a BasicCategory),
+    OneLiner {
+        category: &'a str,
+        group: &'a str,
+        command: &'a str,
+        mans: &'a str,
+        source: Source,
+    },
+    Tip {
+        id: i64,
+        title: &'a str,
+        source: Source,
+        sections: &'a [TipSection],
+    },
+}
+
+fn source_name(source: Source) -> &'static str {
+    match source {
+        Source::Base => "base",
+        Source::Overlay => "overlay",
+    }
+}
+
+// RFC 4180：包含分隔符、引号或换行的字段加引号，引号加倍
+fn write_csv_row(out: &mut Vec<u8>, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push(b'"');
+            out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field.as_bytes());
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+}
+
+fn write_json_line(out: &mut Vec<u8>, record: &Record) -> Result<(), AppError> {
+    serde_json::to_writer(&mut *out, record)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize export record: {}", e)))?;
+    out.push(b'\n');
+    Ok(())
+}
+
+// 按需生成导出内容的游标
+pub struct Export {
+    data: web::Data<AppState>,
+    format: Format,
+    // 尚未导出完的数据种类
+    kinds: VecDeque<Kind>,
+    // 当前分批读取的数据库与已导出的最大 id
+    source: Source,
+    cursor: i64,
+    header_written: bool,
+    // 被覆盖库遮蔽的上游命令名称与提示标题
+    shadowed: Option<HashSet<String>>,
+    shadowed_tips: Option<HashSet<String>>,
+    records: usize,
+}
+
+impl Export {
+    pub fn new(data: web::Data<AppState>, format: Format, kind: Kind) -> Result<Self, AppError> {
+        let kinds = match (format, kind) {
+            (Format::Csv, Kind::All) => {
+                return Err(AppError::InvalidInput(
+                    "CSV export needs a single kind: commands, categories, one-liners or tips".to_string(),
+                ));
+            }
+            (_, Kind::All) => VecDeque::from([Kind::Commands, Kind::Categories, Kind::OneLiners, Kind::Tips]),
+            (_, kind) => VecDeque::from([kind]),
+        };
+        Ok(Self {
+            data,
+            format,
+            kinds,
+            source: Source::Base,
+            cursor: 0,
+            header_written: false,
+            shadowed: None,
+            shadowed_tips: None,
+            records: 0,
+        })
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    // 下一段输出；None 表示导出完成
+    pub fn next_chunk(&mut self) -> Result<Option<Bytes>, AppError> {
+        let mut out = Vec::new();
+        while let Some(&kind) = self.kinds.front() {
+            if self.format == Format::Csv && !self.header_written {
+                write_csv_row(&mut out, kind.columns());
+                self.header_written = true;
+            }
+            let more = match kind {
+                Kind::Commands => self.commands(&mut out)?,
+                Kind::Categories => self.categories(&mut out)?,
+                Kind::OneLiners => self.one_liners(&mut out)?,
+                Kind::Tips => self.tips(&mut out)?,
+                Kind::All => false,
+            };
+            if !more {
+                self.kinds.pop_front();
+                self.source = Source::Base;
+                self.cursor = 0;
+            }
+            if !out.is_empty() {
+                return Ok(Some(Bytes::from(out)));
+            }
+        }
+        Ok(None)
+    }
+
+    // 分批读取的种类：上游读完后切换到覆盖库，覆盖库读完（或没有该表）时返回 None
+    fn with_source<T>(
+        &mut self,
+        table: &str,
+        read: impl FnOnce(&Connection, i64) -> Result<Vec<T>, AppError>,
+    ) -> Result<Option<Vec<T>>, AppError> {
+        let batch = match self.source {
+            Source::Base => read(&*self.data.lock_db()?, self.cursor)?,
+            Source::Overlay => match self.data.lock_overlay()? {
+                Some(conn) if overlay::table_exists(&conn, table)? => read(&conn, self.cursor)?,
+                _ => return Ok(None),
+            },
+        };
+        if batch.is_empty() {
+            if self.source == Source::Overlay {
+                return Ok(None);
+            }
+            self.source = Source::Overlay;
+            self.cursor = 0;
+        }
+        Ok(Some(batch))
+    }
+
+    fn commands(&mut self, out: &mut Vec<u8>) -> Result<bool, AppError> {
+        if self.shadowed.is_none() {
+            let shadowed = match self.data.lock_overlay()? {
+                Some(conn) if overlay::table_exists(&conn, "Command")? => overlay_command_names(&conn)?,
+                _ => HashSet::new(),
+            };
+            self.shadowed = Some(shadowed);
+        }
+
+        let source = self.source;
+        let Some(batch) = self.with_source("Command", read_commands)? else { return Ok(false) };
+        let shadowed = self.shadowed.as_ref().expect("loaded above");
+        for (command, sections) in &batch {
+            self.cursor = command.id;
+            if source == Source::Base && shadowed.contains(&command.name) {
+                continue;
+            }
+            let id = match source {
+                Source::Base => command.id,
+                Source::Overlay => command.id + OVERLAY_ID_OFFSET,
+            };
+            let category = category_name(command.category);
+            match self.format {
+                Format::Ndjson => write_json_line(
+                    out,
+                    &Record::Command {
+                        id,
+                        name: &command.name,
+                        category,
+                        description: &command.description,
+                        source,
+                        sections,
+                    },
+                )?,
+                Format::Csv => {
+                    let id = id.to_string();
+                    let row = [id.as_str(), &command.name, category, &command.description, source_name(source)];
+                    if sections.is_empty() {
+                        write_csv_row(out, &[&row[..], &["", ""]].concat());
+                    }
+                    for section in sections {
+                        write_csv_row(out, &[&row[..], &[section.title.as_str(), section.content.as_str()]].concat());
+                    }
+                }
+            }
+            self.records += 1;
+        }
+        Ok(true)
+    }
+
+    fn categories(&mut self, out: &mut Vec<u8>) -> Result<bool, AppError> {
+        for category in merged_basic_categories(&self.data)? {
+            match self.format {
+                Format::Ndjson => write_json_line(out, &Record::Category(&category))?,
+                Format::Csv => {
+                    let (id, position) = (category.id.to_string(), category.position.to_string());
+                    let description = category.description.as_deref().unwrap_or_default();
+                    write_csv_row(out, &[&id, &category.title, &position, description, source_name(category.source)]);
+                }
+            }
+            self.records += 1;
+        }
+        Ok(false)
+    }
+
+    // 一行命令按分类所在的数据库读取（覆盖库中的同名分类遮蔽上游分类）
+    fn one_liners(&mut self, out: &mut Vec<u8>) -> Result<bool, AppError> {
+        for category in merged_basic_categories(&self.data)? {
+            let rows = match category.source {
+                Source::Base => read_one_liners(&*self.data.lock_db()?, category.id)?,
+                Source::Overlay => match self.data.lock_overlay()? {
+                    Some(conn) => read_one_liners(&conn, category.id - OVERLAY_ID_OFFSET)?,
+                    None => vec![],
+                },
+            };
+            for (group, command, mans) in &rows {
+                match self.format {
+                    Format::Ndjson => write_json_line(
+                        out,
+                        &Record::OneLiner { category: &category.title, group, command, mans, source: category.source },
+                    )?,
+                    Format::Csv => write_csv_row(out, &[&category.title, group, command, mans, source_name(category.source)]),
+                }
+                self.records += 1;
+            }
+        }
+        Ok(false)
+    }
+
+    fn tips(&mut self, out: &mut Vec<u8>) -> Result<bool, AppError> {
+        if self.shadowed_tips.is_none() {
+            let shadowed = match self.data.lock_overlay()? {
+                Some(conn) if overlay::table_exists(&conn, "Tip")? => overlay_tip_titles(&conn)?,
+                _ => HashSet::new(),
+            };
+            self.shadowed_tips = Some(shadowed);
+        }
+
+        let source = self.source;
+        let Some(batch) = self.with_source("Tip", read_tips)? else { return Ok(false) };
+        let shadowed = self.shadowed_tips.as_ref().expect("loaded above");
+        for (id, title, sections) in &batch {
+            self.cursor = *id;
+            if source == Source::Base && shadowed.contains(title) {
+                continue;
+            }
+            let id = match source {
+                Source::Base => *id,
+                Source::Overlay => id + OVERLAY_ID_OFFSET,
+            };
+            match self.format {
+                Format::Ndjson => write_json_line(out, &Record::Tip { id, title, source, sections })?,
+                Format::Csv => {
+                    let id = id.to_string();
+                    let row = [id.as_str(), title, source_name(source)];
+                    if sections.is_empty() {
+                        write_csv_row(out, &[&row[..], &["", "", "", ""]].concat());
+                    }
+                    for section in sections {
+                        let section_type = section.section_type.to_string();
+                        let fields = [section_type.as_str(), &section.data1, &section.data2, &section.extra];
+                        write_csv_row(out, &[&row[..], &fields[..]].concat());
+                    }
+                }
+            }
+            self.records += 1;
+        }
+        Ok(true)
+    }
+}
+
+// 按 id 分批遍历合并覆盖库后的全部命令（遮蔽的上游命令跳过），读取时只短暂持有锁
+pub fn for_each_command(
+    data: &AppState,
+    mut f: impl FnMut(&crate::Command, &[CommandSection]) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shadowed = match data.lock_overlay()? {
+        Some(conn) if overlay::table_exists(&conn, "Command")? => overlay_command_names(&conn)?,
+        _ => HashSet::new(),
+    };
+    for source in [Source::Base, Source::Overlay] {
+        let mut cursor = 0;
+        loop {
+            let batch = match source {
+                Source::Base => read_commands(&*data.lock_db()?, cursor)?,
+                Source::Overlay => match data.lock_overlay()? {
+                    Some(conn) if overlay::table_exists(&conn, "Command")? => read_commands(&conn, cursor)?,
+                    _ => vec![],
+                },
+            };
+            if batch.is_empty() {
+                break;
+            }
+            for (command, sections) in &batch {
+                cursor = command.id;
+                if source == Source::Base && shadowed.contains(&command.name) {
+                    continue;
+                }
+                f(command, sections)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// id 大于 after 的一批命令及其章节（排除 NAME 章节，与详情接口一致）
+fn read_commands(conn: &Connection, after: i64) -> Result<Vec<(crate::Command, Vec<CommandSection>)>, AppError> {
+    let commands = query_commands(
+        conn,
+        "SELECT id, name, category, description FROM Command WHERE id > ?1 ORDER BY id LIMIT ?2",
+        params![after, BATCH_SIZE],
+        "export commands",
+    )?;
+    let (Some(first), Some(last)) = (commands.first(), commands.last()) else { return Ok(vec![]) };
+
+    let mut stmt = conn.prepare(
+        "SELECT command_id, title, content FROM CommandSection
+         WHERE command_id BETWEEN ?1 AND ?2 AND title != 'NAME' ORDER BY id",
+    )?;
+    let mut sections: HashMap<i64, Vec<CommandSection>> = HashMap::new();
+    let rows = stmt.query_map(params![first.id, last.id], |row| {
+        Ok((row.get::<_, i64>(0)?, CommandSection { title: row.get(1)?, content: row.get(2)? }))
+    })?;
+    for row in rows {
+        let (command_id, section) = row?;
+        sections.entry(command_id).or_default().push(section);
+    }
+
+    Ok(commands
+        .into_iter()
+        .map(|command| {
+            let sections = sections.remove(&command.id).unwrap_or_default();
+            (command, sections)
+        })
+        .collect())
+}
+
+// 覆盖库中的提示标题，同名提示遮蔽上游提示
+fn overlay_tip_titles(conn: &Connection) -> Result<HashSet<String>, AppError> {
+    let mut stmt = conn.prepare("SELECT title FROM Tip")?;
+    let titles = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    Ok(titles)
+}
+
+fn read_tips(conn: &Connection, after: i64) -> Result<Vec<(i64, String, Vec<TipSection>)>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, title FROM Tip WHERE id > ?1 ORDER BY id LIMIT ?2")?;
+    let tips: Vec<(i64, String)> = stmt
+        .query_map(params![after, BATCH_SIZE], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    tips.into_iter()
+        .map(|(id, title)| Ok((id, title, load_tip_sections(conn, id)?)))
+        .collect()
+}
+
+// (分组描述, 命令, 相关手册页)
+pub fn read_one_liners(conn: &Connection, category_id: i64) -> Result<Vec<(String, String, String)>, AppError> {
+    if !overlay::table_exists(conn, "BasicGroup")? || !overlay::table_exists(conn, "BasicCommand")? {
+        return Ok(vec![]);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT bg.description, bc.command, bc.mans
+         FROM BasicCommand bc
+         JOIN BasicGroup bg ON bc.group_id = bg.id
+         WHERE bg.category_id = ?1
+         ORDER BY bg.position, bc.id",
+    )?;
+    let rows = stmt
+        .query_map(params![category_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+    Ok(rows)
+}
+
+// 每次被拉取时读取下一批，导出中途出错时只能中断连接
+impl MessageBody for Export {
+    type Error = actix_web::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let export = self.get_mut();
+        let chunk = export.next_chunk();
+        match &chunk {
+            Ok(None) => info!("Export finished: {} records", export.records),
+            Err(e) => error!("Export aborted after {} records: {}", export.records, e),
+            Ok(Some(_)) => {}
+        }
+        Poll::Ready(chunk.map_err(actix_web::Error::from).transpose())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    kind: Kind,
+}
+
+// 全量导出，流式输出
+#[utoipa::path(
+    get,
+    path = "/api/v1/export",
+    tag = "export",
+    params(
+        ("format" = Option<String>, Query, description = "`ndjson` (default) or `csv`"),
+        ("kind" = Option<String>, Query, description = "`all` (default, NDJSON only), `commands`, `categories`, `one-liners` or `tips`"),
+    ),
+    responses(
+        (status = 200, description = "Streamed export; every NDJSON record has a `type` field, CSV starts with a header row", content_type = "application/x-ndjson", body = String),
+        (status = 400, description = "Unknown format or kind, or CSV with `kind=all` (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+    )
+)]
+pub async fn export(query: web::Query<ExportQuery>, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let export = Export::new(data, query.format, query.kind)?;
+    info!("Exporting {} as {}", query.kind, query.format.extension());
+
+    let filename = format!("linux-command-library-{}.{}", query.kind, query.format.extension());
+    let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+        .map_err(|e| AppError::InternalError(e.to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(query.format.content_type())
+        .insert_header((header::CONTENT_DISPOSITION, disposition))
+        .body(export))
+}
+
+// 命令行模式：写入文件或标准输出
+pub fn run(data: web::Data<AppState>, format: Format, kind: Kind, mut output: impl Write) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut export = Export::new(data, format, kind)?;
+    while let Some(chunk) = export.next_chunk()? {
+        output.write_all(&chunk)?;
+    }
+    output.flush()?;
+    Ok(export.records())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_app_state;
+
+    #[test]
+    fn test_csv_escaping() {
+        let mut out = Vec::new();
+        write_csv_row(&mut out, &["plain", "a,b", "say \"hi\"", "two\nlines", "cr\r", ""]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\"cr\r\",\r\n"
+        );
+    }
+
+    #[test]
+    fn test_format_and_kind_parsing() {
+        assert_eq!(" JSONL ".parse::<Format>(), Ok(Format::Ndjson));
+        assert_eq!("csv".parse::<Format>(), Ok(Format::Csv));
+        assert!("xml".parse::<Format>().is_err());
+        assert_eq!("one_liners".parse::<Kind>(), Ok(Kind::OneLiners));
+        assert_eq!(Kind::OneLiners.to_string(), "one-liners");
+        assert!("everything".parse::<Kind>().is_err());
+    }
+
+    #[test]
+    fn test_overlay_tips_shadow_upstream_tips() {
+        let data = web::Data::new(test_app_state("secret"));
+        data.lock_overlay().unwrap().unwrap().execute_batch(
+            "INSERT INTO Tip (title, position) VALUES ('Quick Navigation', 1), ('Team tip', 2);
+             INSERT INTO TipSection (tip_id, position, type, data1, data2, extra) VALUES (1, 0, 0, 'Use Ctrl+E', '', '');",
+        ).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(run(data, Format::Csv, Kind::Tips, &mut out).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "id,title,source,type,data1,data2,extra\r\n{},Quick Navigation,overlay,0,Use Ctrl+E,,\r\n{},Team tip,overlay,,,,\r\n",
+                OVERLAY_ID_OFFSET + 1,
+                OVERLAY_ID_OFFSET + 2
+            )
+        );
+    }
+}
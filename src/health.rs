@@ -0,0 +1,144 @@
+// 存活与就绪检查
+//
+// /health/live 只说明进程能处理请求，不访问数据库，供重启策略使用。
+// /health/ready 通过 AppState 对数据库执行一次廉价查询并校验表结构，
+// 任一检查失败或正在停机时返回 503，负载均衡应停止向该实例转发流量。
+
+use std::time::Instant;
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{web, HttpResponse};
+use log::warn;
+use rusqlite::Connection;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{missing_tables, ApiResponse, AppError, AppState, REQUIRED_TABLES};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    /// `ready`, `degraded` or `shutting_down`
+    #[schema(example = "ready")]
+    status: &'static str,
+    database: DatabaseCheck,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overlay: Option<DatabaseCheck>,
+    /// Hash of the database files at startup (also used for ETags)
+    content_hash: String,
+    sqlite_version: &'static str,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DatabaseCheck {
+    ok: bool,
+    /// Value of `PRAGMA user_version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_version: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing_tables: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    latency_ms: f64,
+}
+
+impl DatabaseCheck {
+    fn failed(error: String, started: Instant) -> Self {
+        Self {
+            ok: false,
+            user_version: None,
+            missing_tables: vec![],
+            error: Some(error),
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+// 读取 user_version 并检查必需的表；`required` 为空时只验证数据库可读
+fn probe(conn: &Connection, required: &[&'static str], started: Instant) -> DatabaseCheck {
+    let result = conn
+        .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .and_then(|version| Ok((version, missing_tables(conn, required)?)));
+
+    match result {
+        Ok((user_version, missing_tables)) => DatabaseCheck {
+            ok: missing_tables.is_empty(),
+            user_version: Some(user_version),
+            missing_tables,
+            error: None,
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        },
+        Err(e) => DatabaseCheck::failed(e.to_string(), started),
+    }
+}
+
+fn check(lock: Result<Option<impl std::ops::Deref<Target = Connection>>, AppError>, required: &[&'static str]) -> Option<DatabaseCheck> {
+    let started = Instant::now();
+    match lock {
+        Ok(Some(conn)) => Some(probe(&conn, required, started)),
+        Ok(None) => None,
+        Err(e) => Some(DatabaseCheck::failed(e.to_string(), started)),
+    }
+}
+
+// 存活检查
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses((status = 200, description = "The process is running", body = ApiResponse<String>))
+)]
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(ApiResponse {
+            success: true,
+            data: Some("alive"),
+            message: None,
+        })
+}
+
+// 就绪检查
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Database reachable and schema valid", body = ApiResponse<Readiness>),
+        (status = 503, description = "Database unreachable, corrupt or missing tables, or the server is shutting down", body = ApiResponse<Readiness>),
+    )
+)]
+pub async fn ready(data: web::Data<AppState>) -> HttpResponse {
+    let database = check(data.lock_db().map(Some), REQUIRED_TABLES)
+        .unwrap_or_else(|| DatabaseCheck::failed("Database is not configured".to_string(), Instant::now()));
+    // 覆盖库可能只读挂载或只包含部分表，只要求可读
+    let overlay = check(data.lock_overlay(), &[]);
+
+    let shutting_down = data.shutdown.is_shutting_down();
+    let healthy = database.ok && overlay.as_ref().is_none_or(|o| o.ok) && !shutting_down;
+    let readiness = Readiness {
+        status: match (healthy, shutting_down) {
+            (_, true) => "shutting_down",
+            (true, _) => "ready",
+            (false, _) => "degraded",
+        },
+        database,
+        overlay,
+        content_hash: data.content.hash().to_string(),
+        sqlite_version: rusqlite::version(),
+    };
+
+    let mut res = if healthy {
+        HttpResponse::Ok()
+    } else {
+        if !shutting_down {
+            warn!("Readiness check failed: {:?}", readiness);
+        }
+        HttpResponse::ServiceUnavailable()
+    };
+    res.insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(ApiResponse {
+            success: healthy,
+            data: Some(readiness),
+            message: None,
+        })
+}
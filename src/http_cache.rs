@@ -0,0 +1,231 @@
+// HTTP 缓存：ETag、Last-Modified 与 Cache-Control
+//
+// 数据库在两次部署之间只读，只有覆盖库会被写入。ETag 由数据库内容版本
+// （启动时的文件哈希 + 已提交的内容写入代数，见 content 模块）与请求路径/查询串计算得到，
+// 命中 If-None-Match 时直接返回 304，不再执行 SQL 和序列化。ETag 是弱校验值：
+// 同一内容的原文与各种压缩编码共用一个 ETag，而强校验值要求每种表示各不相同。
+// 随机结果的端点（random-tip、popular）以及带 Authorization 的请求不参与缓存。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ResourceDef, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue, HttpDate};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpResponse};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use crate::config::CacheConfig;
+use crate::AppState;
+
+pub const DEFAULT_MAX_AGE: u32 = 300;
+
+// 可缓存的路由（相对于 /api/v1 或 /api），名称用于 CACHE_MAX_AGE_<NAME> 配置
+const ROUTES: &[(&str, &str)] = &[
+    ("commands", "/commands"),
+    ("command", "/commands/{id}"),
+    ("categories", "/categories"),
+    ("categories", "/categories/detailed"),
+    ("category", "/category/{name}"),
+    ("search", "/search"),
+    ("suggestions", "/suggestions"),
+    ("stats", "/stats"),
+];
+
+const PREFIXES: &[&str] = &["/api/v1", "/api"];
+
+static ROUTE_DEFS: LazyLock<Vec<(&'static str, ResourceDef)>> =
+    LazyLock::new(|| ROUTES.iter().map(|(name, path)| (*name, ResourceDef::new(*path))).collect());
+
+// 可按路由配置 max-age 的路由名称
+pub fn route_names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = ROUTES.iter().map(|(name, _)| *name).collect();
+    names.dedup();
+    names
+}
+
+// 返回可缓存路由的名称；随机端点和写入端点不在其中
+pub fn cacheable_route(path: &str) -> Option<&'static str> {
+    let relative = PREFIXES.iter().find_map(|prefix| path.strip_prefix(prefix))?;
+    ROUTE_DEFS
+        .iter()
+        .find(|(_, def)| def.is_match(relative))
+        .map(|(name, _)| *name)
+}
+
+// 本次请求的内容版本，计算一次后放入请求扩展供内层中间件复用
+#[derive(Clone)]
+pub struct ContentVersion(pub String);
+
+pub fn content_version(req: &ServiceRequest, state: &AppState) -> Result<String, crate::AppError> {
+    if let Some(ContentVersion(version)) = req.extensions().get::<ContentVersion>() {
+        return Ok(version.clone());
+    }
+    let version = state.content_version()?;
+    req.extensions_mut().insert(ContentVersion(version.clone()));
+    Ok(version)
+}
+
+pub struct HttpCache {
+    default_max_age: u32,
+    max_age: HashMap<&'static str, u32>,
+    // 最近一次观察到的内容版本及其时间，用作 Last-Modified
+    seen: Mutex<Option<(String, SystemTime)>>,
+}
+
+impl HttpCache {
+    pub fn new(default_max_age: u32, max_age: HashMap<&'static str, u32>) -> Self {
+        Self {
+            default_max_age,
+            max_age,
+            seen: Mutex::new(None),
+        }
+    }
+
+    // cache.max_age 为默认值，cache.route_max_age 按路由覆盖；0 表示每次都需重新验证
+    pub fn from_config(config: &CacheConfig) -> Self {
+        let max_age = route_names()
+            .into_iter()
+            .filter_map(|name| config.route_max_age.get(name).map(|seconds| (name, *seconds)))
+            .collect();
+        Self::new(config.max_age, max_age)
+    }
+
+    fn cache_control(&self, route: &str) -> String {
+        match self.max_age.get(route).copied().unwrap_or(self.default_max_age) {
+            0 => "public, no-cache".to_string(),
+            seconds => format!("public, max-age={}", seconds),
+        }
+    }
+
+    // 内容版本变化时刷新 Last-Modified
+    fn last_modified(&self, version: &str) -> SystemTime {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        match &*seen {
+            Some((seen_version, at)) if seen_version == version => *at,
+            _ => {
+                let now = SystemTime::now();
+                *seen = Some((version.to_string(), now));
+                now
+            }
+        }
+    }
+}
+
+// 数据库文件内容的哈希；无法读取时（例如内存数据库）退化为进程级随机值
+pub fn content_hash(paths: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for path in paths {
+        let hashed = File::open(path).and_then(|mut file| std::io::copy(&mut file, &mut hasher));
+        if let Err(e) = hashed {
+            warn!("Cannot hash database file {}, ETags will change on restart: {}", path, e);
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+            hasher.update(nanos.to_le_bytes());
+        }
+    }
+    hex(&hasher.finalize())
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn etag_for(version: &str, req: &ServiceRequest) -> String {
+    let digest = Sha256::new()
+        .chain_update(version)
+        .chain_update("\n")
+        .chain_update(req.path())
+        .chain_update("?")
+        .chain_update(req.query_string())
+        .finalize();
+    format!("W/\"{}\"", &hex(&digest)[..32])
+}
+
+// 弱比较（RFC 9110 8.8.3.2）：忽略 W/ 前缀
+pub fn weak_eq(a: &str, b: &str) -> bool {
+    a.strip_prefix("W/").unwrap_or(a) == b.strip_prefix("W/").unwrap_or(b)
+}
+
+// If-None-Match 使用弱比较（RFC 9110 13.1.2）
+fn none_match(req: &ServiceRequest, etag: &str) -> Option<bool> {
+    let value = req.headers().get(header::IF_NONE_MATCH)?.to_str().ok()?;
+    Some(value.split(',').map(str::trim).any(|tag| tag == "*" || weak_eq(tag, etag)))
+}
+
+fn not_modified_since(req: &ServiceRequest, last_modified: SystemTime) -> bool {
+    let since = req
+        .headers()
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<HttpDate>().ok())
+        .map(SystemTime::from);
+    let seconds = |t: SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    matches!(since, Some(since) if seconds(last_modified) <= seconds(since))
+}
+
+fn insert_headers(headers: &mut header::HeaderMap, etag: &str, cache_control: &str, last_modified: SystemTime) {
+    let values = [
+        (header::ETAG, HeaderValue::from_str(etag)),
+        (header::CACHE_CONTROL, HeaderValue::from_str(cache_control)),
+        (header::LAST_MODIFIED, HeaderValue::from_str(&HttpDate::from(last_modified).to_string())),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = value {
+            headers.insert(name, value);
+        }
+    }
+    // 带 token 的请求会返回私人内容（例如笔记）；保留处理器设置的 Vary（例如 Accept-Encoding）
+    headers.append(header::VARY, HeaderValue::from_static("Authorization"));
+}
+
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD)
+        && !req.headers().contains_key(header::AUTHORIZATION);
+    let cache = req.app_data::<web::Data<HttpCache>>().cloned();
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+
+    let (cache, state, route) = match (cacheable, cache, state) {
+        (true, Some(cache), Some(state)) => match cacheable_route(req.path()) {
+            Some(route) => (cache, state, route),
+            None => return Ok(next.call(req).await?.map_into_left_body()),
+        },
+        _ => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    let version = match content_version(&req, &state) {
+        Ok(version) => version,
+        Err(e) => {
+            warn!("Skipping HTTP caching, cannot read content version: {}", e);
+            return Ok(next.call(req).await?.map_into_left_body());
+        }
+    };
+
+    let etag = etag_for(&version, &req);
+    let cache_control = cache.cache_control(route);
+    let last_modified = cache.last_modified(&version);
+
+    let fresh = match none_match(&req, &etag) {
+        Some(matched) => matched,
+        None => not_modified_since(&req, last_modified),
+    };
+    if fresh {
+        debug!("Not modified: {} ({})", req.path(), etag);
+        let mut res = HttpResponse::NotModified().finish();
+        insert_headers(res.headers_mut(), &etag, &cache_control, last_modified);
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    if res.status() == StatusCode::OK {
+        insert_headers(res.headers_mut(), &etag, &cache_control, last_modified);
+    }
+    Ok(res.map_into_left_body())
+}
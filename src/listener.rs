@@ -0,0 +1,191 @@
+// 监听地址：TCP、TLS 与 Unix 域套接字
+//
+// 监听地址为 `host:port` 或 `unix:/path/to/socket`，可同时配置多个。
+// TLS 证书链与私钥从 PEM 文件加载，收到 SIGHUP 时重新读取；新证书无效时
+// 记录错误并继续使用旧证书，已建立的连接不受影响。Unix 套接字绑定前会
+// 删除残留的套接字文件，绑定后按配置设置权限。
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use log::{error, info};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use thiserror::Error;
+
+use crate::config::TlsConfig;
+
+const UNIX_PREFIX: &str = "unix:";
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Cannot read {path}: {source}")]
+    Read { path: PathBuf, source: rustls::pki_types::pem::Error },
+
+    #[error("No certificate found in {0}")]
+    NoCertificate(PathBuf),
+
+    #[error("Invalid certificate or key ({cert} / {key}): {source}")]
+    Invalid { cert: PathBuf, key: PathBuf, source: rustls::Error },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl ListenAddr {
+    pub fn parse(addr: &str) -> Result<Self, String> {
+        if let Some(path) = addr.strip_prefix(UNIX_PREFIX) {
+            return if path.starts_with('/') {
+                Ok(ListenAddr::Unix(PathBuf::from(path)))
+            } else {
+                Err(format!("unix socket path must be absolute, got {:?}", addr))
+            };
+        }
+        match addr.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(ListenAddr::Tcp(addr.to_string())),
+            _ => Err(format!("listen address must be host:port or unix:/path, got {:?}", addr)),
+        }
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddr::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+// 八进制权限，例如 "660"
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode.trim().trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|m| *m <= 0o777)
+        .ok_or_else(|| format!("socket mode must be octal permissions such as \"660\", got {:?}", mode))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn read_err(path: &Path) -> impl Fn(rustls::pki_types::pem::Error) -> TlsError + '_ {
+    move |source| TlsError::Read { path: path.to_path_buf(), source }
+}
+
+fn load_certified_key(cert: &Path, key: &Path) -> Result<CertifiedKey, TlsError> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .map_err(read_err(cert))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_err(cert))?;
+    if chain.is_empty() {
+        return Err(TlsError::NoCertificate(cert.to_path_buf()));
+    }
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(read_err(key))?;
+
+    CertifiedKey::from_der(chain, private_key, &provider()).map_err(|source| TlsError::Invalid {
+        cert: cert.to_path_buf(),
+        key: key.to_path_buf(),
+        source,
+    })
+}
+
+// 为每次握手提供当前证书，reload 时原子替换
+#[derive(Debug)]
+pub struct CertReloader {
+    cert: PathBuf,
+    key: PathBuf,
+    current: ArcSwap<CertifiedKey>,
+}
+
+impl CertReloader {
+    pub fn load(tls: &TlsConfig) -> Result<Self, TlsError> {
+        let (cert, key) = (PathBuf::from(&tls.cert), PathBuf::from(&tls.key));
+        let current = ArcSwap::from_pointee(load_certified_key(&cert, &key)?);
+        Ok(Self { cert, key, current })
+    }
+
+    pub fn reload(&self) -> Result<(), TlsError> {
+        self.current.store(Arc::new(load_certified_key(&self.cert, &self.key)?));
+        Ok(())
+    }
+
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.current.load_full()
+    }
+
+    pub fn server_config(self: &Arc<Self>) -> rustls::ServerConfig {
+        let mut config = rustls::ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .expect("ring provider supports the default protocol versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        config
+    }
+}
+
+impl ResolvesServerCert for CertReloader {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+// 收到 SIGHUP 时重新加载所有 TLS 证书
+#[cfg(unix)]
+pub fn reload_on_sighup(reloaders: Vec<Arc<CertReloader>>) -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    if reloaders.is_empty() {
+        return Ok(());
+    }
+    let mut hangup = signal(SignalKind::hangup())?;
+    actix_web::rt::spawn(async move {
+        while hangup.recv().await.is_some() {
+            for reloader in &reloaders {
+                match reloader.reload() {
+                    Ok(()) => info!("Reloaded TLS certificate {}", reloader.cert.display()),
+                    Err(e) => error!("Keeping previous TLS certificate: {}", e),
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_reloaders: Vec<Arc<CertReloader>>) -> io::Result<()> {
+    Ok(())
+}
+
+// 删除上次运行残留的套接字文件；路径存在但不是套接字时报错，避免误删
+#[cfg(unix)]
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+pub fn set_socket_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
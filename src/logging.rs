@@ -0,0 +1,189 @@
+// 日志格式与访问日志
+//
+// LOG_FORMAT=json 时每行输出一个 JSON 对象，便于日志管道解析；默认为文本格式。
+// 两种格式都会带上当前请求的 request_id，以及日志调用中的键值字段
+// （例如 `info!(results = n; "...")`）。访问日志由本模块的中间件输出，
+// 包含路由模板、状态码、耗时和处理函数记录的结果数量。
+
+use std::cell::Cell;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use log::kv::{Error as KvError, Key, Value, VisitSource};
+use log::{info, Record};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+
+use crate::request_id;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format {:?} (expected \"text\" or \"json\")", other)),
+        }
+    }
+}
+
+// 将日志记录的键值字段收集为 JSON
+struct Fields(Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+        let value = if let Some(v) = value.to_u64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_i64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_f64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_bool() {
+            Json::from(v)
+        } else {
+            Json::from(value.to_string())
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+fn fields(record: &Record) -> Map<String, Json> {
+    let mut fields = Fields(Map::new());
+    // 字段收集失败时仍输出消息本身
+    let _ = record.key_values().visit(&mut fields);
+    fields.0
+}
+
+// JSON 格式的一行日志；字段名与固定字段冲突时保留固定字段
+pub fn json_line(ts: &str, record: &Record) -> Json {
+    let mut line = Map::new();
+    line.insert("ts".into(), Json::from(ts));
+    line.insert("level".into(), Json::from(record.level().as_str()));
+    line.insert("target".into(), Json::from(record.target()));
+    line.insert("msg".into(), Json::from(record.args().to_string()));
+    if let Some(id) = request_id::current() {
+        line.insert("request_id".into(), Json::from(id));
+    }
+    for (key, value) in fields(record) {
+        line.entry(key).or_insert(value);
+    }
+    Json::Object(line)
+}
+
+pub fn init(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::new().default_filter_or("info"));
+
+    match format {
+        LogFormat::Json => builder.format(|buf, record| {
+            let ts = buf.timestamp_millis().to_string();
+            writeln!(buf, "{}", json_line(&ts, record))
+        }),
+        LogFormat::Text => builder.format(|buf, record| {
+            write!(buf, "[{} {:<5} {}]", buf.timestamp_millis(), record.level(), record.target())?;
+            if let Some(id) = request_id::current() {
+                write!(buf, " [{}]", id)?;
+            }
+            write!(buf, " {}", record.args())?;
+            for (key, value) in fields(record) {
+                write!(buf, " {}={}", key, value)?;
+            }
+            writeln!(buf)
+        }),
+    };
+
+    builder.init();
+}
+
+tokio::task_local! {
+    static RESULT_COUNT: Cell<Option<usize>>;
+}
+
+// 记录本次请求返回的结果数量，写入访问日志；不在请求处理期间时忽略
+pub fn record_results(count: usize) {
+    let _ = RESULT_COUNT.try_with(|cell| cell.set(Some(count)));
+}
+
+// 访问日志；需位于 request_id 中间件之内，才能带上请求 id
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+
+    let (res, results) = RESULT_COUNT
+        .scope(Cell::new(None), async {
+            let res = next.call(req).await;
+            (res, RESULT_COUNT.with(Cell::get))
+        })
+        .await;
+    let res = res?;
+
+    let route = res.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let status = res.status().as_u16();
+    match results {
+        Some(results) => info!(
+            target: "access", method = method.as_str(), path = path.as_str(), route = route.as_str(),
+            status = status, latency_ms = latency_ms, results = results;
+            "{} {} {} {:.1}ms", method, path, status, latency_ms
+        ),
+        None => info!(
+            target: "access", method = method.as_str(), path = path.as_str(), route = route.as_str(),
+            status = status, latency_ms = latency_ms;
+            "{} {} {} {:.1}ms", method, path, status, latency_ms
+        ),
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_log_line() {
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+
+        let fields: &[(&str, Value)] = &[
+            ("route", Value::from("/api/v1/search")),
+            ("status", Value::from(200u16)),
+            ("latency_ms", Value::from(1.5f64)),
+            ("level", Value::from("spoofed")),
+        ];
+        let line = json_line(
+            "2024-01-01T00:00:00.000Z",
+            &Record::builder()
+                .args(format_args!("GET /api/v1/search 200"))
+                .level(log::Level::Info)
+                .target("access")
+                .key_values(&fields)
+                .build(),
+        );
+
+        assert_eq!(line["msg"], "GET /api/v1/search 200");
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], "access");
+        assert_eq!(line["route"], "/api/v1/search");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["latency_ms"], 1.5);
+        assert!(line.get("request_id").is_none());
+    }
+}
@@ -1,11 +1,42 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Result, middleware, error::ResponseError};
-use actix_cors::Cors;
-use actix_files::Files;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Result, middleware, error::ResponseError};
 use rusqlite::{Connection, params, Error as SqliteError, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
 use thiserror::Error;
+use utoipa::{IntoParams, ToSchema};
 use log::{error, warn, info, debug};
+use rand::seq::SliceRandom;
+
+mod admin;
+mod assets;
+mod catalog;
+mod config;
+mod compression;
+mod content;
+mod cors;
+mod docset;
+mod export;
+mod health;
+mod listener;
+mod notes;
+mod openapi;
+mod http_cache;
+mod logging;
+mod man;
+mod metrics;
+mod overlay;
+mod rate_limit;
+mod request_id;
+mod response_cache;
+mod revisions;
+mod shutdown;
+mod suggestions;
+mod tldr;
+mod users;
+
+use overlay::Source;
 
 // 自定义错误类型
 #[derive(Error, Debug)]
@@ -16,49 +47,128 @@ enum AppError {
     #[error("Command not found")]
     CommandNotFound,
 
+    #[error("Category '{0}' not found")]
+    CategoryNotFound(String),
+
+    #[error("Search query cannot be empty")]
+    EmptyQuery,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("Too many requests, retry after {0} seconds")]
+    RateLimited(u64),
+
+    #[error("{0} are not enabled")]
+    FeatureDisabled(String),
+
     #[error("Internal server error: {0}")]
     InternalError(String),
 }
 
+impl AppError {
+    // 稳定的机器可读错误码，客户端应依赖它而不是 message
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::DatabaseError(_) => "DATABASE_ERROR",
+            AppError::CommandNotFound => "COMMAND_NOT_FOUND",
+            AppError::CategoryNotFound(_) => "CATEGORY_NOT_FOUND",
+            AppError::EmptyQuery => "EMPTY_QUERY",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Unauthorized => "UNAUTHORIZED",
+            AppError::InvalidInput(_) => "INVALID_INPUT",
+            AppError::RateLimited(_) => "RATE_LIMITED",
+            AppError::FeatureDisabled(_) => "FEATURE_DISABLED",
+            AppError::InternalError(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    // 返回给客户端的详细信息；数据库错误只记录日志，不暴露细节
+    fn detail(&self) -> String {
+        match self {
+            AppError::DatabaseError(_) => "Database error".to_string(),
+            AppError::NotFound(detail) | AppError::InvalidInput(detail) | AppError::InternalError(detail) => detail.clone(),
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
-        error!("Application error: {}", self);
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+
+        match self {
+            AppError::CommandNotFound | AppError::CategoryNotFound(_) | AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::InvalidInput(_) | AppError::EmptyQuery => StatusCode::BAD_REQUEST,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::FeatureDisabled(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::DatabaseError(_) | AppError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 
-        let (status_code, message) = match self {
-            AppError::CommandNotFound => (actix_web::http::StatusCode::NOT_FOUND, "Command not found"),
-            AppError::InvalidInput(_) => (actix_web::http::StatusCode::BAD_REQUEST, "Invalid input"),
-            AppError::DatabaseError(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "Database error"),
-            AppError::InternalError(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
-        };
+    fn error_response(&self) -> HttpResponse {
+        let request_id = request_id::current();
+        error!("Application error [{}]: {}", request_id.as_deref().unwrap_or("-"), self);
 
-        HttpResponse::build(status_code)
-            .json(ApiResponse::<String> {
-                success: false,
-                data: None,
-                message: Some(message.to_string()),
-            })
+        let mut res = HttpResponse::build(self.status_code());
+        if let AppError::RateLimited(retry_after) = self {
+            res.insert_header((actix_web::http::header::RETRY_AFTER, retry_after.to_string()));
+        }
+        res.json(ErrorResponse {
+            success: false,
+            code: self.code(),
+            message: self.detail(),
+            request_id,
+        })
     }
 }
 
 // 数据模型
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct Command {
     id: i64,
     name: String,
     #[serde(serialize_with = "serialize_category")]
+    #[schema(value_type = String, example = "Network")]
     category: i64,
     description: String,
+    #[serde(default)]
+    source: Source,
+}
+
+impl Command {
+    // 从 `SELECT id, name, category, description` 查询结果构建命令
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Command {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            description: row.get(3)?,
+            source: Source::Base,
+        })
+    }
 }
 
 fn serialize_category<S>(category: &i64, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
-    // 将数字分类转换为字符串显示 - 基于Kotlin项目的分类
-    let category_str = match category {
+    serializer.serialize_str(category_name(*category))
+}
+
+// 导入（man、tldr）新建的命令所属分类
+const MISCELLANEOUS_CATEGORY: i64 = 1;
+
+// 将数字分类转换为字符串显示 - 基于Kotlin项目的分类
+fn category_name(category: i64) -> &'static str {
+    match category {
         1 => "Miscellaneous",
         2 => "System information",
         3 => "System control",
@@ -83,36 +193,46 @@ where
         22 => "Pico Texteditor",
         23 => "Micro Texteditor",
         _ => "Other",
-    };
-    serializer.serialize_str(category_str)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct CommandDetail {
     id: i64,
     name: String,
     #[serde(serialize_with = "serialize_category")]
+    #[schema(value_type = String, example = "Network")]
     category: i64,
     description: String,
     sections: Vec<CommandSection>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tldr: Option<String>, // 添加TLDR字段，类似Kotlin项目
+    #[serde(default)]
+    source: Source,
+    // 审核通过的团队示例，独立于上游 TLDR
+    #[serde(default)]
+    examples: Vec<suggestions::CommandExample>,
+    // 当前用户的私人笔记，仅对所有者返回
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<notes::Note>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct CommandSection {
     title: String,
     content: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct Tip {
     id: i64,
     title: String,
     sections: Vec<TipSection>,
+    #[serde(default)]
+    source: Source,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct TipSection {
     #[serde(rename = "type")]
     section_type: i64,
@@ -122,7 +242,7 @@ struct TipSection {
 }
 
 // 基础分类模型 - 来自Kotlin项目的BasicCategory
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct BasicCategory {
     id: i64,
     title: String,
@@ -131,6 +251,26 @@ struct BasicCategory {
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     icon: Option<String>,
+    #[serde(default)]
+    source: Source,
+}
+
+// 分类下的分组 - 来自Kotlin项目的BasicGroup
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct BasicGroup {
+    id: i64,
+    category_id: i64,
+    position: i64,
+    description: String,
+}
+
+// 分组下的一行命令 - 来自Kotlin项目的BasicCommand
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct BasicCommand {
+    id: i64,
+    group_id: i64,
+    command: String,
+    mans: String,
 }
 
 // 搜索结果模型 (保留供将来使用)
@@ -144,7 +284,7 @@ struct SearchResult {
 }
 
 // 应用统计模型
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 struct AppStats {
     total_commands: i64,
     total_categories: i64,
@@ -152,147 +292,353 @@ struct AppStats {
     total_basic_categories: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct SearchQuery {
+    /// Search term
     q: String,
+    /// Numeric command category
     category: Option<String>,
+    /// Maximum number of results (default 50, at most 100)
     limit: Option<i64>,
+    /// Only search commands the caller has private notes on (requires a user token)
+    mine: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     message: Option<String>,
 }
 
+// 错误响应体
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorResponse {
+    success: bool,
+    #[schema(example = "COMMAND_NOT_FOUND")]
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
 // 数据库管理
 struct AppState {
     db: Mutex<Connection>,
+    // 可选的覆盖数据库（团队内部命令）
+    overlay: Option<Mutex<Connection>>,
+    // 管理员写入 API 的 bearer token，未设置时写入 API 关闭
+    admin_token: Option<String>,
+    // 关闭时注册用户需要管理员 token
+    open_registration: bool,
+    // 启动时数据库文件的内容哈希与内容表写入代数，用于 ETag 和各级缓存
+    content: content::ContentTracker,
+    // 可选的内存目录快照（PRELOAD_CATALOG）
+    catalog: Option<catalog::CatalogHandle>,
+    // 停机状态与停机钩子
+    shutdown: shutdown::Shutdown,
+    // 预先压缩的完整命令列表，内容版本变化时重建
+    command_index: compression::Versioned<CommandIndex>,
+}
+
+// /commands 的响应体
+struct CommandIndex {
+    count: usize,
+    body: compression::Precompressed,
 }
 
 impl AppState {
-    fn new(db_path: &str) -> Result<Self, AppError> {
+    fn new(db_path: &str, overlay_path: Option<&str>) -> Result<Self, AppError> {
         info!("Initializing database connection to: {}", db_path);
 
-        let conn = Connection::open(db_path)
+        let mut conn = Connection::open(db_path)
             .map_err(|e| {
                 error!("Failed to open database at {}: {}", db_path, e);
                 AppError::DatabaseError(e)
             })?;
+        conn.profile(Some(metrics::profile_query));
 
         // 验证数据库schema
         Self::validate_schema(&conn)?;
 
+        let mut hashed = vec![db_path];
+        hashed.extend(overlay_path);
+        let content = content::ContentTracker::new(http_cache::content_hash(&hashed));
+
+        let overlay = match overlay_path {
+            Some(path) => {
+                let conn = Self::open_overlay(path)?;
+                content.track(&conn);
+                Some(Mutex::new(conn))
+            }
+            None => None,
+        };
+
         info!("Database connection established successfully");
         Ok(Self {
             db: Mutex::new(conn),
+            overlay,
+            admin_token: None,
+            open_registration: true,
+            content,
+            catalog: None,
+            shutdown: Default::default(),
+            command_index: Default::default(),
         })
     }
 
-    fn validate_schema(conn: &Connection) -> Result<(), AppError> {
-        let tables = ["Command", "CommandSection", "Tip", "TipSection", "BasicCategory", "BasicGroup", "BasicCommand"];
+    // 启用管理员写入 API；写入需要可写的覆盖库
+    fn with_admin_token(mut self, token: String) -> Result<Self, AppError> {
+        if self.overlay.is_none() {
+            return Err(AppError::InvalidInput(
+                "ADMIN_TOKEN requires OVERLAY_DATABASE_PATH to be set".to_string(),
+            ));
+        }
 
-        for table in &tables {
-            let count: Result<i64, _> = conn.query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
-                [table],
-                |row| row.get(0)
-            );
+        info!("Admin write API enabled");
+        self.admin_token = Some(token);
+        Ok(self)
+    }
 
-            match count {
-                Ok(0) => {
-                    warn!("Table '{}' not found in database", table);
-                }
-                Ok(_) => {
-                    debug!("Table '{}' found in database", table);
-                }
-                Err(e) => {
-                    error!("Error validating table '{}': {}", table, e);
-                    return Err(AppError::DatabaseError(e));
-                }
+    // 启动时预加载目录快照，读取端点不再访问数据库
+    fn with_catalog(mut self) -> Result<Self, AppError> {
+        self.catalog = Some(catalog::CatalogHandle::load(&self)?);
+        Ok(self)
+    }
+
+    // 当前目录快照；未启用预加载或重新构建失败时为 None
+    fn catalog(&self) -> Option<std::sync::Arc<catalog::Catalog>> {
+        self.catalog.as_ref().and_then(|handle| handle.snapshot(self))
+    }
+
+    fn open_overlay(overlay_path: &str) -> Result<Connection, AppError> {
+        info!("Initializing overlay database connection to: {}", overlay_path);
+
+        let mut conn = Connection::open(overlay_path)
+            .map_err(|e| {
+                error!("Failed to open overlay database at {}: {}", overlay_path, e);
+                AppError::DatabaseError(e)
+            })?;
+        conn.profile(Some(metrics::profile_query));
+
+        // 覆盖库同时作为可写存储；只读挂载时写入功能不可用
+        if let Err(e) = ensure_store_schema(&conn) {
+            warn!("Overlay database is not writable, write features are disabled: {}", e);
+        }
+
+        for table in ["Command", "CommandSection", "BasicCategory"] {
+            if overlay::table_exists(&conn, table)? {
+                debug!("Table '{}' found in overlay database", table);
+            } else {
+                warn!("Table '{}' not found in overlay database", table);
+            }
+        }
+
+        Ok(conn)
+    }
+
+    fn validate_schema(conn: &Connection) -> Result<(), AppError> {
+        let missing = missing_tables(conn, REQUIRED_TABLES).map_err(|e| {
+            error!("Error validating database schema: {}", e);
+            AppError::DatabaseError(e)
+        })?;
+
+        for table in REQUIRED_TABLES {
+            if missing.contains(table) {
+                warn!("Table '{}' not found in database", table);
+            } else {
+                debug!("Table '{}' found in database", table);
             }
         }
 
         Ok(())
     }
+
+    // 数据库内容版本：启动时的文件哈希加上覆盖库内容表的写入代数（见 content 模块），
+    // data_version 检测其他进程的写入。预加载目录时读取不访问数据库，目录快照也只随
+    // 写入代数重建，因此版本只取哈希与写入代数，不加锁。
+    fn content_version(&self) -> Result<String, AppError> {
+        if self.catalog.is_some() {
+            return Ok(format!("{}:{}", self.content.hash(), self.content.generation()));
+        }
+
+        let data_version = |conn: &Connection| -> Result<i64, AppError> {
+            Ok(conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+        };
+
+        let base = data_version(&*self.lock_db()?)?;
+        let overlay = match self.lock_overlay()? {
+            Some(conn) => data_version(&conn)?.to_string(),
+            None => String::new(),
+        };
+        Ok(format!("{}:{}:{}.{}", self.content.hash(), self.content.generation(), base, overlay))
+    }
+
+    // 完整命令列表（/commands），按内容版本缓存，生成时压缩一次
+    fn command_index(&self) -> Result<std::sync::Arc<CommandIndex>, AppError> {
+        let version = self.content_version()?;
+        self.command_index.get_or_try_init(&version, || {
+            let started = Instant::now();
+            let serialize = |commands: &[Command]| {
+                serde_json::to_vec(&ApiResponse { success: true, data: Some(commands), message: None })
+                    .map_err(|e| AppError::InternalError(format!("Failed to serialize command index: {}", e)))
+            };
+            let (count, body) = match self.catalog() {
+                Some(catalog) => (catalog.commands().len(), serialize(catalog.commands())?),
+                None => {
+                    let commands = self.load_all_commands()?;
+                    (commands.len(), serialize(&commands)?)
+                }
+            };
+            let index = CommandIndex { count, body: compression::Precompressed::new(body) };
+            info!(
+                "Built command index: {} commands, {} bytes in {:.1}ms",
+                count,
+                index.body.identity().len(),
+                started.elapsed().as_secs_f64() * 1000.0
+            );
+            Ok(index)
+        })
+    }
+
+    fn load_all_commands(&self) -> Result<Vec<Command>, AppError> {
+        let sql = "SELECT id, name, category, description FROM Command ORDER BY name";
+
+        let mut commands = {
+            let conn = self.lock_db()?;
+            query_commands(&conn, sql, [], "all commands")?
+        };
+        if let Some(overlay) = self.lock_overlay()? {
+            let shadowed = overlay_command_names(&overlay)?;
+            let extra = query_commands(&overlay, sql, [], "overlay all commands")?;
+            commands = overlay::merge_commands(commands, extra, &shadowed);
+            commands.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        Ok(commands)
+    }
+
+    fn lock_db(&self) -> Result<MutexGuard<'_, Connection>, AppError> {
+        let started = Instant::now();
+        let guard = self.db.lock().map_err(|e| {
+            error!("Failed to acquire database lock: {}", e);
+            AppError::InternalError("Database lock error".to_string())
+        });
+        metrics::metrics().observe_lock_wait("base", started.elapsed());
+        guard
+    }
+
+    fn lock_overlay(&self) -> Result<Option<MutexGuard<'_, Connection>>, AppError> {
+        match &self.overlay {
+            Some(overlay) => {
+                let started = Instant::now();
+                let guard = overlay.lock().map(Some).map_err(|e| {
+                    error!("Failed to acquire overlay database lock: {}", e);
+                    AppError::InternalError("Database lock error".to_string())
+                });
+                metrics::metrics().observe_lock_wait("overlay", started.elapsed());
+                guard
+            }
+            None => Ok(None),
+        }
+    }
 }
 
-// API 端点
+// 上游数据库必须包含的表
+const REQUIRED_TABLES: &[&str] = &["Command", "CommandSection", "Tip", "TipSection", "BasicCategory", "BasicGroup", "BasicCommand"];
 
-// 获取应用统计信息
-async fn get_stats(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    info!("Fetching application statistics");
+// 返回 `tables` 中数据库缺少的表
+fn missing_tables(conn: &Connection, tables: &[&'static str]) -> Result<Vec<&'static str>, SqliteError> {
+    let mut missing = Vec::new();
+    for table in tables {
+        if !overlay::table_exists(conn, table)? {
+            missing.push(*table);
+        }
+    }
+    Ok(missing)
+}
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+// 初始化可写存储（覆盖库）所需的全部表
+fn ensure_store_schema(conn: &Connection) -> Result<(), SqliteError> {
+    overlay::ensure_schema(conn)?;
+    revisions::ensure_schema(conn)?;
+    suggestions::ensure_schema(conn)?;
+    users::ensure_schema(conn)?;
+    notes::ensure_schema(conn)
+}
 
-    // 统计命令数量
-    let total_commands: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM Command",
-        [],
-        |row| row.get(0)
-    ).map_err(|e| {
-        error!("Failed to count commands: {}", e);
-        AppError::DatabaseError(e)
-    })?;
+// 查询辅助函数
 
-    // 统计分类数量（已弃用 - 使用BasicCategory数量代替）
-    let _total_categories: i64 = conn.query_row(
-        "SELECT COUNT(DISTINCT category) FROM Command",
-        [],
-        |row| row.get(0)
-    ).map_err(|e| {
-        error!("Failed to count categories: {}", e);
+// 执行返回 `id, name, category, description` 的命令查询
+fn query_commands<P: rusqlite::Params>(conn: &Connection, sql: &str, params: P, what: &str) -> Result<Vec<Command>, AppError> {
+    let mut stmt = conn.prepare(sql).map_err(|e| {
+        error!("Failed to prepare {} query: {}", what, e);
         AppError::DatabaseError(e)
     })?;
 
-    // 统计提示数量
-    let total_tips: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM Tip",
-        [],
-        |row| row.get(0)
-    ).map_err(|e| {
-        error!("Failed to count tips: {}", e);
-        AppError::DatabaseError(e)
-    })?;
+    let commands = stmt
+        .query_map(params, Command::from_row)
+        .map_err(|e| {
+            error!("Failed to execute {} query: {}", what, e);
+            AppError::DatabaseError(e)
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
 
-    // 统计基础分类数量
-    let total_basic_categories: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM BasicCategory",
-        [],
-        |row| row.get(0)
-    ).map_err(|e| {
-        error!("Failed to count basic categories: {}", e);
+    Ok(commands)
+}
+
+// 获取覆盖库中所有命令名称，用于遮蔽上游同名命令
+fn overlay_command_names(overlay: &Connection) -> Result<HashSet<String>, AppError> {
+    let mut stmt = overlay.prepare("SELECT name FROM Command").map_err(|e| {
+        error!("Failed to prepare overlay command names query: {}", e);
         AppError::DatabaseError(e)
     })?;
 
-    info!("Stats: {} commands, {} categories, {} tips, {} basic categories",
-          total_commands, total_basic_categories, total_tips, total_basic_categories);
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| {
+            error!("Failed to execute overlay command names query: {}", e);
+            AppError::DatabaseError(e)
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
 
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(AppStats {
-            total_commands,
-            total_categories: total_basic_categories, // 使用实际的basic类别数量
-            total_tips,
-            total_basic_categories,
-        }),
-        message: None,
-    }))
+    Ok(names)
 }
 
-// 获取详细的分类信息（包含描述和图标） - 使用真实的BasicCategory数据
-async fn get_categories_detailed(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    info!("Fetching detailed categories from BasicCategory table");
-
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+// 根据标题返回分类描述（基于Kotlin项目）
+fn category_description(title: &str) -> Option<String> {
+    let description = match title {
+        "One-liners" => "Useful linux command line one liners",
+        "System information" => "System and battery/cpu/memory/disk usage info on Linux",
+        "System control" => "Lock, unlock, start/stop bluetooth/wifi, shutdown, reboot system",
+        "Users & Groups" => "Create, delete, user, group, list, info",
+        "Files & Folders" => "File and directory operations",
+        "Input" => "Move, click, mouse, type, text, xdotool, ydotool, read, copy, clipboard",
+        "Printing" => "Printer management and printing commands",
+        "JSON" => "JSON processing and manipulation tools",
+        "Network" => "Network configuration and tools",
+        "Search & Find" => "Search and find files and content",
+        "GIT" => "Git version control commands",
+        "SSH" => "SSH connection and key management",
+        "Video & Audio" => "Video and audio processing tools",
+        "Package manager" => "Package management commands",
+        "Hacking tools" => "Security testing and hacking tools",
+        "Terminal games" => "Games that run in the terminal",
+        "Crypto currencies" => "Cryptocurrency related commands",
+        "VIM Texteditor" => "VIM text editor commands and shortcuts",
+        "Emacs Texteditor" => "Emacs text editor commands and shortcuts",
+        "Nano Texteditor" => "Nano text editor commands and shortcuts",
+        "Pico Texteditor" => "Pico text editor commands and shortcuts",
+        "Micro Texteditor" => "Micro text editor commands and shortcuts",
+        _ => return None,
+    };
+    Some(description.to_string())
+}
 
+// 读取 BasicCategory 表
+fn query_basic_categories(conn: &Connection) -> Result<Vec<BasicCategory>, AppError> {
     let mut stmt = conn
         .prepare("SELECT id, title, position FROM BasicCategory ORDER BY position")
         .map_err(|e| {
@@ -300,39 +646,14 @@ async fn get_categories_detailed(data: web::Data<AppState>) -> Result<HttpRespon
             AppError::DatabaseError(e)
         })?;
 
-    let categories: Vec<BasicCategory> = stmt
+    let categories = stmt
         .query_map([], |row| {
             let id: i64 = row.get(0)?;
             let title: String = row.get(1)?;
             let position: i64 = row.get(2)?;
 
             // 根据Kotlin项目添加描述和图标
-            let description = match title.as_str() {
-                "One-liners" => Some("Useful linux command line one liners".to_string()),
-                "System information" => Some("System and battery/cpu/memory/disk usage info on Linux".to_string()),
-                "System control" => Some("Lock, unlock, start/stop bluetooth/wifi, shutdown, reboot system".to_string()),
-                "Users & Groups" => Some("Create, delete, user, group, list, info".to_string()),
-                "Files & Folders" => Some("File and directory operations".to_string()),
-                "Input" => Some("Move, click, mouse, type, text, xdotool, ydotool, read, copy, clipboard".to_string()),
-                "Printing" => Some("Printer management and printing commands".to_string()),
-                "JSON" => Some("JSON processing and manipulation tools".to_string()),
-                "Network" => Some("Network configuration and tools".to_string()),
-                "Search & Find" => Some("Search and find files and content".to_string()),
-                "GIT" => Some("Git version control commands".to_string()),
-                "SSH" => Some("SSH connection and key management".to_string()),
-                "Video & Audio" => Some("Video and audio processing tools".to_string()),
-                "Package manager" => Some("Package management commands".to_string()),
-                "Hacking tools" => Some("Security testing and hacking tools".to_string()),
-                "Terminal games" => Some("Games that run in the terminal".to_string()),
-                "Crypto currencies" => Some("Cryptocurrency related commands".to_string()),
-                "VIM Texteditor" => Some("VIM text editor commands and shortcuts".to_string()),
-                "Emacs Texteditor" => Some("Emacs text editor commands and shortcuts".to_string()),
-                "Nano Texteditor" => Some("Nano text editor commands and shortcuts".to_string()),
-                "Pico Texteditor" => Some("Pico text editor commands and shortcuts".to_string()),
-                "Micro Texteditor" => Some("Micro text editor commands and shortcuts".to_string()),
-                _ => None,
-            };
-
+            let description = category_description(&title);
             let icon = None; // Frontend uses Lucide icons, not custom SVG files
 
             Ok(BasicCategory {
@@ -341,6 +662,7 @@ async fn get_categories_detailed(data: web::Data<AppState>) -> Result<HttpRespon
                 position,
                 description,
                 icon,
+                source: Source::Base,
             })
         })
         .map_err(|e| {
@@ -350,133 +672,386 @@ async fn get_categories_detailed(data: web::Data<AppState>) -> Result<HttpRespon
         .filter_map(|r| r.ok())
         .collect();
 
-    info!("Found {} detailed categories", categories.len());
-
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(categories),
-        message: None,
-    }))
+    Ok(categories)
 }
 
-// 获取命令建议（自动完成）
-async fn get_command_suggestions(
-    query: web::Query<SearchQuery>,
-    data: web::Data<AppState>,
-) -> Result<HttpResponse, AppError> {
-    if query.q.trim().is_empty() {
-        return Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(vec![] as Vec<String>),
-            message: None,
-        }));
-    }
+// 读取合并后的分类（上游 + 覆盖库）
+fn merged_basic_categories(data: &AppState) -> Result<Vec<BasicCategory>, AppError> {
+    let base = {
+        let conn = data.lock_db()?;
+        query_basic_categories(&conn)?
+    };
 
-    info!("Fetching command suggestions for: {}", query.q);
+    let overlay = match data.lock_overlay()? {
+        Some(conn) if overlay::table_exists(&conn, "BasicCategory")? => query_basic_categories(&conn)?,
+        _ => return Ok(base),
+    };
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+    Ok(overlay::merge_categories(base, overlay))
+}
 
-    let search_term = format!("{}%", query.q);
-    let mut stmt = conn
-        .prepare("SELECT DISTINCT name FROM Command WHERE name LIKE ?1 ORDER BY name LIMIT 10")
-        .map_err(|e| {
-            error!("Failed to prepare suggestions query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
+// 合并后的条目数：上游数量 - 被遮蔽数量 + 覆盖库数量
+fn merged_count(base: &Connection, overlay: &Connection, table: &str, key: &str) -> Result<i64, AppError> {
+    if !overlay::table_exists(overlay, table)? {
+        return Ok(base.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?);
+    }
 
-    let suggestions: Vec<String> = stmt
-        .query_map(params![&search_term], |row| {
-            Ok(row.get::<_, String>(0)?)
-        })
-        .map_err(|e| {
-            error!("Failed to execute suggestions query: {}", e);
-            AppError::DatabaseError(e)
-        })?
+    let mut stmt = overlay.prepare(&format!("SELECT DISTINCT {} FROM {}", key, table))?;
+    let keys: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
         .filter_map(|r| r.ok())
         .collect();
 
-    debug!("Found {} suggestions for query: {}", suggestions.len(), query.q);
+    let overlay_count: i64 = overlay.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+    let base_count: i64 = base.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
 
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(suggestions),
-        message: None,
-    }))
+    let mut shadowed = 0;
+    let sql = format!("SELECT COUNT(*) FROM {} WHERE {} = ?1", table, key);
+    for key in &keys {
+        shadowed += base.query_row(&sql, params![key], |row| row.get::<_, i64>(0))?;
+    }
+
+    Ok(base_count - shadowed + overlay_count)
 }
 
-// 获取热门命令（基于某种算法）
-async fn get_popular_commands(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    info!("Fetching popular commands");
+// 加载命令详情（不含遮蔽处理）
+fn load_command_detail(conn: &Connection, command_id: i64) -> Result<Option<CommandDetail>, AppError> {
+    // 获取命令基本信息
+    let mut stmt = conn
+        .prepare("SELECT id, name, category, description
+                  FROM Command
+                  WHERE id = ?1")
+        .map_err(|e| {
+            error!("Failed to prepare command query: {}", e);
+            AppError::DatabaseError(e)
+        })?;
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+    let cmd = match stmt.query_row(params![command_id], Command::from_row).optional() {
+        Ok(Some(cmd)) => cmd,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            error!("Database error fetching command {}: {}", command_id, e);
+            return Err(AppError::DatabaseError(e));
+        }
+    };
 
-    // 获取一些常用命令（这里可以根据实际使用统计来调整）
-    let mut stmt = conn
-        .prepare("SELECT id, name, category, description FROM Command WHERE category IN (1,3,5,10) ORDER BY RANDOM() LIMIT 20")
+    // 获取命令章节，排除NAME章节（根据Kotlin项目的实现）
+    let mut sect_stmt = conn
+        .prepare("SELECT title, content FROM CommandSection WHERE command_id = ?1 AND title != 'NAME' ORDER BY id")
         .map_err(|e| {
-            error!("Failed to prepare popular commands query: {}", e);
+            error!("Failed to prepare sections query: {}", e);
             AppError::DatabaseError(e)
         })?;
 
-    let commands: Vec<Command> = stmt
-        .query_map([], |row| {
-            Ok(Command {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                description: row.get(3)?,
+    let sections: Vec<CommandSection> = sect_stmt
+        .query_map(params![command_id], |row| {
+            Ok(CommandSection {
+                title: row.get(0)?,
+                content: row.get(1)?,
             })
         })
         .map_err(|e| {
-            error!("Failed to execute popular commands query: {}", e);
+            error!("Failed to execute sections query: {}", e);
             AppError::DatabaseError(e)
         })?
         .filter_map(|r| r.ok())
         .collect();
 
-    info!("Found {} popular commands", commands.len());
-
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(commands),
-        message: None,
+    // TLDR章节（如果存在）已包含在上面的章节中，无需再次查询
+    let tldr: Option<String> = sections
+        .iter()
+        .find(|s| s.title == "TLDR")
+        .map(|s| s.content.clone());
+
+    Ok(Some(CommandDetail {
+        id: cmd.id,
+        name: cmd.name,
+        category: cmd.category,
+        description: cmd.description,
+        sections,
+        tldr,
+        source: Source::Base,
+        examples: vec![],
+        notes: vec![],
     }))
 }
 
-// API 端点
+// 与 search_in 中 SQL 相同的相关性规则（LIKE 不区分大小写）
+fn search_relevance(name: &str, description: &str, term: &str) -> i64 {
+    let lowered = term.to_lowercase();
+    let name_lower = name.to_lowercase();
+    let description = description.to_lowercase();
+
+    if name == term {
+        100
+    } else if name_lower.starts_with(&lowered) {
+        50
+    } else if name_lower.contains(&lowered) {
+        30
+    } else if description.starts_with(&lowered) {
+        20
+    } else if description.contains(&lowered) {
+        10
+    } else {
+        0
+    }
+}
 
-// 获取所有分类
-async fn get_categories(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    info!("Fetching all categories");
+// 在单个数据库中按相关性搜索命令，返回 (命令, 相关性)
+fn search_in(conn: &Connection, term: &str, category: Option<&String>, limit: i64) -> Result<Vec<(Command, i64)>, AppError> {
+    // 改进搜索查询：按相关性排序
+    let sql = if category.is_some() {
+        "SELECT id, name, category, description,
+               CASE
+                   WHEN name = ?1 THEN 100  -- 精确匹配名称，最高优先级
+                   WHEN name LIKE ?2 THEN 50  -- 名称开头匹配
+                   WHEN name LIKE ?3 THEN 30  -- 名称包含匹配
+                   WHEN description LIKE ?2 THEN 20  -- 描述开头匹配
+                   WHEN description LIKE ?3 THEN 10  -- 描述包含匹配
+                   ELSE 0
+               END as relevance
+         FROM Command
+         WHERE (name LIKE ?3 OR description LIKE ?3) AND category = ?4
+         ORDER BY relevance DESC, name ASC
+         LIMIT ?5"
+    } else {
+        "SELECT id, name, category, description,
+               CASE
+                   WHEN name = ?1 THEN 100  -- 精确匹配名称，最高优先级
+                   WHEN name LIKE ?2 THEN 50  -- 名称开头匹配
+                   WHEN name LIKE ?3 THEN 30  -- 名称包含匹配
+                   WHEN description LIKE ?2 THEN 20  -- 描述开头匹配
+                   WHEN description LIKE ?3 THEN 10  -- 描述包含匹配
+                   ELSE 0
+               END as relevance
+         FROM Command
+         WHERE name LIKE ?3 OR description LIKE ?3
+         ORDER BY relevance DESC, name ASC
+         LIMIT ?4"
+    };
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
+    let exact_term = term;
+    let start_term = format!("{}%", exact_term);
+    let contain_term = format!("%{}%", exact_term);
+    debug!("Search SQL: {} with terms: exact='{}', start='{}', contain='{}'", sql, exact_term, start_term, contain_term);
+
+    let mut stmt = conn.prepare(sql).map_err(|e| {
+        error!("Failed to prepare search query: {}", e);
+        AppError::DatabaseError(e)
     })?;
 
+    let map_row = |row: &rusqlite::Row| Ok((Command::from_row(row)?, row.get::<_, i64>(4)?));
+
+    let commands = if let Some(cat) = category {
+        stmt.query_map(params![&exact_term, &start_term, &contain_term, cat, limit], map_row)
+            .map_err(|e| {
+                error!("Failed to execute category search query: {}", e);
+                AppError::DatabaseError(e)
+            })?
+            .filter_map(|r| r.ok())
+            .collect()
+    } else {
+        stmt.query_map(params![&exact_term, &start_term, &contain_term, limit], map_row)
+            .map_err(|e| {
+                error!("Failed to execute search query: {}", e);
+                AppError::DatabaseError(e)
+            })?
+            .filter_map(|r| r.ok())
+            .collect()
+    };
+
+    Ok(commands)
+}
+
+// 获取某个分类下的命令；分类不存在时返回 None
+fn category_commands(conn: &Connection, category_name: &str) -> Result<Option<Vec<Command>>, AppError> {
+    // First, find the BasicCategory ID
+    let mut category_stmt = conn
+        .prepare("SELECT id FROM BasicCategory WHERE title = ?1")
+        .map_err(|e| {
+            error!("Failed to prepare category lookup query: {}", e);
+            AppError::DatabaseError(e)
+        })?;
+
+    let category_id: Option<i64> = category_stmt
+        .query_row(params![category_name], |row| row.get::<_, i64>(0))
+        .optional()
+        .map_err(|e| {
+            error!("Failed to lookup category '{}': {}", category_name, e);
+            AppError::DatabaseError(e)
+        })?;
+
+    let category_id = match category_id {
+        Some(id) => {
+            debug!("Found BasicCategory '{}' with ID: {}", category_name, id);
+            id
+        }
+        None => return Ok(None),
+    };
+
+    // 覆盖库可能只有 BasicCategory 而没有分组数据
+    if !overlay::table_exists(conn, "BasicGroup")? || !overlay::table_exists(conn, "BasicCommand")? {
+        return Ok(Some(vec![]));
+    }
+
+    // Get commands through BasicGroup -> BasicCommand relationship
     let mut stmt = conn
-        .prepare("SELECT title FROM BasicCategory ORDER BY position")
+        .prepare("SELECT bc.id, bc.command, bc.mans, bg.description
+                  FROM BasicCommand bc
+                  JOIN BasicGroup bg ON bc.group_id = bg.id
+                  WHERE bg.category_id = ?1
+                  ORDER BY bc.command")
         .map_err(|e| {
-            error!("Failed to prepare categories query: {}", e);
+            error!("Failed to prepare basic commands by category query: {}", e);
             AppError::DatabaseError(e)
         })?;
 
-    let categories: Vec<String> = stmt
-        .query_map([], |row| row.get(0))
+    let commands: Vec<Command> = stmt
+        .query_map(params![category_id], |row| {
+            let id: i64 = row.get(0)?;
+            let command: String = row.get(1)?;
+            let _mans: String = row.get(2)?;
+            let description: String = row.get(3)?;
+
+            // Extract first line of command (before newline if any)
+            let command_name = command.lines().next().unwrap_or(&command).trim();
+
+            Ok(Command {
+                id,
+                name: command_name.to_string(),
+                category: 0, // Use 0 as placeholder for BasicCategory commands
+                description,
+                source: Source::Base,
+            })
+        })
         .map_err(|e| {
-            error!("Failed to execute categories query: {}", e);
+            error!("Failed to execute basic commands by category query: {}", e);
             AppError::DatabaseError(e)
         })?
         .filter_map(|r| r.ok())
         .collect();
 
-    info!("Found {} categories", categories.len());
+    info!("Found {} basic commands for category '{}' (ID: {})", commands.len(), category_name, category_id);
+
+    Ok(Some(commands))
+}
+
+// 统计命令、分类和提示数量
+fn compute_stats(data: &AppState) -> Result<AppStats, AppError> {
+    let conn = data.lock_db()?;
+    let overlay_conn = data.lock_overlay()?;
+
+    // 统计命令数量（合并覆盖库）
+    let total_commands: i64 = match overlay_conn.as_deref() {
+        Some(overlay) => merged_count(&conn, overlay, "Command", "name")?,
+        None => conn.query_row(
+            "SELECT COUNT(*) FROM Command",
+            [],
+            |row| row.get(0)
+        ).map_err(|e| {
+            error!("Failed to count commands: {}", e);
+            AppError::DatabaseError(e)
+        })?,
+    };
+
+    // 统计分类数量（已弃用 - 使用BasicCategory数量代替）
+    let _total_categories: i64 = conn.query_row(
+        "SELECT COUNT(DISTINCT category) FROM Command",
+        [],
+        |row| row.get(0)
+    ).map_err(|e| {
+        error!("Failed to count categories: {}", e);
+        AppError::DatabaseError(e)
+    })?;
+
+    // 统计提示数量（合并覆盖库）
+    let total_tips: i64 = match overlay_conn.as_deref() {
+        Some(overlay) => merged_count(&conn, overlay, "Tip", "title")?,
+        None => conn.query_row(
+            "SELECT COUNT(*) FROM Tip",
+            [],
+            |row| row.get(0)
+        ).map_err(|e| {
+            error!("Failed to count tips: {}", e);
+            AppError::DatabaseError(e)
+        })?,
+    };
+
+    // 统计基础分类数量（合并覆盖库）
+    let total_basic_categories: i64 = match overlay_conn.as_deref() {
+        Some(overlay) => merged_count(&conn, overlay, "BasicCategory", "title")?,
+        None => conn.query_row(
+            "SELECT COUNT(*) FROM BasicCategory",
+            [],
+            |row| row.get(0)
+        ).map_err(|e| {
+            error!("Failed to count basic categories: {}", e);
+            AppError::DatabaseError(e)
+        })?,
+    };
+
+    info!("Stats: {} commands, {} categories, {} tips, {} basic categories",
+          total_commands, total_basic_categories, total_tips, total_basic_categories);
+
+    Ok(AppStats {
+        total_commands,
+        total_categories: total_basic_categories, // 使用实际的basic类别数量
+        total_tips,
+        total_basic_categories,
+    })
+}
+
+// API 端点
+
+// 获取应用统计信息
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Command, category and tip counts", body = ApiResponse<AppStats>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_stats(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    info!("Fetching application statistics");
+
+    let stats = match data.catalog() {
+        Some(catalog) => catalog.stats().clone(),
+        None => compute_stats(&data)?,
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(stats),
+        message: None,
+    }))
+}
+
+// 获取详细的分类信息（包含描述和图标） - 使用真实的BasicCategory数据
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories/detailed",
+    tag = "categories",
+    responses(
+        (status = 200, description = "Categories with description and icon, sorted by position", body = ApiResponse<Vec<BasicCategory>>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_categories_detailed(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    info!("Fetching detailed categories from BasicCategory table");
+
+    if let Some(catalog) = data.catalog() {
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(catalog.categories()),
+            message: None,
+        }));
+    }
+
+    let categories = merged_basic_categories(&data)?;
+
+    info!("Found {} detailed categories", categories.len());
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -485,104 +1060,123 @@ async fn get_categories(data: web::Data<AppState>) -> Result<HttpResponse, AppEr
     }))
 }
 
-// 搜索命令
-async fn search_commands(
+// 获取命令建议（自动完成）
+#[utoipa::path(
+    get,
+    path = "/api/v1/suggestions",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Up to 10 command names starting with `q`", body = ApiResponse<Vec<String>>),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_command_suggestions(
     query: web::Query<SearchQuery>,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, AppError> {
-    // 验证搜索查询
     if query.q.trim().is_empty() {
-        warn!("Empty search query received");
-        return Err(AppError::InvalidInput("Search query cannot be empty".to_string()));
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(vec![] as Vec<String>),
+            message: None,
+        }));
     }
 
-    info!("Searching commands with query: {:?}", query.q);
+    debug!("Fetching command suggestions for: {:?}", query.q);
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+    if let Some(catalog) = data.catalog() {
+        let suggestions = catalog.suggestions(&query.q, 10);
+        logging::record_results(suggestions.len());
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(suggestions),
+            message: None,
+        }));
+    }
 
-    let limit = query.limit.unwrap_or(50).min(100); // 限制最大返回数量
+    let search_term = format!("{}%", query.q);
+    let fetch = |conn: &Connection| -> Result<Vec<String>, AppError> {
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT name FROM Command WHERE name LIKE ?1 ORDER BY name LIMIT 10")
+            .map_err(|e| {
+                error!("Failed to prepare suggestions query: {}", e);
+                AppError::DatabaseError(e)
+            })?;
 
-    // 改进搜索查询：按相关性排序
-    let sql = if let Some(ref _cat) = query.category {
-        format!(
-            "SELECT id, name, category, description,
-                   CASE
-                       WHEN name = ?1 THEN 100  -- 精确匹配名称，最高优先级
-                       WHEN name LIKE ?2 THEN 50  -- 名称开头匹配
-                       WHEN name LIKE ?3 THEN 30  -- 名称包含匹配
-                       WHEN description LIKE ?2 THEN 20  -- 描述开头匹配
-                       WHEN description LIKE ?3 THEN 10  -- 描述包含匹配
-                       ELSE 0
-                   END as relevance
-             FROM Command
-             WHERE (name LIKE ?3 OR description LIKE ?3) AND category = ?4
-             ORDER BY relevance DESC, name ASC
-             LIMIT ?5"
-        )
-    } else {
-        format!(
-            "SELECT id, name, category, description,
-                   CASE
-                       WHEN name = ?1 THEN 100  -- 精确匹配名称，最高优先级
-                       WHEN name LIKE ?2 THEN 50  -- 名称开头匹配
-                       WHEN name LIKE ?3 THEN 30  -- 名称包含匹配
-                       WHEN description LIKE ?2 THEN 20  -- 描述开头匹配
-                       WHEN description LIKE ?3 THEN 10  -- 描述包含匹配
-                       ELSE 0
-                   END as relevance
-             FROM Command
-             WHERE name LIKE ?3 OR description LIKE ?3
-             ORDER BY relevance DESC, name ASC
-             LIMIT ?4"
-        )
+        let suggestions = stmt
+            .query_map(params![&search_term], |row| row.get::<_, String>(0))
+            .map_err(|e| {
+                error!("Failed to execute suggestions query: {}", e);
+                AppError::DatabaseError(e)
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(suggestions)
     };
 
-    let exact_term = query.q.trim();
-    let start_term = format!("{}%", exact_term);
-    let contain_term = format!("%{}%", exact_term);
-    debug!("Search SQL: {} with terms: exact='{}', start='{}', contain='{}'", sql, exact_term, start_term, contain_term);
+    let mut suggestions = {
+        let conn = data.lock_db()?;
+        fetch(&conn)?
+    };
+    if let Some(overlay) = data.lock_overlay()? {
+        suggestions.extend(fetch(&overlay)?);
+        suggestions.sort();
+        suggestions.dedup();
+        suggestions.truncate(10);
+    }
 
-    let mut stmt = conn.prepare(&sql).map_err(|e| {
-        error!("Failed to prepare search query: {}", e);
-        AppError::DatabaseError(e)
-    })?;
+    logging::record_results(suggestions.len());
 
-    let commands: Vec<Command> = if let Some(ref cat) = query.category {
-        stmt.query_map(params![&exact_term, &start_term, &contain_term, cat, limit], |row| {
-            Ok(Command {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })
-        .map_err(|e| {
-            error!("Failed to execute category search query: {}", e);
-            AppError::DatabaseError(e)
-        })?
-        .filter_map(|r| r.ok())
-        .collect()
-    } else {
-        stmt.query_map(params![&exact_term, &start_term, &contain_term, limit], |row| {
-            Ok(Command {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })
-        .map_err(|e| {
-            error!("Failed to execute search query: {}", e);
-            AppError::DatabaseError(e)
-        })?
-        .filter_map(|r| r.ok())
-        .collect()
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(suggestions),
+        message: None,
+    }))
+}
+
+// 获取热门命令（基于某种算法）
+#[utoipa::path(
+    get,
+    path = "/api/v1/popular",
+    tag = "search",
+    responses(
+        (status = 200, description = "Up to 20 randomly picked common commands", body = ApiResponse<Vec<Command>>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_popular_commands(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    info!("Fetching popular commands");
+
+    if let Some(catalog) = data.catalog() {
+        let commands = catalog.popular(20);
+        logging::record_results(commands.len());
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(commands),
+            message: None,
+        }));
+    }
+
+    // 获取一些常用命令（这里可以根据实际使用统计来调整）
+    let sql = "SELECT id, name, category, description FROM Command WHERE category IN (1,3,5,10) ORDER BY RANDOM() LIMIT 20";
+
+    let mut commands = {
+        let conn = data.lock_db()?;
+        query_commands(&conn, sql, [], "popular commands")?
     };
+    // 两侧各随机取 20 个，合并后打乱再截断，覆盖库命令不会总被截掉（与 Catalog::popular 一致）
+    if let Some(overlay) = data.lock_overlay()? {
+        let shadowed = overlay_command_names(&overlay)?;
+        let extra = query_commands(&overlay, sql, [], "overlay popular commands")?;
+        commands = overlay::merge_commands(commands, extra, &shadowed);
+        commands.shuffle(&mut rand::rng());
+        commands.truncate(20);
+    }
 
-    info!("Found {} commands for search query: {}", commands.len(), query.q);
+    logging::record_results(commands.len());
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -591,39 +1185,119 @@ async fn search_commands(
     }))
 }
 
-// 获取所有命令（用于字母列表）
-async fn get_all_commands(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    info!("Fetching all commands for alphabetical listing");
+// API 端点
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
+// 获取所有分类
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories",
+    tag = "categories",
+    responses(
+        (status = 200, description = "Category titles", body = ApiResponse<Vec<String>>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_categories(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    info!("Fetching all categories");
 
-    let mut stmt = conn
-        .prepare("SELECT id, name, category, description FROM Command ORDER BY name")
-        .map_err(|e| {
-            error!("Failed to prepare all commands query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
+    let categories: Vec<String> = match data.catalog() {
+        Some(catalog) => catalog.categories().iter().map(|c| c.title.clone()).collect(),
+        None => merged_basic_categories(&data)?
+            .into_iter()
+            .map(|c| c.title)
+            .collect(),
+    };
 
-    let commands: Vec<Command> = stmt
-        .query_map([], |row| {
-            Ok(Command {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })
-        .map_err(|e| {
-            error!("Failed to execute all commands query: {}", e);
-            AppError::DatabaseError(e)
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
+    info!("Found {} categories", categories.len());
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(categories),
+        message: None,
+    }))
+}
+
+// 搜索命令
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "search",
+    params(SearchQuery),
+    security((), ("user_token" = [])),
+    responses(
+        (status = 200, description = "Matching commands ordered by relevance", body = ApiResponse<Vec<Command>>),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 400, description = "Empty query (`EMPTY_QUERY`)", body = ErrorResponse),
+        (status = 401, description = "`mine=true` without a valid user token", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn search_commands(
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    // 验证搜索查询
+    if query.q.trim().is_empty() {
+        warn!("Empty search query received");
+        return Err(AppError::EmptyQuery);
+    }
+
+    debug!("Searching commands with query: {:?}", query.q);
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 100); // 限制返回数量；负数在 SQLite 中表示不限制
+    let term = query.q.trim();
 
-    info!("Found {} commands for alphabetical listing", commands.len());
+    if query.mine.unwrap_or(false) {
+        let commands = notes::search_mine(&req, &data, term, query.category.as_ref(), limit)?;
+        logging::record_results(commands.len());
+        info!(results = commands.len(), mine = true; "Search completed");
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(commands),
+            message: None,
+        }));
+    }
+
+    if let Some(catalog) = data.catalog() {
+        let commands = catalog.search(term, query.category.as_ref(), limit);
+        logging::record_results(commands.len());
+        info!(results = commands.len(); "Search completed");
+        if commands.is_empty() {
+            metrics::metrics().search_zero_results.inc();
+        }
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(commands),
+            message: None,
+        }));
+    }
+
+    let mut results = {
+        let conn = data.lock_db()?;
+        search_in(&conn, term, query.category.as_ref(), limit)?
+    };
+
+    // 合并覆盖库结果：被遮蔽的上游命令不再出现，重新按相关性排序
+    if let Some(overlay) = data.lock_overlay()? {
+        let shadowed = overlay_command_names(&overlay)?;
+        results.retain(|(cmd, _)| !shadowed.contains(&cmd.name));
+        results.extend(
+            search_in(&overlay, term, query.category.as_ref(), limit)?
+                .into_iter()
+                .map(|(cmd, relevance)| (overlay::mark_command(cmd), relevance)),
+        );
+        results.sort_by(|(a, ra), (b, rb)| rb.cmp(ra).then_with(|| a.name.cmp(&b.name)));
+        results.truncate(limit.max(0) as usize);
+    }
+
+    let commands: Vec<Command> = results.into_iter().map(|(cmd, _)| cmd).collect();
+
+    logging::record_results(commands.len());
+    info!(results = commands.len(); "Search completed");
+    if commands.is_empty() {
+        metrics::metrics().search_zero_results.inc();
+    }
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -632,103 +1306,162 @@ async fn get_all_commands(data: web::Data<AppState>) -> Result<HttpResponse, App
     }))
 }
 
+// 获取所有命令（用于字母列表）
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands",
+    tag = "commands",
+    responses(
+        (status = 200, description = "All commands sorted by name", body = ApiResponse<Vec<Command>>),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_all_commands(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    info!("Fetching all commands for alphabetical listing");
+
+    let index = data.command_index()?;
+    logging::record_results(index.count);
+
+    Ok(index.body.respond(&req, HttpResponse::Ok().content_type("application/json")))
+}
+
+// 按 API id 解析命令详情（处理覆盖库遮蔽），并附加团队示例
+fn resolve_command_detail(data: &AppState, command_id: i64) -> Result<Option<CommandDetail>, AppError> {
+    if let Some(catalog) = data.catalog() {
+        return Ok(catalog.detail(command_id).cloned());
+    }
+
+    let detail = match overlay::resolve_id(command_id) {
+        (Source::Overlay, overlay_id) => match data.lock_overlay()? {
+            Some(overlay) => load_command_detail(&overlay, overlay_id)?.map(overlay::mark_detail),
+            None => None,
+        },
+        (Source::Base, base_id) => {
+            let base = {
+                let conn = data.lock_db()?;
+                load_command_detail(&conn, base_id)?
+            };
+
+            // 覆盖库中的同名命令遮蔽上游命令
+            match (base, data.lock_overlay()?) {
+                (Some(base), Some(overlay)) => {
+                    let shadow_id: Option<i64> = overlay
+                        .query_row("SELECT id FROM Command WHERE name = ?1 ORDER BY id LIMIT 1", params![&base.name], |row| row.get(0))
+                        .optional()?;
+                    match shadow_id {
+                        Some(id) => load_command_detail(&overlay, id)?.map(overlay::mark_detail),
+                        None => Some(base),
+                    }
+                }
+                (base, _) => base,
+            }
+        }
+    };
+
+    let mut detail = match detail {
+        Some(detail) => detail,
+        None => return Ok(None),
+    };
+
+    if let Some(overlay) = data.lock_overlay()? {
+        detail.examples = suggestions::examples_for(&overlay, &detail.name)?;
+    }
+
+    Ok(Some(detail))
+}
+
+// 按名称查找命令，覆盖库中的同名命令优先
+fn find_command_by_name(data: &AppState, name: &str) -> Result<Option<Command>, AppError> {
+    if let Some(catalog) = data.catalog() {
+        return Ok(catalog.find_by_name(name).cloned());
+    }
+
+    let sql = "SELECT id, name, category, description FROM Command WHERE name = ?1 ORDER BY id LIMIT 1";
+
+    if let Some(overlay) = data.lock_overlay()?
+        && let Some(cmd) = overlay.query_row(sql, params![name], Command::from_row).optional()?
+    {
+        return Ok(Some(overlay::mark_command(cmd)));
+    }
+
+    let conn = data.lock_db()?;
+    Ok(conn.query_row(sql, params![name], Command::from_row).optional()?)
+}
+
 // 获取命令详情
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands/{id}",
+    tag = "commands",
+    params(("id" = i64, Path, description = "Command id; overlay commands are offset by 1000000000")),
+    security((), ("user_token" = [])),
+    responses(
+        (status = 200, description = "Command with sections, examples and the caller's private notes", body = ApiResponse<CommandDetail>),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 async fn get_command(
+    req: HttpRequest,
     command_id: web::Path<i64>,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, AppError> {
     let command_id = *command_id;
     info!("Fetching command details for id: {}", command_id);
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
-
-    // 获取命令基本信息
-    let mut stmt = conn
-        .prepare("SELECT id, name, category, description
-                  FROM Command
-                  WHERE id = ?1")
-        .map_err(|e| {
-            error!("Failed to prepare command query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
-
-    let command = stmt.query_row(params![command_id], |row| {
-        Ok(Command {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            category: row.get(2)?,
-            description: row.get(3)?,
-        })
-    });
-
-    let cmd = match command {
-        Ok(cmd) => cmd,
-        Err(rusqlite::Error::QueryReturnedNoRows) => {
+    let mut detail = match resolve_command_detail(&data, command_id)? {
+        Some(detail) => detail,
+        None => {
             warn!("Command with id {} not found", command_id);
             return Err(AppError::CommandNotFound);
         }
-        Err(e) => {
-            error!("Database error fetching command {}: {}", command_id, e);
-            return Err(AppError::DatabaseError(e));
-        }
     };
 
-    // 获取命令章节，排除NAME章节（根据Kotlin项目的实现）
-    let mut sect_stmt = conn
-        .prepare("SELECT title, content FROM CommandSection WHERE command_id = ?1 AND title != 'NAME' ORDER BY id")
-        .map_err(|e| {
-            error!("Failed to prepare sections query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
+    detail.notes = notes::notes_for_request(&req, &data, &detail.name)?;
 
-    let sections: Vec<CommandSection> = sect_stmt
-        .query_map(params![command_id], |row| {
-            Ok(CommandSection {
-                title: row.get(0)?,
-                content: row.get(1)?,
-            })
-        })
-        .map_err(|e| {
-            error!("Failed to execute sections query: {}", e);
-            AppError::DatabaseError(e)
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    // 获取TLDR章节（如果存在）
-    let mut tldr_stmt = conn
-        .prepare("SELECT content FROM CommandSection WHERE command_id = ?1 AND title = 'TLDR'")
-        .map_err(|e| {
-            error!("Failed to prepare TLDR query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
-
-    let tldr: Option<String> = tldr_stmt
-        .query_row(params![command_id], |row| {
-            Ok(row.get::<_, String>(0)?)
-        })
-        .ok(); // 忽略错误，TLDR可能不存在
-
-    info!("Command {} found with {} sections", cmd.name, sections.len());
+    info!("Command {} found with {} sections", detail.name, detail.sections.len());
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
-        data: Some(CommandDetail {
-            id: cmd.id,
-            name: cmd.name,
-            category: cmd.category,
-            description: cmd.description,
-            sections,
-            tldr,
-        }),
+        data: Some(detail),
         message: None,
     }))
 }
 
+// 按标题读取分类下的命令，覆盖库中的同名分类优先；分类不存在时返回 None
+fn resolve_category_commands(data: &AppState, category_name: &str) -> Result<Option<Vec<Command>>, AppError> {
+    let overlay_commands = match data.lock_overlay()? {
+        Some(overlay) if overlay::table_exists(&overlay, "BasicCategory")? => {
+            category_commands(&overlay, category_name)?
+                .map(|commands| commands.into_iter().map(overlay::mark_command).collect())
+        }
+        _ => None,
+    };
+
+    match overlay_commands {
+        Some(commands) => Ok(Some(commands)),
+        None => {
+            let conn = data.lock_db()?;
+            category_commands(&conn, category_name)
+        }
+    }
+}
+
 // 获取按分类的命令 - 使用BasicCategory系统
+#[utoipa::path(
+    get,
+    path = "/api/v1/category/{name}",
+    tag = "categories",
+    params(("name" = String, Path, description = "Category title")),
+    responses(
+        (status = 200, description = "One-liner commands in the category", body = ApiResponse<Vec<Command>>),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 404, description = "Category not found (`CATEGORY_NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 async fn get_commands_by_category(
     category: web::Path<String>,
     data: web::Data<AppState>,
@@ -736,105 +1469,66 @@ async fn get_commands_by_category(
     let category_name = category.as_str();
     info!("Fetching commands for BasicCategory: {}", category_name);
 
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
-
-    // First, find the BasicCategory ID
-    let mut category_stmt = conn
-        .prepare("SELECT id FROM BasicCategory WHERE title = ?1")
-        .map_err(|e| {
-            error!("Failed to prepare category lookup query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
-
-    let category_id: Option<i64> = category_stmt
-        .query_row(params![category_name], |row| {
-            Ok(row.get::<_, i64>(0)?)
-        })
-        .optional()
-        .map_err(|e| {
-            error!("Failed to lookup category '{}': {}", category_name, e);
-            AppError::DatabaseError(e)
-        })?;
+    let commands = match data.catalog() {
+        Some(catalog) => catalog.category_commands(category_name).map(<[Command]>::to_vec),
+        None => resolve_category_commands(&data, category_name)?,
+    };
 
-    let category_id = match category_id {
-        Some(id) => {
-            debug!("Found BasicCategory '{}' with ID: {}", category_name, id);
-            id
-        }
+    let commands: Vec<Command> = match commands {
+        Some(commands) => commands,
         None => {
             warn!("BasicCategory '{}' not found", category_name);
-            return Ok(HttpResponse::Ok().json(ApiResponse {
-                success: true,
-                data: Some(vec![] as Vec<Command>),
-                message: Some(format!("Category '{}' not found", category_name)),
-            }));
+            return Err(AppError::CategoryNotFound(category_name.to_string()));
         }
     };
+    logging::record_results(commands.len());
 
-    // Get commands through BasicGroup -> BasicCommand relationship
-    let mut stmt = conn
-        .prepare("SELECT bc.id, bc.command, bc.mans, bg.description
-                  FROM BasicCommand bc
-                  JOIN BasicGroup bg ON bc.group_id = bg.id
-                  WHERE bg.category_id = ?1
-                  ORDER BY bc.command")
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(commands),
+        message: None,
+    }))
+}
+
+// 读取提示的章节
+fn load_tip_sections(conn: &Connection, tip_id: i64) -> Result<Vec<TipSection>, AppError> {
+    let mut sect_stmt = conn
+        .prepare("SELECT type, data1, data2, extra FROM TipSection WHERE tip_id = ?1 ORDER BY position")
         .map_err(|e| {
-            error!("Failed to prepare basic commands by category query: {}", e);
+            error!("Failed to prepare tip sections query: {}", e);
             AppError::DatabaseError(e)
         })?;
 
-    let commands: Vec<Command> = stmt
-        .query_map(params![category_id], |row| {
-            let id: i64 = row.get(0)?;
-            let command: String = row.get(1)?;
-            let _mans: String = row.get(2)?;
-            let description: String = row.get(3)?;
-
-            // Extract first line of command (before newline if any)
-            let command_name = command.lines().next().unwrap_or(&command).trim();
-
-            Ok(Command {
-                id,
-                name: command_name.to_string(),
-                category: 0, // Use 0 as placeholder for BasicCategory commands
-                description,
+    let sections: Vec<TipSection> = sect_stmt
+        .query_map(params![tip_id], |row| {
+            Ok(TipSection {
+                section_type: row.get(0)?,
+                data1: row.get(1)?,
+                data2: row.get(2)?,
+                extra: row.get(3)?,
             })
         })
         .map_err(|e| {
-            error!("Failed to execute basic commands by category query: {}", e);
+            error!("Failed to execute tip sections query: {}", e);
             AppError::DatabaseError(e)
         })?
         .filter_map(|r| r.ok())
         .collect();
 
-    info!("Found {} basic commands for category '{}' (ID: {})", commands.len(), category_name, category_id);
-
-    Ok(HttpResponse::Ok().json(ApiResponse {
-        success: true,
-        data: Some(commands),
-        message: None,
-    }))
+    Ok(sections)
 }
 
-// 获取随机提示
-async fn get_random_tip(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
-    let conn = data.db.lock().map_err(|e| {
-        error!("Failed to acquire database lock: {}", e);
-        AppError::InternalError("Database lock error".to_string())
-    })?;
-
+// 从单个数据库随机读取一条提示，跳过标题在 shadowed（JSON 数组）中的提示
+fn random_tip_in(conn: &Connection, shadowed: &str) -> Result<Tip, AppError> {
     let mut stmt = conn
-        .prepare("SELECT id, title FROM Tip ORDER BY RANDOM() LIMIT 1")
+        .prepare("SELECT id, title FROM Tip WHERE title NOT IN (SELECT value FROM json_each(?1)) ORDER BY RANDOM() LIMIT 1")
         .map_err(|e| {
             error!("Failed to prepare random tip query: {}", e);
             AppError::DatabaseError(e)
         })?;
 
     let tip = stmt
-        .query_row([], |row| {
+        .query_row(params![shadowed], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
         })
         .map_err(|e| {
@@ -845,43 +1539,96 @@ async fn get_random_tip(data: web::Data<AppState>) -> Result<HttpResponse, AppEr
     let (id, title) = tip;
 
     // Get tip sections
-    let mut sect_stmt = conn
-        .prepare("SELECT type, data1, data2, extra FROM TipSection WHERE tip_id = ?1 ORDER BY position")
-        .map_err(|e| {
-            error!("Failed to prepare tip sections query: {}", e);
-            AppError::DatabaseError(e)
-        })?;
+    let sections = load_tip_sections(conn, id)?;
 
-    let sections: Vec<TipSection> = sect_stmt
-        .query_map(params![id], |row| {
-            Ok(TipSection {
-                section_type: row.get(0)?,
-                data1: row.get(1)?,
-                data2: row.get(2)?,
-                extra: row.get(3)?,
-            })
-        })
-        .map_err(|e| {
-            error!("Failed to execute tip sections query: {}", e);
-            AppError::DatabaseError(e)
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
+    Ok(Tip {
+        id,
+        title,
+        sections,
+        source: Source::Base,
+    })
+}
+
+// 获取随机提示
+#[utoipa::path(
+    get,
+    path = "/api/v1/random-tip",
+    tag = "tips",
+    responses(
+        (status = 200, description = "A random tip", body = ApiResponse<Tip>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+async fn get_random_tip(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    if let Some(catalog) = data.catalog() {
+        let tip = catalog
+            .random_tip()
+            .cloned()
+            .ok_or_else(|| AppError::NotFound("No tips available".to_string()))?;
+        return Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(tip),
+            message: None,
+        }));
+    }
+
+    // 覆盖库中的提示按数量比例参与随机，同名提示遮蔽上游提示
+    let (overlay_count, shadowed): (i64, String) = match data.lock_overlay()? {
+        Some(overlay) if overlay::table_exists(&overlay, "Tip")? => {
+            let mut stmt = overlay.prepare("SELECT title FROM Tip")?;
+            let titles: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+            let shadowed = serde_json::to_string(&titles)
+                .map_err(|e| AppError::InternalError(format!("Failed to encode tip titles: {}", e)))?;
+            (titles.len() as i64, shadowed)
+        }
+        _ => (0, "[]".to_string()),
+    };
+
+    let tip = {
+        let conn = data.lock_db()?;
+        let base_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM Tip WHERE title NOT IN (SELECT value FROM json_each(?1))",
+            params![&shadowed],
+            |row| row.get(0),
+        )?;
+        let pick: i64 = if overlay_count > 0 {
+            conn.query_row("SELECT ABS(RANDOM()) % ?1", params![base_count + overlay_count], |row| row.get(0))?
+        } else {
+            0
+        };
+
+        if pick < base_count { Some(random_tip_in(&conn, &shadowed)?) } else { None }
+    };
+
+    let tip = match tip {
+        Some(tip) => tip,
+        None => match data.lock_overlay()? {
+            Some(overlay) => {
+                let mut tip = random_tip_in(&overlay, "[]")?;
+                tip.id += overlay::OVERLAY_ID_OFFSET;
+                tip.source = Source::Overlay;
+                tip
+            }
+            None => return Err(AppError::InternalError("Overlay database is not configured".to_string())),
+        },
+    };
 
-    info!("Found random tip: {} with {} sections", title, sections.len());
+    info!("Found random tip: {} with {} sections", tip.title, tip.sections.len());
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
-        data: Some(Tip {
-            id,
-            title,
-            sections,
-        }),
+        data: Some(tip),
         message: None,
     }))
 }
 
 // 健康检查
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "Service is up", body = ApiResponse<String>))
+)]
 async fn health_check() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -890,12 +1637,36 @@ async fn health_check() -> Result<HttpResponse> {
     }))
 }
 
-// 提供前端页面
-async fn serve_frontend() -> Result<HttpResponse> {
-    let html = include_str!("index.html");
-    Ok(HttpResponse::Ok()
-        .content_type("text/html; charset=utf-8")
-        .body(html))
+// API 路由，相对于 /api/v1（及兼容的 /api）作用域
+fn configure_api(cfg: &mut web::ServiceConfig) {
+    // 应用统计
+    cfg.route("/stats", web::get().to(get_stats))
+        // 分类相关
+        .route("/categories", web::get().to(get_categories))
+        .route("/categories/detailed", web::get().to(get_categories_detailed))
+        // 搜索相关
+        .route("/search", web::get().to(search_commands))
+        .route("/suggestions", web::get().to(get_command_suggestions))
+        .route("/popular", web::get().to(get_popular_commands))
+        // 命令相关
+        .route("/commands", web::get().to(get_all_commands))
+        .route("/commands/{id}", web::get().to(get_command))
+        .route("/category/{name}", web::get().to(get_commands_by_category))
+        // 提示相关
+        .route("/random-tip", web::get().to(get_random_tip))
+        // 响应缓存统计
+        .route("/cache/stats", web::get().to(response_cache::cache_stats))
+        // 全量导出（NDJSON / CSV，流式）
+        .route("/export", web::get().to(export::export))
+        // 修订历史
+        .configure(revisions::configure)
+        // 示例建议
+        .configure(suggestions::configure)
+        // 用户收藏、合集与笔记
+        .configure(users::configure)
+        .configure(notes::configure)
+        // 管理员写入
+        .configure(admin::configure);
 }
 
 #[cfg(test)]
@@ -1032,14 +1803,7 @@ mod tests {
             .unwrap();
 
         let command = stmt
-            .query_row([], |row| {
-                Ok(Command {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    category: row.get(2)?,
-                    description: row.get(3)?,
-                })
-            })
+            .query_row([], Command::from_row)
             .unwrap();
 
         assert_eq!(command.name, "grep");
@@ -1080,14 +1844,7 @@ mod tests {
 
         let search_term = "%grep%";
         let commands: Vec<Command> = stmt
-            .query_map(params![search_term], |row| {
-                Ok(Command {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    category: row.get(2)?,
-                    description: row.get(3)?,
-                })
-            })
+            .query_map(params![search_term], Command::from_row)
             .unwrap()
             .filter_map(|r| r.ok())
             .collect();
@@ -1129,9 +1886,7 @@ mod tests {
             .prepare("SELECT content FROM CommandSection WHERE command_id = ?1 AND title = 'TLDR'")
             .unwrap();
 
-        let tldr: Result<String, _> = stmt.query_row(params![1], |row| {
-            Ok(row.get::<_, String>(0)?)
-        });
+        let tldr: Result<String, _> = stmt.query_row(params![1], |row| row.get::<_, String>(0));
 
         assert!(tldr.is_ok());
         assert_eq!(tldr.unwrap(), "grep pattern file");
@@ -1170,6 +1925,7 @@ mod tests {
                     id: row.get(0)?,
                     title: row.get(1)?,
                     sections: vec![], // Would be populated separately
+                    source: Source::Base,
                 })
             })
             .unwrap();
@@ -1202,67 +1958,1799 @@ mod tests {
         assert_eq!(sections[0].section_type, 0);
         assert_eq!(sections[0].data1, "Use Ctrl+A to go to beginning of line");
     }
+
+    #[test]
+    fn test_overlay_shadows_base_command() {
+        let base = create_test_database();
+        let overlay = create_test_database();
+        overlay.execute("UPDATE Command SET description = 'Internal grep wrapper' WHERE name = 'grep'", []).unwrap();
+        overlay.execute("DELETE FROM Command WHERE name = 'chmod'", []).unwrap();
+        overlay.execute("INSERT INTO Command (category, name, description) VALUES (1, 'deployctl', 'Deploy internal services')", []).unwrap();
+
+        let sql = "SELECT id, name, category, description FROM Command ORDER BY name";
+        let shadowed = overlay_command_names(&overlay).unwrap();
+        let merged = overlay::merge_commands(
+            query_commands(&base, sql, [], "test").unwrap(),
+            query_commands(&overlay, sql, [], "test").unwrap(),
+            &shadowed,
+        );
+
+        assert_eq!(merged.len(), 3);
+        let grep = merged.iter().find(|c| c.name == "grep").unwrap();
+        assert_eq!(grep.source, Source::Overlay);
+        assert_eq!(grep.description, "Internal grep wrapper");
+        assert_eq!(overlay::resolve_id(grep.id), (Source::Overlay, 1));
+        let chmod = merged.iter().find(|c| c.name == "chmod").unwrap();
+        assert_eq!(chmod.source, Source::Base);
+        assert_eq!(overlay::resolve_id(chmod.id), (Source::Base, 2));
+    }
+
+    #[test]
+    fn test_overlay_merged_count() {
+        let base = create_test_database();
+        let overlay = create_test_database();
+        overlay.execute("DELETE FROM Command WHERE name = 'chmod'", []).unwrap();
+        overlay.execute("INSERT INTO Command (category, name, description) VALUES (1, 'deployctl', 'Deploy internal services')", []).unwrap();
+
+        // grep 被遮蔽，chmod 来自上游，deployctl 来自覆盖库
+        assert_eq!(merged_count(&base, &overlay, "Command", "name").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_overlay_merge_categories() {
+        let base = create_test_database();
+        let overlay = create_test_database();
+        overlay.execute("DELETE FROM BasicCategory WHERE title = 'System'", []).unwrap();
+        overlay.execute("INSERT INTO BasicCategory (position, title) VALUES (0, 'Internal tools')", []).unwrap();
+
+        let merged = overlay::merge_categories(
+            query_basic_categories(&base).unwrap(),
+            query_basic_categories(&overlay).unwrap(),
+        );
+
+        let titles: Vec<&str> = merged.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Internal tools", "System", "Files"]);
+        assert_eq!(merged[0].source, Source::Overlay);
+        assert_eq!(merged[1].source, Source::Base);
+        assert_eq!(merged[2].source, Source::Overlay);
+    }
+
+    pub(crate) fn test_app_state(token: &str) -> AppState {
+        let overlay = Connection::open_in_memory().unwrap();
+        ensure_store_schema(&overlay).unwrap();
+        let content = content::ContentTracker::new("test".to_string());
+        content.track(&overlay);
+        AppState {
+            db: Mutex::new(create_test_database()),
+            overlay: Some(Mutex::new(overlay)),
+            admin_token: Some(token.to_string()),
+            open_registration: true,
+            content,
+            catalog: None,
+            shutdown: Default::default(),
+            command_index: Default::default(),
+        }
+    }
+
+    fn test_state_with_admin(token: &str) -> web::Data<AppState> {
+        web::Data::new(test_app_state(token))
+    }
+
+    #[actix_web::test]
+    async fn test_admin_requires_bearer_token() {
+        use actix_web::test;
+
+        let app = test::init_service(App::new().app_data(test_state_with_admin("secret")).service(web::scope("/api").configure(configure_api))).await;
+
+        let body = serde_json::json!({"name": "deployctl", "category": 1, "description": "Deploy"});
+        let req = test::TestRequest::post().uri("/api/admin/commands").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/commands")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/commands")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(&body)
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["name"], "deployctl");
+        assert_eq!(resp["data"]["source"], "overlay");
+        assert_eq!(resp["data"]["id"], overlay::OVERLAY_ID_OFFSET + 1);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_update_copies_base_command_to_overlay() {
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::put()
+            .uri("/api/admin/commands/1/sections/TLDR")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"content": "grep -rn pattern ."}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // 上游 id 现在返回覆盖库中的副本，其余章节被一并复制
+        let req = test::TestRequest::get().uri("/api/commands/1").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["source"], "overlay");
+        assert_eq!(resp["data"]["tldr"], "grep -rn pattern .");
+        assert_eq!(resp["data"]["sections"].as_array().unwrap().len(), 2);
+
+        let base_sections: i64 = data.lock_db().unwrap()
+            .query_row("SELECT COUNT(*) FROM CommandSection WHERE content = 'grep pattern file'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(base_sections, 1);
+
+        // 删除不存在的章节不会复制上游命令
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/commands/2/sections/MISSING")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let copies: i64 = data.lock_overlay().unwrap().unwrap()
+            .query_row("SELECT COUNT(*) FROM Command WHERE name = 'chmod'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(copies, 0);
+
+        // 修改上游一行命令时整个分类（分组与一行命令）被复制并遮蔽上游分类
+        data.lock_db().unwrap().execute_batch(
+            "CREATE TABLE BasicGroup (id INTEGER PRIMARY KEY, category_id INTEGER, position INTEGER, description TEXT);
+             CREATE TABLE BasicCommand (id INTEGER PRIMARY KEY, group_id INTEGER, command TEXT, mans TEXT);
+             INSERT INTO BasicGroup (id, category_id, position, description) VALUES (1, 1, 1, 'Uptime'), (2, 1, 2, 'Load');
+             INSERT INTO BasicCommand (id, group_id, command, mans) VALUES (1, 1, 'uptime', 'uptime'), (2, 2, 'cat /proc/loadavg', '');",
+        ).unwrap();
+        // 复制后的修改失败时复制一起回滚，不留下遮蔽上游分类的副本
+        let req = test::TestRequest::put()
+            .uri("/api/admin/groups/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"category_id": 999, "position": 1, "description": "Uptime"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let copies: i64 = data.lock_overlay().unwrap().unwrap()
+            .query_row("SELECT (SELECT COUNT(*) FROM BasicCategory) + (SELECT COUNT(*) FROM UpstreamCopy)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(copies, 0);
+
+        let req = test::TestRequest::put()
+            .uri("/api/admin/basic-commands/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"group_id": 1, "command": "uptime -p"}))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["data"]["id"].as_i64().unwrap() > overlay::OVERLAY_ID_OFFSET);
+        assert!(resp["data"]["group_id"].as_i64().unwrap() > overlay::OVERLAY_ID_OFFSET);
+        let req = test::TestRequest::get().uri("/api/category/System").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<&str> = resp["data"].as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["cat /proc/loadavg", "uptime -p"]);
+        // 分类复制后上游分组 id 仍指向其副本
+        let req = test::TestRequest::put()
+            .uri("/api/admin/groups/2")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"category_id": 1, "position": 2, "description": "Load average"}))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["id"], resp["data"]["category_id"].as_i64().unwrap() + 1);
+        let groups: i64 = data.lock_overlay().unwrap().unwrap()
+            .query_row("SELECT COUNT(*) FROM BasicGroup", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(groups, 2);
+
+        // 修改上游提示后只返回覆盖库副本
+        let req = test::TestRequest::put()
+            .uri("/api/admin/tips/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({
+                "title": "Quick Navigation",
+                "position": 1,
+                "sections": [{"type": 0, "data1": "Use Ctrl+E to go to the end of the line", "data2": "", "extra": ""}],
+            }))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/random-tip").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["source"], "overlay");
+        let req = test::TestRequest::get().uri("/api/stats").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["total_tips"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_revisions_diff_and_revert() {
+        use actix_web::test;
+
+        let state = test_state_with_admin("secret");
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::put()
+            .uri("/api/admin/commands/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .insert_header(("X-Author", "alice"))
+            .set_json(serde_json::json!({"name": "grep", "category": 1, "description": "Print lines matching a pattern"}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // 上游导入 + 本次修改
+        let req = test::TestRequest::get().uri("/api/commands/1/revisions").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let revisions = resp["data"].as_array().unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0]["author"], "alice");
+        assert_eq!(revisions[0]["action"], "update");
+        assert_eq!(revisions[1]["author"], "upstream");
+        let (latest, first) = (revisions[0]["id"].as_i64().unwrap(), revisions[1]["id"].as_i64().unwrap());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/commands/1/diff?from={}&to={}", first, latest))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let diff = resp["data"]["diff"].as_str().unwrap();
+        assert!(diff.contains("-description: Search files for lines matching a pattern"));
+        assert!(diff.contains("+description: Print lines matching a pattern"));
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/admin/commands/1/revisions/{}/revert", first))
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["action"], format!("revert:{}", first));
+
+        let req = test::TestRequest::get().uri("/api/commands/1").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["description"], "Search files for lines matching a pattern");
+
+        // 损坏的快照返回 500，而不是空 diff
+        state.lock_overlay().unwrap().unwrap().execute("UPDATE CommandRevision SET snapshot = '{' WHERE id = ?1", [first]).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/commands/1/diff?from={}&to={}", first, latest))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 500);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "INTERNAL_ERROR");
+    }
+
+    #[actix_web::test]
+    async fn test_example_suggestion_moderation() {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::post().uri("/api/users").set_json(serde_json::json!({"name": "bob"})).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let bob = format!("Bearer {}", resp["data"]["token"].as_str().unwrap());
+
+        // 作者取自 token，请求体中的 author 被忽略；未知 token 被拒绝
+        let submit = |command: &str, token: Option<&str>| {
+            let mut req = test::TestRequest::post()
+                .uri("/api/commands/1/examples/suggestions")
+                .set_json(serde_json::json!({"description": "Example", "command": command, "author": "admin"}));
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token.to_string()));
+            }
+            req.to_request()
+        };
+        assert_eq!(test::call_service(&app, submit("grep x", Some("Bearer nope"))).await.status(), 401);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, submit("grep -rn TODO src/", Some(&bob))).await;
+        assert_eq!(resp["data"]["author"], "bob");
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, submit("grep -c ''", None)).await;
+        assert_eq!(resp["data"]["author"], "anonymous");
+
+        let req = test::TestRequest::get()
+            .uri("/api/admin/suggestions")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let pending = resp["data"].as_array().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0]["command_name"], "grep");
+
+        for (id, action) in [(pending[0]["id"].as_i64().unwrap(), "approve"), (pending[1]["id"].as_i64().unwrap(), "reject")] {
+            let review = |note: String| {
+                test::TestRequest::post()
+                    .uri(&format!("/api/admin/suggestions/{}/{}", id, action))
+                    .insert_header(("Authorization", "Bearer secret"))
+                    .set_json(serde_json::json!({"note": note}))
+                    .to_request()
+            };
+            // 审核备注有长度限制，被拒绝时建议仍处于待审核状态
+            assert_eq!(test::call_service(&app, review("x".repeat(5000))).await.status(), 400);
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, review("Thanks".to_string())).await;
+            assert_eq!(resp["data"]["review_note"], "Thanks");
+        }
+
+        // 只有审核通过的示例出现在命令详情中，TLDR 保持不变
+        let req = test::TestRequest::get().uri("/api/commands/1").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let examples = resp["data"]["examples"].as_array().unwrap();
+        assert_eq!(examples.len(), 1);
+        assert_eq!(examples[0]["command"], "grep -rn TODO src/");
+        assert_eq!(examples[0]["author"], "bob");
+        assert_eq!(resp["data"]["tldr"], "grep pattern file");
+    }
+
+    #[actix_web::test]
+    async fn test_user_collections_share_and_export() {
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        // 上游 TLDR 是前端直接渲染的 HTML
+        data.lock_db().unwrap().execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES ('TLDR', '<b>Make a script executable</b><br>chmod +x &lt;file&gt;', 2)",
+            [],
+        ).unwrap();
+        let app = test::init_service(
+            App::new().app_data(data).service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::post().uri("/api/v1/users").set_json(serde_json::json!({"name": "carol"})).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let token = resp["data"]["token"].as_str().unwrap().to_string();
+        let auth = ("Authorization", format!("Bearer {}", token));
+
+        let req = test::TestRequest::put().uri("/api/v1/favorites/2").insert_header(auth.clone()).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/v1/favorites").insert_header(auth.clone()).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["name"], "chmod");
+
+        // 关闭开放注册后只有管理员可以注册
+        let mut closed = test_app_state("secret");
+        closed.open_registration = false;
+        let closed = test::init_service(
+            App::new().app_data(web::Data::new(closed)).service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+        let register = |token: Option<&str>| {
+            let mut req = test::TestRequest::post().uri("/api/v1/users").set_json(serde_json::json!({"name": "dave"}));
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+            }
+            req.to_request()
+        };
+        assert_eq!(test::call_service(&closed, register(None)).await.status(), 401);
+        assert_eq!(test::call_service(&closed, register(Some(&token))).await.status(), 401);
+        assert_eq!(test::call_service(&closed, register(Some("secret"))).await.status(), 201);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/collections")
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({"name": "On-call cheatsheet"}))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let id = resp["data"]["id"].as_i64().unwrap();
+        let share_url = resp["data"]["share_url"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/collections/{}/items", id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({"kind": "command", "id": 1}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/collections/{}/items", id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({"kind": "command", "id": 2}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // 其他用户看不到该合集，但可以通过分享链接只读访问
+        let req = test::TestRequest::get().uri(&format!("/api/v1/collections/{}", id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+        let req = test::TestRequest::get().uri(&share_url).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["items"][0]["title"], "grep");
+        assert!(resp["data"]["share_url"].is_null());
+
+        let req = test::TestRequest::get().uri(&format!("{}/export", share_url)).to_request();
+        let markdown = test::call_and_read_body(&app, req).await;
+        assert_eq!(
+            std::str::from_utf8(&markdown).unwrap(),
+            "# On-call cheatsheet\n\n## grep\n\nSearch files for lines matching a pattern\n\n```bash\ngrep pattern file\n```\n\n\
+             ## chmod\n\nChange file permissions\n\n```bash\n# Make a script executable\nchmod +x <file>\n```\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_private_notes_inline_and_search() {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::post().uri("/api/users").set_json(serde_json::json!({"name": "dave"})).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let auth = ("Authorization", format!("Bearer {}", resp["data"]["token"].as_str().unwrap()));
+
+        // 按名称创建笔记
+        let req = test::TestRequest::post()
+            .uri("/api/notes")
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({"command": "chmod", "content": "on our hosts use StrictHostKeyChecking=no"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let note: serde_json::Value = test::read_body_json(resp).await;
+        let note_id = note["data"]["id"].as_i64().unwrap();
+
+        // 只有所有者能看到内联笔记
+        let req = test::TestRequest::get().uri("/api/commands/2").insert_header(auth.clone()).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["notes"][0]["content"], "on our hosts use StrictHostKeyChecking=no");
+        let req = test::TestRequest::get().uri("/api/commands/2").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["data"]["notes"].is_null());
+
+        // mine=true 同时匹配笔记内容
+        let req = test::TestRequest::get().uri("/api/search?q=stricthost&mine=true").insert_header(auth.clone()).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+        assert_eq!(resp["data"][0]["name"], "chmod");
+        let req = test::TestRequest::get().uri("/api/search?q=grep&mine=true").insert_header(auth.clone()).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["data"].as_array().unwrap().is_empty());
+        let req = test::TestRequest::get().uri("/api/search?q=chmod&mine=true").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/notes/{}", note_id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({"content": "use -R carefully"}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/commands/2/notes").insert_header(auth.clone()).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"][0]["content"], "use -R carefully");
+
+        let req = test::TestRequest::delete().uri(&format!("/api/notes/{}", note_id)).insert_header(auth.clone()).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri(&format!("/api/notes/{}", note_id)).insert_header(auth).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_spec_and_docs() {
+        use actix_web::test;
+
+        let app = test::init_service(App::new().configure(openapi::configure)).await;
+
+        let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+        let spec: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"]["/api/v1/commands/{id}"]["get"].is_object());
+        assert!(spec["paths"]["/api/v1/search"]["get"]["parameters"].as_array().unwrap().iter().any(|p| p["name"] == "mine"));
+
+        // category 以字符串输出，tldr 可省略
+        let detail = &spec["components"]["schemas"]["CommandDetail"];
+        assert_eq!(detail["properties"]["category"]["type"], "string");
+        let required = detail["required"].as_array().unwrap();
+        assert!(required.iter().any(|f| f == "sections"));
+        assert!(!required.iter().any(|f| f == "tldr"));
+
+        // 写入与用户接口同样出现在规范中，并引用对应的 bearer 方案
+        let schemes = &spec["components"]["securitySchemes"];
+        assert_eq!(schemes["user_token"]["scheme"], "bearer");
+        assert_eq!(schemes["admin_token"]["scheme"], "bearer");
+        let security = |path: &str, method: &str| spec["paths"][path][method]["security"].to_string();
+        assert!(security("/api/v1/admin/commands/{id}", "put").contains("admin_token"));
+        assert!(security("/api/v1/admin/suggestions", "get").contains("admin_token"));
+        assert!(security("/api/v1/admin/commands/{id}/revisions/{rev}/revert", "post").contains("admin_token"));
+        assert!(security("/api/v1/collections/{id}/items", "post").contains("user_token"));
+        assert!(security("/api/v1/notes/{id}", "delete").contains("user_token"));
+        assert!(security("/api/v1/commands/{id}/examples/suggestions", "post").contains("user_token"));
+        for path in ["/api/v1/commands/{id}/revisions", "/api/v1/commands/{id}/diff", "/api/v1/users/me", "/api/v1/favorites", "/api/v1/shared/collections/{token}"] {
+            assert!(spec["paths"][path]["get"].is_object(), "{} is missing", path);
+        }
+        assert!(spec["paths"]["/api/v1/admin/reload"]["post"].is_object());
+        // 上游条目不能删除
+        assert!(spec["paths"]["/api/v1/admin/tips/{id}"]["delete"]["description"].as_str().unwrap().contains("read-only"));
+        assert_eq!(
+            spec["paths"]["/api/v1/admin/tips"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/TipInput"
+        );
+        let kinds = &spec["components"]["schemas"]["ItemKind"]["enum"];
+        assert_eq!(kinds, &serde_json::json!(["command", "one-liner"]));
+
+        let req = test::TestRequest::get().uri("/api/docs/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_versioned_api_error_codes() {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .wrap(middleware::from_fn(request_id::middleware))
+                .service(web::scope("/api/v1").configure(configure_api))
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/v1/search?q=%20").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let header_id = resp.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["code"], "EMPTY_QUERY");
+        assert_eq!(body["message"], "Search query cannot be empty");
+        assert_eq!(body["request_id"], header_id.as_str());
+
+        // 沿用客户端传入的请求 id
+        let req = test::TestRequest::get().uri("/api/v1/commands/999").insert_header(("X-Request-Id", "trace-42")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "COMMAND_NOT_FOUND");
+        assert_eq!(body["request_id"], "trace-42");
+
+        let req = test::TestRequest::get().uri("/api/v1/category/Nonexistent").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "CATEGORY_NOT_FOUND");
+        assert_eq!(body["message"], "Category 'Nonexistent' not found");
+
+        // 没有可写存储时用户功能不可用
+        let mut state = test_app_state("secret");
+        state.overlay = None;
+        let bare = test::init_service(
+            App::new().app_data(web::Data::new(state)).service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+        let req = test::TestRequest::post().uri("/api/v1/users").set_json(serde_json::json!({"name": "ann"})).to_request();
+        let resp = test::call_service(&bare, req).await;
+        assert_eq!(resp.status(), 503);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "FEATURE_DISABLED");
+        assert_eq!(body["message"], "User accounts are not enabled");
+
+        // 未版本化的 /api 仍然可用
+        let req = test::TestRequest::get().uri("/api/commands/1").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["name"], "grep");
+    }
+
+    #[actix_web::test]
+    async fn test_etag_and_cache_control() {
+        use actix_web::test;
+
+        let cache = http_cache::HttpCache::new(300, [("command", 3600)].into_iter().collect());
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .app_data(web::Data::new(cache))
+                .wrap(middleware::from_fn(http_cache::middleware))
+                .service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/v1/commands/1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "public, max-age=3600");
+        assert!(resp.headers().contains_key("last-modified"));
+        let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+
+        let req = test::TestRequest::get().uri("/api/v1/commands").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "public, max-age=300");
+        assert_ne!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+
+        let req = test::TestRequest::get().uri("/api/v1/commands/1").insert_header(("If-None-Match", etag.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 304);
+        assert_eq!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+
+        // 私人数据的写入不影响 ETag
+        let req = test::TestRequest::post().uri("/api/v1/users").set_json(serde_json::json!({"name": "erin"})).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/v1/commands/1").insert_header(("If-None-Match", etag.as_str())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 304);
+
+        // 写入覆盖库的内容表后 ETag 失效
+        let req = test::TestRequest::put()
+            .uri("/api/v1/admin/commands/1/sections/TLDR")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"content": "grep -rn pattern ."}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/v1/commands/1").insert_header(("If-None-Match", etag.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_ne!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+
+        // 随机端点与带 token 的请求不缓存
+        let req = test::TestRequest::get().uri("/api/v1/random-tip").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(!resp.headers().contains_key("etag"));
+        let req = test::TestRequest::get().uri("/api/v1/commands/1").insert_header(("Authorization", "Bearer x")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(!resp.headers().contains_key("etag"));
+    }
+
+    #[actix_web::test]
+    async fn test_response_cache_hits_and_invalidation() {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .app_data(web::Data::new(response_cache::ResponseCache::new(2)))
+                .wrap(middleware::from_fn(response_cache::middleware))
+                .service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+        let stats = |resp: serde_json::Value| {
+            let data = &resp["data"];
+            (data["hits"].as_u64().unwrap(), data["misses"].as_u64().unwrap(), data["entries"].as_u64().unwrap())
+        };
+
+        let first: serde_json::Value = test::call_and_read_body_json(&app, get("/api/v1/commands/1")).await;
+        let second: serde_json::Value = test::call_and_read_body_json(&app, get("/api/v1/commands/1")).await;
+        assert_eq!(first, second);
+        assert_eq!(second["data"]["tldr"], "grep pattern file");
+        assert_eq!(stats(test::call_and_read_body_json(&app, get("/api/v1/cache/stats")).await), (1, 1, 1));
+
+        // 容量为 2，最久未使用的条目被淘汰
+        test::call_service(&app, get("/api/v1/stats")).await;
+        test::call_service(&app, get("/api/v1/categories")).await;
+        test::call_service(&app, get("/api/v1/commands/1")).await;
+        assert_eq!(stats(test::call_and_read_body_json(&app, get("/api/v1/cache/stats")).await), (1, 4, 2));
+
+        // 写入后缓存失效，返回新内容
+        let req = test::TestRequest::put()
+            .uri("/api/v1/admin/commands/1/sections/TLDR")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"content": "grep -rn pattern ."}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/v1/commands/1")).await;
+        assert_eq!(resp["data"]["tldr"], "grep -rn pattern .");
+        assert_eq!(stats(test::call_and_read_body_json(&app, get("/api/v1/cache/stats")).await), (1, 5, 1));
+    }
+
+    #[actix_web::test]
+    async fn test_cors_preflight() {
+        use actix_web::http::{header, Method, StatusCode};
+        use actix_web::test;
+
+        let cors_config = config::CorsConfig {
+            origins: vec!["https://app.example.com".to_string(), "https://*.example.org".to_string()],
+            allow_credentials: true,
+            max_age: Some(600),
+            ..Default::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(cors::build(&cors_config))
+                .route("/api/v1/search", web::get().to(HttpResponse::Ok)),
+        ).await;
+
+        let preflight = |origin: &str| {
+            test::TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri("/api/v1/search")
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization"))
+                .to_request()
+        };
+
+        // 精确来源与子域名通配
+        for origin in ["https://app.example.com", "https://docs.example.org", "https://a.b.example.org"] {
+            let resp = test::call_service(&app, preflight(origin)).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", origin);
+            let headers = resp.headers();
+            assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), origin);
+            assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+            assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+            let methods = headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap().to_str().unwrap();
+            assert!(methods.contains("GET") && methods.contains("DELETE"));
+            assert!(!methods.contains("PATCH"));
+        }
+
+        // 裸域名、后缀伪造、错误协议和未列出的来源都被拒绝
+        for origin in ["https://example.org", "https://app.example.org.evil.com", "http://docs.example.org", "https://other.com"] {
+            let resp = test::call_service(&app, preflight(origin)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", origin);
+            assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        }
+
+        // 未允许的方法
+        let req = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/v1/search")
+            .insert_header((header::ORIGIN, "https://app.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        // 实际请求带上暴露的响应头
+        let req = test::TestRequest::get()
+            .uri("/api/v1/search")
+            .insert_header((header::ORIGIN, "https://docs.example.org"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://docs.example.org");
+        assert!(resp.headers().get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap().to_str().unwrap().to_lowercase().contains("etag"));
+    }
+
+    #[actix_web::test]
+    async fn test_embedded_assets() {
+        use actix_web::http::{header, StatusCode};
+        use actix_web::test;
+
+        // 目录中的前端文件都已嵌入
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for dir in assets::DIRS {
+            for entry in std::fs::read_dir(root.join(dir)).unwrap() {
+                let path = format!("{}/{}", dir, entry.unwrap().file_name().to_string_lossy());
+                assert!(assets::Assets::embedded_paths().any(|p| p == path), "{} is not embedded", path);
+            }
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(assets::Assets::new(None)))
+                .configure(assets::configure),
+        ).await;
+
+        // 页面引用带内容哈希的资源，且本身每次都需重新验证
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/html; charset=utf-8");
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+        let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(!html.contains("?v=11"));
+        let start = html.find("/stylesheets/base.css?v=").unwrap();
+        let css_url = &html[start..html[start..].find('"').unwrap() + start];
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri(css_url).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/css; charset=utf-8");
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "public, max-age=31536000, immutable");
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+        let css = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(css.contains("url(/images/icon-search.svg?v="));
+
+        // 版本不匹配时不能长期缓存；ETag 命中返回 304
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/stylesheets/base.css?v=11").to_request()).await;
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+        let req = test::TestRequest::get().uri("/stylesheets/base.css").insert_header((header::IF_NONE_MATCH, etag)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_MODIFIED);
+
+        // MIME 类型与压缩
+        let req = test::TestRequest::get().uri("/scripts/copy.js").insert_header((header::ACCEPT_ENCODING, "gzip")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/javascript; charset=utf-8");
+        assert_eq!(resp.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/images/icon-search.svg").to_request()).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "image/svg+xml");
+
+        for uri in ["/scripts/missing.js", "/scripts/../index.html", "/src/main.rs"] {
+            let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri);
+        }
+
+        // 开发模式从磁盘读取，修改后立即生效
+        let dir = std::env::temp_dir().join(format!("lcl-assets-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("scripts")).unwrap();
+        std::fs::write(dir.join("index.html"), "<script src=\"/scripts/app.js?v=1\"></script>").unwrap();
+        std::fs::write(dir.join("scripts/app.js"), "one").unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(assets::Assets::new(Some(dir.clone()))))
+                .configure(assets::configure),
+        ).await;
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(test::read_body(resp).await, "<script src=\"/scripts/app.js?v=1\"></script>");
+        std::fs::write(dir.join("scripts/app.js"), "two").unwrap();
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/scripts/app.js?v=1").to_request()).await;
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-cache");
+        assert_eq!(test::read_body(resp).await, "two");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_response_compression() {
+        use actix_web::http::header;
+        use actix_web::test;
+        use std::io::Read;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .app_data(web::Data::new(http_cache::HttpCache::new(300, Default::default())))
+                .app_data(web::Data::new(response_cache::ResponseCache::new(16)))
+                .wrap(middleware::from_fn(response_cache::middleware))
+                .wrap(middleware::from_fn(http_cache::middleware))
+                .wrap(middleware::Compress::default())
+                .service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+        let get = |uri: &str, accept: &str| {
+            test::TestRequest::get().uri(uri).insert_header((header::ACCEPT_ENCODING, accept)).to_request()
+        };
+        let decode = |encoding: &str, body: &[u8]| -> Vec<u8> {
+            let mut out = Vec::new();
+            match encoding {
+                "br" => brotli::Decompressor::new(body, 4096).read_to_end(&mut out).unwrap(),
+                "zstd" => zstd::stream::read::Decoder::new(body).unwrap().read_to_end(&mut out).unwrap(),
+                "gzip" => flate2::read::GzDecoder::new(body).read_to_end(&mut out).unwrap(),
+                _ => return body.to_vec(),
+            };
+            out
+        };
+
+        // 完整命令列表使用预先压缩的版本，按 q 值协商，不会被中间件重复压缩
+        let identity = test::call_and_read_body(&app, get("/api/v1/commands", "identity")).await;
+        let commands: serde_json::Value = serde_json::from_slice(&identity).unwrap();
+        assert!(!commands["data"].as_array().unwrap().is_empty());
+        for (accept, expected) in [("br, gzip", "br"), ("gzip;q=1, br;q=0.5", "gzip"), ("zstd", "zstd")] {
+            let resp = test::call_service(&app, get("/api/v1/commands", accept)).await;
+            let headers = resp.headers().clone();
+            assert_eq!(headers.get_all(header::CONTENT_ENCODING).count(), 1, "{}", accept);
+            assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), expected, "{}", accept);
+            let vary: Vec<_> = headers.get_all(header::VARY).map(|v| v.to_str().unwrap().to_ascii_lowercase()).collect();
+            assert!(vary.iter().any(|v| v.contains("accept-encoding")) && vary.iter().any(|v| v.contains("authorization")));
+            // 各编码共用同一个 ETag，因此必须是弱校验值
+            assert!(headers.get(header::ETAG).unwrap().to_str().unwrap().starts_with("W/\""));
+            let body = test::read_body(resp).await;
+            assert!(body.len() < identity.len());
+            assert_eq!(decode(expected, &body), identity.to_vec());
+        }
+
+        // 写入后重建命令列表
+        let req = test::TestRequest::post()
+            .uri("/api/v1/admin/commands")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"name": "deployctl", "category": 1, "description": "Deploy"}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let resp = test::call_service(&app, get("/api/v1/commands", "br")).await;
+        let body = decode("br", &test::read_body(resp).await);
+        let updated: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(updated["data"].as_array().unwrap().len(), commands["data"].as_array().unwrap().len() + 1);
+
+        // 其他响应由中间件按请求压缩，响应缓存中保存的是原文
+        for _ in 0..2 {
+            let resp = test::call_service(&app, get("/api/v1/commands/1", "zstd")).await;
+            assert_eq!(resp.headers().get(header::CONTENT_ENCODING).unwrap(), "zstd");
+            let detail: serde_json::Value = serde_json::from_slice(&decode("zstd", &test::read_body(resp).await)).unwrap();
+            assert_eq!(detail["data"]["name"], "grep");
+        }
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/api/v1/commands/1").to_request()).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[actix_web::test]
+    async fn test_streaming_export() {
+        use actix_web::http::header;
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        {
+            let conn = data.lock_db().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE BasicGroup (id INTEGER PRIMARY KEY, category_id INTEGER, position INTEGER, description TEXT);
+                 CREATE TABLE BasicCommand (id INTEGER PRIMARY KEY, group_id INTEGER, command TEXT, mans TEXT);
+                 INSERT INTO BasicGroup (id, category_id, position, description) VALUES (1, 1, 1, 'Uptime');
+                 INSERT INTO BasicCommand (group_id, command, mans) VALUES (1, 'uptime -p', 'uptime');",
+            ).unwrap();
+            // 超过一个批次，验证分批游标
+            let tx = conn.unchecked_transaction().unwrap();
+            for i in 0..1200 {
+                tx.execute("INSERT INTO Command (category, name, description) VALUES (1, ?1, 'generated')", [format!("gen{:04}", i)]).unwrap();
+            }
+            tx.commit().unwrap();
+        }
+        let app = test::init_service(App::new().app_data(data.clone()).service(web::scope("/api/v1").configure(configure_api))).await;
+
+        // 覆盖库中的 grep 遮蔽上游 grep
+        let req = test::TestRequest::post()
+            .uri("/api/v1/admin/commands")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({
+                "name": "grep", "category": 1, "description": "Team grep",
+                "sections": [{"title": "TLDR", "content": "grep -e \"a,b\"\nnext"}]
+            }))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/api/v1/export").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/x-ndjson");
+        assert!(resp.headers().get(header::CONTENT_DISPOSITION).unwrap().to_str().unwrap().contains("linux-command-library-all.ndjson"));
+        let body = test::read_body(resp).await;
+        let records: Vec<serde_json::Value> = std::str::from_utf8(&body).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let of_type = |kind: &str| records.iter().filter(|r| r["type"] == kind).collect::<Vec<_>>();
+
+        let commands = of_type("command");
+        assert_eq!(commands.len(), 1202);
+        let ids: HashSet<i64> = commands.iter().map(|c| c["id"].as_i64().unwrap()).collect();
+        assert_eq!(ids.len(), 1202);
+        let grep: Vec<_> = commands.iter().filter(|c| c["name"] == "grep").collect();
+        assert_eq!(grep.len(), 1);
+        assert_eq!(grep[0]["source"], "overlay");
+        assert!(grep[0]["id"].as_i64().unwrap() >= overlay::OVERLAY_ID_OFFSET);
+        let chmod = commands.iter().find(|c| c["name"] == "chmod").unwrap();
+        assert_eq!(chmod["category"], "System control");
+        assert_eq!(of_type("category").len(), 2);
+        let one_liners = of_type("one_liner");
+        assert_eq!(one_liners.len(), 1);
+        assert_eq!(one_liners[0]["category"], "System");
+        assert_eq!(one_liners[0]["group"], "Uptime");
+        assert_eq!(of_type("tip")[0]["sections"][0]["data1"], "Use Ctrl+A to go to beginning of line");
+        // 记录按种类依次输出
+        let first_tip = records.iter().position(|r| r["type"] == "tip").unwrap();
+        assert!(records[..first_tip].iter().all(|r| r["type"] != "tip") && records[first_tip..].iter().all(|r| r["type"] == "tip"));
+
+        // CSV：每个章节一行，字段按 RFC 4180 转义
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/api/v1/export?format=csv&kind=commands").to_request()).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/csv; charset=utf-8");
+        let csv = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(csv.starts_with("id,name,category,description,source,section,content\r\n"));
+        assert!(csv.contains(",grep,Miscellaneous,Team grep,overlay,TLDR,\"grep -e \"\"a,b\"\"\nnext\"\r\n"));
+        assert!(csv.contains("2,chmod,System control,Change file permissions,base,,\r\n"));
+
+        for uri in ["/api/v1/export?format=csv", "/api/v1/export?kind=everything", "/api/v1/export?format=xml"] {
+            let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), 400, "{}", uri);
+        }
+
+        // 命令行模式
+        let mut out = Vec::new();
+        let records = export::run(data.clone(), export::Format::Csv, "one_liners".parse().unwrap(), &mut out).unwrap();
+        assert_eq!(records, 1);
+        assert_eq!(String::from_utf8(out).unwrap(), "category,group,command,mans,source\r\nSystem,Uptime,uptime -p,uptime,base\r\n");
+    }
+
+    #[actix_web::test]
+    async fn test_docset_export() {
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        {
+            let conn = data.lock_db().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE BasicGroup (id INTEGER PRIMARY KEY, category_id INTEGER, position INTEGER, description TEXT);
+                 CREATE TABLE BasicCommand (id INTEGER PRIMARY KEY, group_id INTEGER, command TEXT, mans TEXT);
+                 INSERT INTO BasicGroup (id, category_id, position, description) VALUES (1, 2, 1, 'Find text');
+                 INSERT INTO BasicCommand (group_id, command, mans) VALUES (1, 'grep -r foo . | less', 'grep, less');
+                 INSERT INTO Command (category, name, description) VALUES (1, 'Mail', 'Send mail');
+                 INSERT INTO Command (category, name, description) VALUES (1, 'mail', 'Send mail');
+                 INSERT INTO CommandSection (title, content, command_id) VALUES ('SEE ALSO', '<b>chmod</b>(1)<br><script>x</script> &amp;', 2);",
+            ).unwrap();
+        }
+        let app = test::init_service(App::new().app_data(data.clone()).service(web::scope("/api/v1").configure(configure_api))).await;
+        let req = test::TestRequest::post()
+            .uri("/api/v1/admin/commands")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"name": "grep", "category": 1, "description": "Team grep", "sections": []}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let dir = std::env::temp_dir().join(format!("lcl-docset-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("Team.docset");
+        let summary = docset::build(&data, &output, false).unwrap();
+        assert_eq!((summary.commands, summary.guides), (4, 1));
+        assert!(!dir.join("Team.docset.partial").exists());
+
+        let contents = output.join("Contents");
+        let plist = std::fs::read_to_string(contents.join("Info.plist")).unwrap();
+        assert!(plist.contains("<string>Team</string>") && plist.contains("<key>isDashDocset</key>"));
+
+        let index = Connection::open(contents.join("Resources/docSet.dsidx")).unwrap();
+        let entries: Vec<(String, String, String)> = index
+            .prepare("SELECT name, type, path FROM searchIndex ORDER BY type, name")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        // 覆盖库的 grep 遮蔽上游 grep；不区分大小写的文件名不冲突
+        assert_eq!(entries, vec![
+            ("Mail".to_string(), "Command".to_string(), "commands/Mail.html".to_string()),
+            ("chmod".to_string(), "Command".to_string(), "commands/chmod.html".to_string()),
+            ("grep".to_string(), "Command".to_string(), "commands/grep.html".to_string()),
+            ("mail".to_string(), "Command".to_string(), "commands/mail-2.html".to_string()),
+            ("Files".to_string(), "Guide".to_string(), "guides/Files.html".to_string()),
+        ]);
+
+        let documents = contents.join("Resources/Documents");
+        let grep = std::fs::read_to_string(documents.join("commands/grep.html")).unwrap();
+        assert!(grep.contains("Team grep"));
+        let chmod = std::fs::read_to_string(documents.join("commands/chmod.html")).unwrap();
+        assert!(chmod.contains("//apple_ref/cpp/Section/SEE%20ALSO"));
+        assert!(chmod.contains("<b>chmod</b>(1)\n&lt;script&gt;x&lt;/script&gt; &amp;"));
+        let guide = std::fs::read_to_string(documents.join("guides/Files.html")).unwrap();
+        assert!(guide.contains("<pre>grep -r foo . | less</pre>"));
+        assert!(guide.contains("<a href=\"../commands/grep.html\">grep</a>, less"));
+        let index_page = std::fs::read_to_string(documents.join("index.html")).unwrap();
+        assert!(index_page.contains("<a href=\"guides/Files.html\">Files</a>"));
+
+        // 已存在时需要 --force
+        assert!(docset::build(&data, &output, false).is_err());
+        assert_eq!(docset::build(&data, &output, true).unwrap().commands, 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tldr_import_and_export() {
+        let data = test_app_state("secret");
+        let dir = std::env::temp_dir().join(format!("lcl-tldr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for platform in ["common", "linux", "osx"] {
+            std::fs::create_dir_all(dir.join("pages").join(platform)).unwrap();
+        }
+        let page = |name: &str, example: &str| {
+            format!("# {}\n\n> Do things.\n> More information: <https://example.com/{}>.\n\n- {}:\n\n`{} {{{{path/to/file}}}} > out`\n", name, name, example, name)
+        };
+        std::fs::write(dir.join("pages/common/grep.md"), page("grep", "Common example")).unwrap();
+        std::fs::write(dir.join("pages/linux/grep.md"), page("grep", "Linux example")).unwrap();
+        std::fs::write(dir.join("pages/osx/chmod.md"), page("chmod", "Ignored")).unwrap();
+        std::fs::write(dir.join("pages/common/frobnicate.md"), page("frobnicate", "Frobnicate")).unwrap();
+        std::fs::write(dir.join("pages/common/broken.md"), "# broken\n\n- No command:\n").unwrap();
+
+        let summary = tldr::import(&data, &dir, false).unwrap();
+        assert_eq!((summary.updated, summary.created, summary.unchanged), (1, 0, 0));
+        assert_eq!(summary.unmatched, vec!["frobnicate".to_string()]);
+        assert_eq!(summary.failed.len(), 1);
+
+        // 上游 grep 被复制到覆盖库，TLDR 使用 linux 平台的页面
+        let grep = find_command_by_name(&data, "grep").unwrap().unwrap();
+        assert_eq!(grep.source, Source::Overlay);
+        let tldr = resolve_command_detail(&data, grep.id).unwrap().unwrap().tldr.unwrap();
+        assert!(tldr.starts_with("Do things.<br>More information: &lt;https://example.com/grep&gt;.<br><br><b>Linux example</b>"));
+        assert!(tldr.ends_with("<code>grep {{path/to/file}} &gt; out</code>"));
+        let revisions: i64 = data.lock_overlay().unwrap().unwrap()
+            .query_row("SELECT COUNT(*) FROM CommandRevision WHERE author = 'tldr-import'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(revisions, 1);
+
+        // 再次导入没有变化；create_missing 为缺少的命令建立覆盖库命令
+        let summary = tldr::import(&data, &dir, true).unwrap();
+        assert_eq!((summary.updated, summary.created, summary.unchanged), (0, 1, 1));
+        assert_eq!(find_command_by_name(&data, "frobnicate").unwrap().unwrap().description, "Do things.");
+
+        // 导出 TLDR 与团队示例
+        data.lock_overlay().unwrap().unwrap()
+            .execute("INSERT INTO CommandExample (command_name, description, command, author) VALUES ('grep', 'Count matches', 'grep -c foo file', 'alice')", [])
+            .unwrap();
+        let out = dir.join("out");
+        assert_eq!(tldr::export(&data, &out, "common").unwrap(), 2);
+        let exported = std::fs::read_to_string(out.join("pages/common/grep.md")).unwrap();
+        assert_eq!(
+            exported,
+            "# grep\n\n> Do things.\n> More information: <https://example.com/grep>.\n\n- Linux example:\n\n`grep {{path/to/file}} > out`\n\n- Count matches:\n\n`grep -c foo file`\n"
+        );
+        let parsed = tldr::Page::parse_markdown(&exported).unwrap();
+        assert_eq!(parsed.examples.len(), 2);
+        assert!(!out.join("pages/common/chmod.md").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_man_page_import() {
+        use std::io::Write;
+
+        let data = test_app_state("secret");
+        let dir = std::env::temp_dir().join(format!("lcl-man-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for section in ["man1", "man3", "man8"] {
+            std::fs::create_dir_all(dir.join(section)).unwrap();
+        }
+        let gzip = |path: &str, source: &str| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(source.as_bytes()).unwrap();
+            std::fs::write(dir.join(path), encoder.finish().unwrap()).unwrap();
+        };
+        gzip(
+            "man1/frob.1.gz",
+            ".\\\" comment\n.TH FROB 1\n.de XX\n.B ignored\n..\n.SH NAME\nfrob \\- frobnicate \\fIfiles\\fR\n.SH SYNOPSIS\n.B frob\n[\\fB\\-v\\fR] <file>\n\
+             .SH OPTIONS\n.TP\n.BR \\-v \", \" \\-\\-verbose\nBe\nloud.\n.SS Exit\nZero \\(em ok.\n.SH EXAMPLE\n.nf\nfrob a\nfrob b\n.fi\n",
+        );
+        std::fs::write(dir.join("man1/unfrob.1"), ".so man1/frob.1\n").unwrap();
+        gzip("man1/bsd.1.gz", ".Dd May 1, 2020\n.Dt BSD 1\n.Sh NAME\n.Nm bsd\n");
+        std::fs::write(dir.join("man1/empty.1"), "just text\n").unwrap();
+        gzip("man8/grep.8.gz", ".TH GREP 8\n.SH NAME\ngrep \\- system grep\n.SH SYNOPSIS\n.B grep\n");
+        gzip("man3/printf.3.gz", ".TH PRINTF 3\n.SH NAME\nprintf \\- library call\n");
+
+        let page = man::parse(&String::from_utf8_lossy(&std::fs::read(dir.join("man1/empty.1")).unwrap()), "empty");
+        assert_eq!(page.unwrap_err(), "no .SH sections found");
+
+        // 上游已有的 grep 默认跳过，man3 不在默认章节中
+        let sections: Vec<String> = man::DEFAULT_SECTIONS.split(',').map(String::from).collect();
+        let summary = man::import(&data, &dir, &sections, false).unwrap();
+        assert_eq!((summary.imported, summary.replaced, summary.skipped), (2, 0, 1));
+        let mut failed: Vec<_> = summary.failed.iter().map(|(path, reason)| (path.file_name().unwrap().to_str().unwrap(), reason.as_str())).collect();
+        failed.sort();
+        assert_eq!(failed, vec![("bsd.1.gz", "mdoc (BSD) pages are not supported"), ("empty.1", "no .SH sections found")]);
+
+        let frob = find_command_by_name(&data, "frob").unwrap().unwrap();
+        assert_eq!(frob.source, Source::Overlay);
+        assert_eq!(frob.description, "frobnicate files");
+        let detail = resolve_command_detail(&data, frob.id).unwrap().unwrap();
+        let section = |title: &str| detail.sections.iter().find(|s| s.title == title).unwrap().content.clone();
+        assert_eq!(section("SYNOPSIS"), "<b>frob</b> [<b>-v</b>] &lt;file&gt;");
+        assert_eq!(section("OPTIONS"), "<b>-v</b>, <b>--verbose</b>\n    Be loud.\n\n<b>Exit</b>\nZero — ok.");
+        assert_eq!(section("EXAMPLE"), "frob a\nfrob b");
+        // .so 重定向使用目标页面的内容，名称取自文件名
+        assert_eq!(find_command_by_name(&data, "unfrob").unwrap().unwrap().description, "frobnicate files");
+
+        let summary = man::import(&data, &dir.join("man8"), &sections, true).unwrap();
+        assert_eq!((summary.imported, summary.replaced), (0, 1));
+        let grep = find_command_by_name(&data, "grep").unwrap().unwrap();
+        assert_eq!((grep.source, grep.description.as_str()), (Source::Overlay, "system grep"));
+        // 替换上游命令时保留分类与 TLDR，其余章节来自手册页
+        let detail = resolve_command_detail(&data, grep.id).unwrap().unwrap();
+        assert_eq!(detail.category, 1);
+        assert_eq!(detail.tldr.as_deref(), Some("grep pattern file"));
+        let titles: Vec<&str> = detail.sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["TLDR", "SYNOPSIS"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit() {
+        use actix_web::http::StatusCode;
+        use actix_web::test;
+        use std::net::SocketAddr;
+
+        let mut config = config::Config::default();
+        config.rate_limit.search = config::GroupLimit { per_minute: 60, burst: 2 };
+        config.rate_limit.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(rate_limit::RateLimiter::from_config(&config.rate_limit)))
+                .wrap(middleware::from_fn(rate_limit::middleware))
+                .wrap(middleware::from_fn(request_id::middleware))
+                .route("/api/v1/search", web::get().to(HttpResponse::Ok))
+                .route("/api/v1/stats", web::get().to(HttpResponse::Ok))
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        ).await;
+        let get = |path: &str, peer: &str, forwarded_for: Option<&str>| {
+            let mut req = test::TestRequest::get().uri(path).peer_addr(SocketAddr::new(peer.parse().unwrap(), 40000));
+            if let Some(forwarded_for) = forwarded_for {
+                req = req.insert_header(("X-Forwarded-For", forwarded_for));
+            }
+            req.to_request()
+        };
+
+        for _ in 0..2 {
+            assert_eq!(test::call_service(&app, get("/api/v1/search?q=ls", "203.0.113.5", None)).await.status(), StatusCode::OK);
+        }
+        let resp = test::call_service(&app, get("/api/v1/search?q=ls", "203.0.113.5", None)).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "1");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "RATE_LIMITED");
+        assert!(body["request_id"].is_string());
+
+        // 其他分组与非 API 路由不受影响
+        assert_eq!(test::call_service(&app, get("/api/v1/stats", "203.0.113.5", None)).await.status(), StatusCode::OK);
+        assert_eq!(test::call_service(&app, get("/health", "203.0.113.5", None)).await.status(), StatusCode::OK);
+
+        // 不受信任的直连地址伪造 X-Forwarded-For 无效
+        let resp = test::call_service(&app, get("/api/v1/search?q=ls", "203.0.113.5", Some("198.51.100.1"))).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        // 经受信任代理转发时按真实客户端计数
+        let resp = test::call_service(&app, get("/api/v1/search?q=ls", "10.0.0.1", Some("203.0.113.5, 198.51.100.7, 10.1.2.3"))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_tls_and_unix_socket_listeners() {
+        use std::io::{Read, Write};
+        use std::sync::Arc;
+
+        let dir = std::env::temp_dir().join(format!("lcl-listeners-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tls = config::TlsConfig {
+            cert: dir.join("cert.pem").display().to_string(),
+            key: dir.join("key.pem").display().to_string(),
+        };
+        let issue = || {
+            let issued = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            std::fs::write(&tls.cert, issued.cert.pem()).unwrap();
+            std::fs::write(&tls.key, issued.signing_key.serialize_pem()).unwrap();
+            issued.cert.der().clone()
+        };
+
+        let first = issue();
+        let reloader = Arc::new(listener::CertReloader::load(&tls).unwrap());
+        let socket = dir.join("api.sock");
+        std::fs::write(&socket, "").unwrap();
+        assert!(listener::remove_stale_socket(&socket).is_err());
+        std::fs::remove_file(&socket).unwrap();
+
+        let server = HttpServer::new(|| App::new().route("/health", web::get().to(HttpResponse::Ok)))
+            .workers(1)
+            .bind_rustls_0_23("127.0.0.1:0", reloader.server_config())
+            .unwrap();
+        let port = server.addrs()[0].port();
+        let server = server.bind_uds(&socket).unwrap().run();
+        listener::set_socket_mode(&socket, 0o600).unwrap();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        // 返回响应状态行与服务端证书
+        let https_get = move |root: rustls::pki_types::CertificateDer<'static>| {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(root).unwrap();
+            let client = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let conn = rustls::ClientConnection::new(Arc::new(client), "localhost".try_into().unwrap()).unwrap();
+            let mut stream = rustls::StreamOwned::new(conn, std::net::TcpStream::connect(("127.0.0.1", port)).unwrap());
+            stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response);
+            let served = stream.conn.peer_certificates().unwrap()[0].clone();
+            (String::from_utf8_lossy(&response).lines().next().unwrap_or_default().to_string(), served)
+        };
+
+        let (status, served) = actix_web::rt::task::spawn_blocking({
+            let first = first.clone();
+            move || https_get(first)
+        }).await.unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(served, first);
+
+        // 重新加载后新连接使用新证书；无效证书不替换当前证书
+        let second = issue();
+        reloader.reload().unwrap();
+        std::fs::write(&tls.cert, "not a certificate").unwrap();
+        assert!(reloader.reload().is_err());
+        let (status, served) = actix_web::rt::task::spawn_blocking({
+            let second = second.clone();
+            move || https_get(second)
+        }).await.unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(served, second);
+
+        // Unix 套接字
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let status = actix_web::rt::task::spawn_blocking({
+            let socket = socket.clone();
+            move || {
+                let mut stream = std::os::unix::net::UnixStream::connect(socket).unwrap();
+                stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response.lines().next().unwrap_or_default().to_string()
+            }
+        }).await.unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK");
+
+        handle.stop(true).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_graceful_shutdown() {
+        use std::io::{Read, Write};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        let data = test_state_with_admin("secret");
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str, result: Result<(), AppError>| {
+            let order = order.clone();
+            move |_: &AppState| {
+                order.lock().unwrap().push(name);
+                result
+            }
+        };
+        data.shutdown.add_hook("first", record("first", Err(AppError::InternalError("flush failed".to_string()))));
+        shutdown::register_database_hooks(&data);
+        data.shutdown.add_hook("last", record("last", Ok(())));
+
+        let app_data = data.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_data.clone())
+                .route("/health/ready", web::get().to(health::ready))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let port = server.addrs()[0].port();
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let get = move |path: &'static str| {
+            actix_web::rt::task::spawn_blocking(move || {
+                let mut stream = std::net::TcpStream::connect(("127.0.0.1", port))?;
+                write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path)?;
+                let mut response = String::new();
+                stream.read_to_string(&mut response)?;
+                Ok::<_, std::io::Error>(response)
+            })
+        };
+
+        // 测试库缺少部分表，停机前为 degraded
+        assert!(get("/health/ready").await.unwrap().unwrap().contains("\"degraded\""));
+
+        // 停机延迟期间仍接受请求，但就绪检查失败
+        let drain = actix_web::rt::spawn({
+            let data = data.clone();
+            async move { shutdown::drain(&handle, &data, Duration::from_millis(300)).await }
+        });
+        actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        let ready = get("/health/ready").await.unwrap().unwrap();
+        assert!(ready.starts_with("HTTP/1.1 503"), "{}", ready);
+        assert!(ready.contains("\"shutting_down\""));
+
+        drain.await.unwrap();
+        assert!(get("/health/ready").await.unwrap().is_err());
+
+        // 钩子按注册顺序执行，失败不影响后续钩子，且只执行一次
+        assert_eq!(data.shutdown.run_hooks(&data), 1);
+        assert_eq!(*order.lock().unwrap(), vec!["first", "last"]);
+        assert_eq!(data.shutdown.run_hooks(&data), 0);
+        // 数据库连接已关闭
+        assert!(data.lock_db().unwrap().query_row("SELECT COUNT(*) FROM Command", [], |_| Ok(())).is_err());
+        assert!(data.lock_overlay().unwrap().unwrap().query_row("SELECT COUNT(*) FROM Command", [], |_| Ok(())).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_liveness_and_readiness() {
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .route("/health/live", web::get().to(health::live))
+                .route("/health/ready", web::get().to(health::ready)),
+        ).await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/health/live").to_request()).await;
+        assert_eq!(resp.status(), 200);
+
+        // 测试库缺少分组表，视为降级
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/health/ready").to_request()).await;
+        assert_eq!(resp.status(), 503);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["data"]["status"], "degraded");
+        assert_eq!(body["data"]["database"]["missing_tables"], serde_json::json!(["BasicGroup", "BasicCommand"]));
+        assert_eq!(body["data"]["overlay"]["ok"], true);
+
+        data.lock_db().unwrap().execute_batch(
+            "CREATE TABLE BasicGroup (id INTEGER PRIMARY KEY, category_id INTEGER, position INTEGER, description TEXT);
+             CREATE TABLE BasicCommand (id INTEGER PRIMARY KEY, group_id INTEGER, command TEXT, mans TEXT);",
+        ).unwrap();
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/health/ready").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["status"], "ready");
+        assert_eq!(body["data"]["content_hash"], "test");
+        assert_eq!(body["data"]["database"]["user_version"], 0);
+    }
+
+    #[actix_web::test]
+    async fn test_prometheus_metrics() {
+        use actix_web::test;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(test_state_with_admin("secret"))
+                .wrap(middleware::from_fn(metrics::middleware))
+                .route("/metrics", web::get().to(metrics::export))
+                .service(web::scope("/api/v1").configure(configure_api)),
+        ).await;
+
+        test::call_service(&app, test::TestRequest::get().uri("/api/v1/commands/1").to_request()).await;
+        test::call_service(&app, test::TestRequest::get().uri("/api/v1/search?q=zzzz").to_request()).await;
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+        // 路由按模板分组
+        let line = body
+            .lines()
+            .find(|l| l.starts_with("http_requests_total{") && l.contains(r#"route="/api/v1/commands/{id}""#))
+            .unwrap();
+        assert!(line.contains(r#"status="200""#) && line.contains(r#"method="GET""#));
+        assert!(body.contains("http_request_duration_seconds_bucket{"));
+        assert!(body.contains(r#"db_lock_wait_seconds_bucket{database="base""#));
+        assert!(body.lines().any(|l| l.starts_with("search_zero_results_total ") && !l.ends_with(" 0")));
+        assert!(body.contains(r#"catalog_items{kind="commands"} 2"#));
+        assert!(body.contains(r#"catalog_items{kind="tips"} 1"#));
+    }
+
+    #[actix_web::test]
+    async fn test_search_limit_is_clamped() {
+        use actix_web::test;
+
+        for data in [test_app_state("secret"), test_app_state("secret").with_catalog().unwrap()] {
+            let app = test::init_service(App::new().app_data(web::Data::new(data)).service(web::scope("/api").configure(configure_api))).await;
+            for (limit, expected) in [("-1", 1), ("0", 1), ("2", 2)] {
+                let req = test::TestRequest::get().uri(&format!("/api/search?q=e&limit={}", limit)).to_request();
+                let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+                assert_eq!(resp["data"].as_array().unwrap().len(), expected, "limit={}", limit);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_preloaded_catalog_serves_reads_and_rebuilds() {
+        use actix_web::test;
+
+        let data = web::Data::new(test_app_state("secret").with_catalog().unwrap());
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .service(web::scope("/api").configure(configure_api)),
+        ).await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        // 直接修改上游库不会被察觉：读取来自快照
+        data.lock_db().unwrap().execute("DELETE FROM Command WHERE name = 'chmod'", []).unwrap();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/commands")).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/search?q=CH")).await;
+        assert_eq!(resp["data"][0]["name"], "chmod");
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/suggestions?q=GR")).await;
+        assert_eq!(resp["data"], serde_json::json!(["grep"]));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/commands/1")).await;
+        assert_eq!(resp["data"]["tldr"], "grep pattern file");
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/random-tip")).await;
+        assert_eq!(resp["data"]["title"], "Quick Navigation");
+        assert_eq!(test::call_service(&app, get("/api/category/Nope")).await.status(), 404);
+
+        // 覆盖库写入使快照过期，下一次读取重新构建
+        let req = test::TestRequest::put()
+            .uri("/api/admin/commands/1/sections/TLDR")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"content": "grep -rn pattern ."}))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/commands/1")).await;
+        assert_eq!(resp["data"]["source"], "overlay");
+        assert_eq!(resp["data"]["tldr"], "grep -rn pattern .");
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, get("/api/commands")).await;
+        assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+
+        // 手动重新加载
+        let req = test::TestRequest::post().uri("/api/admin/reload").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+        let req = test::TestRequest::post()
+            .uri("/api/admin/reload")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["total_commands"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_rejects_invalid_input() {
+        use actix_web::http::Method;
+        use actix_web::test;
+
+        let app = test::init_service(App::new().app_data(test_state_with_admin("secret")).service(web::scope("/api").configure(configure_api))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/commands")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"name": "  ", "category": 1, "description": "Deploy"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        // 上游分类不能删除，只能修改（复制到覆盖库）
+        let req = test::TestRequest::delete()
+            .uri("/api/admin/categories/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::put()
+            .uri("/api/admin/categories/1")
+            .insert_header(("Authorization", "Bearer secret"))
+            .set_json(serde_json::json!({"title": "System", "position": 1}))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["data"]["source"], "overlay");
+
+        // 覆盖库中的名称不能重复，新建与改名都会检查
+        let admin = |method: Method, uri: &str, body: serde_json::Value| {
+            test::TestRequest::default()
+                .method(method)
+                .uri(uri)
+                .insert_header(("Authorization", "Bearer secret"))
+                .set_json(body)
+                .to_request()
+        };
+        let command = |name: &str| serde_json::json!({"name": name, "category": 1, "description": "Deploy"});
+        let tip = |title: &str| serde_json::json!({"title": title, "position": 1, "sections": [{"type": 0, "data1": "x", "data2": "", "extra": ""}]});
+        assert!(test::call_service(&app, admin(Method::POST, "/api/admin/commands", command("deployctl"))).await.status().is_success());
+        assert_eq!(test::call_service(&app, admin(Method::POST, "/api/admin/commands", command("deployctl"))).await.status(), 400);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, admin(Method::POST, "/api/admin/commands", command("rollout"))).await;
+        let uri = format!("/api/admin/commands/{}", resp["data"]["id"]);
+        assert_eq!(test::call_service(&app, admin(Method::PUT, &uri, command("deployctl"))).await.status(), 400);
+        assert!(test::call_service(&app, admin(Method::PUT, &uri, command("rollout"))).await.status().is_success());
+        let req = admin(Method::POST, "/api/admin/categories", serde_json::json!({"title": "System", "position": 1}));
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        assert!(test::call_service(&app, admin(Method::POST, "/api/admin/tips", tip("Team tip"))).await.status().is_success());
+        assert_eq!(test::call_service(&app, admin(Method::POST, "/api/admin/tips", tip("Team tip"))).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_rejects_renaming_upstream_copies() {
+        use actix_web::test;
+
+        let data = test_state_with_admin("secret");
+        let app = test::init_service(App::new().app_data(data.clone()).service(web::scope("/api").configure(configure_api))).await;
+        let rename = |uri: &str, body: serde_json::Value| {
+            test::TestRequest::put().uri(uri).insert_header(("Authorization", "Bearer secret")).set_json(body).to_request()
+        };
+        let names = |resp: serde_json::Value| -> Vec<String> {
+            resp["data"].as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap().to_string()).collect()
+        };
+
+        // 改名会让上游 grep 重新出现，复制一起回滚
+        let req = rename("/api/admin/commands/1", serde_json::json!({"name": "grep2", "category": 1, "description": "Renamed"}));
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let resp = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/api/commands").to_request()).await;
+        assert_eq!(names(resp), ["chmod", "grep"]);
+        let copies: i64 = data.lock_overlay().unwrap().unwrap()
+            .query_row("SELECT COUNT(*) FROM Command", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(copies, 0);
+
+        // 已复制的副本同样不能改名；只修改其他字段仍然可以
+        let req = rename("/api/admin/commands/1", serde_json::json!({"name": "grep", "category": 1, "description": "Team grep"}));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let copy = resp["data"]["id"].as_i64().unwrap();
+        let req = rename(&format!("/api/admin/commands/{}", copy), serde_json::json!({"name": "grep2", "category": 1, "description": "Team grep"}));
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/api/commands").to_request()).await;
+        let commands = resp["data"].as_array().unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(commands.iter().any(|c| c["name"] == "grep" && c["source"] == "overlay"));
+
+        let req = rename("/api/admin/categories/1", serde_json::json!({"title": "Systems", "position": 1}));
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = rename("/api/admin/tips/1", serde_json::json!({
+            "title": "Navigation",
+            "position": 1,
+            "sections": [{"type": 0, "data1": "Ctrl+A", "data2": "", "extra": ""}],
+        }));
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
 }
 
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    // 加载配置：默认值 < 配置文件 < 环境变量 < 命令行参数
+    let cli = <config::Cli as clap::Parser>::parse();
+    let config = match config::Config::load(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if cli.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+
+    logging::init(config.logging.format);
+
+    // 命令行导出模式：读取数据库后退出，日志写到标准错误
+    match &cli.command {
+        Some(config::CliCommand::Export(args)) => {
+            let data = web::Data::new(AppState::new(&config.database.path, config.database.overlay_path.as_deref())?);
+            let records = match &args.output {
+                Some(path) => export::run(data, args.format, args.kind, std::io::BufWriter::new(std::fs::File::create(path)?))?,
+                None => export::run(data, args.format, args.kind, std::io::stdout().lock())?,
+            };
+            info!("Exported {} records", records);
+            return Ok(());
+        }
+        Some(config::CliCommand::Docset(args)) => {
+            let data = AppState::new(&config.database.path, config.database.overlay_path.as_deref())?;
+            docset::build(&data, &args.output, args.force)?;
+            return Ok(());
+        }
+        Some(config::CliCommand::TldrImport(args)) => {
+            let data = AppState::new(&config.database.path, config.database.overlay_path.as_deref())?;
+            let summary = tldr::import(&data, &args.checkout, args.create_missing)?;
+            for (path, reason) in &summary.failed {
+                eprintln!("failed: {}: {}", path.display(), reason);
+            }
+            return Ok(());
+        }
+        Some(config::CliCommand::ManImport(args)) => {
+            let data = AppState::new(&config.database.path, config.database.overlay_path.as_deref())?;
+            let summary = man::import(&data, &args.dir, &args.sections, args.replace)?;
+            let report: String = summary.failed.iter().map(|(path, reason)| format!("{}\t{}\n", path.display(), reason)).collect();
+            match &args.report {
+                Some(path) => std::fs::write(path, report)?,
+                None => eprint!("{}", report),
+            }
+            return Ok(());
+        }
+        Some(config::CliCommand::TldrExport(args)) => {
+            let data = AppState::new(&config.database.path, config.database.overlay_path.as_deref())?;
+            tldr::export(&data, &args.output, &args.platform)?;
+            return Ok(());
+        }
+        None => {}
+    }
 
     info!("Starting Linux Command Library Web API Server");
 
     // 初始化数据库连接
-    let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "database.db".to_string());
-    let app_state = web::Data::new(AppState::new(&db_path)?);
+    let mut app_state = AppState::new(&config.database.path, config.database.overlay_path.as_deref())?;
+    if let Some(token) = config.features.admin_token.clone() {
+        app_state = app_state.with_admin_token(token)?;
+    }
+    if !config.features.open_registration {
+        info!("Open registration disabled, registering users requires the admin token");
+        app_state.open_registration = false;
+    }
+    if config.features.preload_catalog {
+        app_state = app_state.with_catalog()?;
+    }
+    // 启动时生成并压缩完整命令列表，首个请求无需等待
+    if let Err(e) = app_state.command_index() {
+        warn!("Cannot build command index at startup: {}", e);
+    }
+    let app_state = web::Data::new(app_state);
+    shutdown::register_database_hooks(&app_state);
+    let shutdown_state = app_state.clone();
 
-    // 获取配置
-    let server_addr = std::env::var("SERVER_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
-    let enable_cors = std::env::var("ENABLE_CORS").unwrap_or_else(|_| "true".to_string()) == "true";
+    let cors_config = config.cors.clone();
 
-    info!("Starting Linux Command Library API server on http://{}", server_addr);
-    info!("CORS enabled: {}", enable_cors);
+    info!("CORS enabled: {} (origins: {:?})", cors_config.enabled, cors_config.origins);
+
+    let http_cache = web::Data::new(http_cache::HttpCache::from_config(&config.cache));
+    let response_cache = web::Data::new(response_cache::ResponseCache::new(config.cache.response_cache_size));
+    let rate_limiter = web::Data::new(rate_limit::RateLimiter::from_config(&config.rate_limit));
+    info!("Rate limiting enabled: {} (trusted proxies: {:?})", config.rate_limit.enabled, config.rate_limit.trusted_proxies);
+
+    let assets = web::Data::new(assets::Assets::new(config.features.assets_dir.as_ref().map(std::path::PathBuf::from)));
+    match &config.features.assets_dir {
+        Some(dir) => info!("Serving frontend assets from {} (development mode)", dir),
+        None => info!("Serving {} embedded frontend assets", assets::Assets::embedded_paths().count()),
+    }
 
     let server = HttpServer::new(move || {
-        let cors = if enable_cors {
-            Cors::default()
-                .allow_any_origin()
-                .allow_any_method()
-                .allow_any_header()
-        } else {
-            Cors::default()
-        };
+        let cors = cors::build(&cors_config);
 
         App::new()
             .app_data(app_state.clone())
-            .wrap(middleware::Logger::default())
+            .app_data(web::JsonConfig::default().error_handler(|err, _req| {
+                AppError::InvalidInput(err.to_string()).into()
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|err, _req| {
+                AppError::InvalidInput(err.to_string()).into()
+            }))
+            .app_data(web::PathConfig::default().error_handler(|err, _req| {
+                AppError::InvalidInput(err.to_string()).into()
+            }))
+            .app_data(http_cache.clone())
+            .app_data(response_cache.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(assets.clone())
+            .wrap(middleware::from_fn(response_cache::middleware))
+            .wrap(middleware::from_fn(http_cache::middleware))
+            .wrap(middleware::Compress::default())
+            .wrap(middleware::from_fn(rate_limit::middleware))
+            .wrap(middleware::from_fn(metrics::middleware))
+            .wrap(middleware::from_fn(logging::middleware))
+            .wrap(middleware::from_fn(request_id::middleware))
             .wrap(cors)
-            // 静态资源
-            .service(Files::new("/stylesheets", "src/stylesheets"))
-            .service(Files::new("/scripts", "src/scripts"))
-            .service(Files::new("/images", "src/images"))
-            // 前端页面
-            .route("/", web::get().to(serve_frontend))
+            // 前端页面与静态资源
+            .configure(assets::configure)
             // 健康检查
             .route("/health", web::get().to(health_check))
-            // 应用统计
-            .route("/api/stats", web::get().to(get_stats))
-            // 分类相关
-            .route("/api/categories", web::get().to(get_categories))
-            .route("/api/categories/detailed", web::get().to(get_categories_detailed))
-            // 搜索相关
-            .route("/api/search", web::get().to(search_commands))
-            .route("/api/suggestions", web::get().to(get_command_suggestions))
-            .route("/api/popular", web::get().to(get_popular_commands))
-            // 命令相关
-            .route("/api/commands", web::get().to(get_all_commands))
-            .route("/api/commands/{id}", web::get().to(get_command))
-            .route("/api/category/{name}", web::get().to(get_commands_by_category))
-            // 提示相关
-            .route("/api/random-tip", web::get().to(get_random_tip))
-    })
-        .bind(&server_addr)?
-        .run();
+            .route("/health/live", web::get().to(health::live))
+            .route("/health/ready", web::get().to(health::ready))
+            // Prometheus 指标
+            .route("/metrics", web::get().to(metrics::export))
+            // OpenAPI 规范与文档页面（需在 /api 作用域之前注册）
+            .configure(openapi::configure)
+            // 版本化 API，/api 保留为兼容别名
+            .service(web::scope("/api/v1").configure(configure_api))
+            .service(web::scope("/api").configure(configure_api))
+    });
+    let server = match config.server.workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    };
+    // 信号由 shutdown::handle_signals 处理
+    let server = server
+        .disable_signals()
+        .shutdown_timeout(config.server.shutdown_timeout);
+
+    // 绑定所有监听地址；证书无效或地址被占用时启动失败
+    let mut server = server;
+    let mut reloaders = Vec::new();
+    for listener in config.server.all_listeners() {
+        let addr = listener::ListenAddr::parse(&listener.addr)?;
+        server = match (&addr, &listener.tls) {
+            (listener::ListenAddr::Tcp(tcp), Some(tls)) => {
+                let reloader = std::sync::Arc::new(listener::CertReloader::load(tls)?);
+                let server = server.bind_rustls_0_23(tcp, reloader.server_config())?;
+                reloaders.push(reloader);
+                info!("Listening on https://{}", tcp);
+                server
+            }
+            (listener::ListenAddr::Tcp(tcp), None) => {
+                info!("Listening on http://{}", tcp);
+                server.bind(tcp)?
+            }
+            #[cfg(unix)]
+            (listener::ListenAddr::Unix(path), _) => {
+                listener::remove_stale_socket(path)?;
+                let server = server.bind_uds(path)?;
+                if let Some(mode) = &listener.socket_mode {
+                    listener::set_socket_mode(path, listener::parse_mode(mode)?)?;
+                }
+                info!("Listening on {}", addr);
+                server
+            }
+            #[cfg(not(unix))]
+            (listener::ListenAddr::Unix(_), _) => unreachable!("rejected by Config::check"),
+        };
+    }
+    listener::reload_on_sighup(reloaders)?;
+    let server = server.run();
+    shutdown::handle_signals(
+        server.handle(),
+        shutdown_state.clone(),
+        std::time::Duration::from_secs(config.server.shutdown_delay),
+    );
 
     info!("Server started successfully");
     server.await?;
+
+    let failed = shutdown_state.shutdown.run_hooks(&shutdown_state);
+    info!("Shutdown complete");
+    if failed > 0 {
+        std::process::exit(1);
+    }
     Ok(())
 }
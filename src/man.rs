@@ -0,0 +1,693 @@
+// 从本机手册页目录导入命令
+//
+// 读取 /usr/share/man 这样的目录树（或其中的 man1 等单个目录）下的 roff 手册页，
+// 支持 gzip 压缩与只包含 `.so` 的重定向页面。解析 man 宏（.SH、.SS、.TP、
+// .IP、.PP、.B、.I、.BR 等）与常用转义，每个 .SH 生成一个 CommandSection，
+// NAME 章节中 `\-` 之后的文字作为命令描述。内容与上游章节一致为带 <b>、<i>
+// 的文本，按 pre-wrap 显示。BSD mdoc 格式（.Dd/.Sh）不支持，与其他无法解析的
+// 页面一起列入报告。结果写入覆盖库并记录修订；替换上游命令时保留其分类与 TLDR。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use rusqlite::{params, OptionalExtension};
+
+use crate::admin::copy_on_write;
+use crate::{revisions, tldr, AppError, AppState, CommandSection, MISCELLANEOUS_CATEGORY};
+
+// 默认导入的手册章节：用户命令与系统管理命令
+pub const DEFAULT_SECTIONS: &str = "1,8";
+
+// 写入修订时的作者
+const AUTHOR: &str = "man-import";
+
+// `.so` 重定向最多跟随的次数
+const MAX_REDIRECTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+}
+
+impl Font {
+    fn tag(self) -> Option<&'static str> {
+        match self {
+            Font::Regular => None,
+            Font::Bold => Some("b"),
+            Font::Italic => Some("i"),
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "B" | "3" | "CB" => Font::Bold,
+            "I" | "2" | "CI" => Font::Italic,
+            _ => Font::Regular,
+        }
+    }
+}
+
+// roff 特殊字符 `\(xx`、`\[xx]` 与字符串 `\*(xx`
+fn glyph(name: &str) -> &'static str {
+    match name {
+        "em" => "—",
+        "en" => "–",
+        "hy" | "mi" => "-",
+        "aq" => "'",
+        "dq" => "\"",
+        "lq" => "“",
+        "rq" => "”",
+        "oq" => "‘",
+        "cq" => "’",
+        "bu" => "•",
+        "co" => "©",
+        "rg" | "R" => "®",
+        "tm" | "Tm" => "™",
+        "mu" => "×",
+        "de" => "°",
+        "ga" => "`",
+        "ha" => "^",
+        "ti" => "~",
+        "rs" => "\\",
+        "sl" => "/",
+        "ba" | "or" => "|",
+        "->" | "ra" => "→",
+        "<-" | "la" => "←",
+        ">=" => "≥",
+        "<=" => "≤",
+        "!=" => "≠",
+        "+-" => "±",
+        _ => "",
+    }
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        c => out.push(c),
+    }
+}
+
+// 读取转义后的名称：`(xx`、`[name]` 或单个字符
+fn escape_name(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    match chars.next() {
+        Some('(') => chars.by_ref().take(2).collect(),
+        Some('[') => chars.by_ref().take_while(|c| *c != ']').collect(),
+        Some(c) => c.to_string(),
+        None => String::new(),
+    }
+}
+
+// 一行文字中的转义与字体切换转换为 HTML；行尾关闭未结束的字体
+fn inline(text: &str, initial: Font) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut font = Font::Regular;
+    let set_font = |out: &mut String, font: &mut Font, next: Font| {
+        if *font == next {
+            return;
+        }
+        if let Some(tag) = font.tag() {
+            out.push_str(&format!("</{}>", tag));
+        }
+        if let Some(tag) = next.tag() {
+            out.push_str(&format!("<{}>", tag));
+        }
+        *font = next;
+    };
+    set_font(&mut out, &mut font, initial);
+    // `\fP` 回到上一个字体
+    let mut previous = initial;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            push_escaped(&mut out, c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => {
+                let name = escape_name(&mut chars);
+                let next = if name == "P" || name.is_empty() { previous } else { Font::from_name(&name) };
+                previous = font;
+                set_font(&mut out, &mut font, next);
+            }
+            Some('(') => {
+                let name: String = chars.by_ref().take(2).collect();
+                out.push_str(glyph(&name));
+            }
+            Some('[') => {
+                let name: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                out.push_str(glyph(&name));
+            }
+            Some('*') => out.push_str(glyph(&escape_name(&mut chars))),
+            Some('n') => {
+                escape_name(&mut chars);
+            }
+            Some('s') => {
+                if matches!(chars.peek(), Some('+' | '-')) {
+                    chars.next();
+                }
+                while chars.peek().is_some_and(char::is_ascii_digit) {
+                    chars.next();
+                }
+            }
+            // 带引号参数的水平/垂直移动等，整体忽略
+            Some('h' | 'v' | 'w' | 'o' | 'X' | 'l' | 'L' | 'D') => {
+                if chars.next_if_eq(&'\'').is_some() {
+                    for c in chars.by_ref() {
+                        if c == '\'' {
+                            break;
+                        }
+                    }
+                }
+            }
+            Some('"') => break,
+            Some('-') => out.push('-'),
+            Some('e' | '\\') => out.push('\\'),
+            Some(' ' | '~' | '0') => out.push(' '),
+            Some('&' | '|' | '^' | '%' | 'c' | ':' | ')' | '{' | '}' | ',' | '/') | None => {}
+            Some(c) => push_escaped(&mut out, c),
+        }
+    }
+    set_font(&mut out, &mut font, Font::Regular);
+    out
+}
+
+// 宏参数：空白分隔，双引号包围的参数可含空格，引号内的 `""` 表示一个引号
+fn macro_args(rest: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = rest.trim().chars().peekable();
+    while chars.peek().is_some() {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else { break };
+        let mut arg = String::new();
+        if first == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                if c == '"' {
+                    if chars.next_if_eq(&'"').is_some() {
+                        arg.push('"');
+                        continue;
+                    }
+                    break;
+                }
+                arg.push(c);
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+        args.push(arg);
+    }
+    args
+}
+
+#[derive(Debug)]
+pub struct ManPage {
+    pub name: String,
+    pub description: String,
+    pub sections: Vec<CommandSection>,
+}
+
+#[derive(Default)]
+struct Parser {
+    sections: Vec<CommandSection>,
+    current: Option<CommandSection>,
+    fill: bool,
+    // .TP 后的下一行是标签
+    tag_next: bool,
+    // 标签之后的正文缩进
+    indent: bool,
+    // 不带参数的 .B/.I 作用于下一行
+    next_font: Option<Font>,
+}
+
+impl Parser {
+    fn text(&mut self) -> Option<&mut String> {
+        self.current.as_mut().map(|section| &mut section.content)
+    }
+
+    fn push(&mut self, html: &str) {
+        let (fill, indent) = (self.fill, self.indent);
+        let Some(text) = self.text() else { return };
+        let at_line_start = text.is_empty() || text.ends_with('\n');
+        if at_line_start && indent {
+            text.push_str("    ");
+        } else if !at_line_start && fill {
+            text.push(' ');
+        }
+        text.push_str(html);
+        if !fill {
+            text.push('\n');
+        }
+    }
+
+    fn break_line(&mut self) {
+        if let Some(text) = self.text() {
+            let trimmed = text.trim_end_matches(' ').len();
+            text.truncate(trimmed);
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+    }
+
+    fn paragraph(&mut self) {
+        self.break_line();
+        if let Some(text) = self.text()
+            && !text.is_empty()
+            && !text.ends_with("\n\n")
+        {
+            text.push('\n');
+        }
+    }
+
+    fn finish_section(&mut self) {
+        if let Some(mut section) = self.current.take() {
+            section.content = section.content.trim_end().to_string();
+            if !section.content.is_empty() {
+                self.sections.push(section);
+            }
+        }
+    }
+
+    fn text_line(&mut self, line: &str) {
+        let font = self.next_font.take().unwrap_or(Font::Regular);
+        self.text_line_html(&inline(line, font));
+    }
+
+    fn request(&mut self, name: &str, rest: &str) {
+        let args = macro_args(rest);
+        match name {
+            "SH" => {
+                self.finish_section();
+                let title = inline(&args.join(" "), Font::Regular);
+                self.current = Some(CommandSection { title: title.trim().to_string(), content: String::new() });
+                self.fill = true;
+                self.indent = false;
+                self.tag_next = false;
+            }
+            "SS" => {
+                self.paragraph();
+                self.indent = false;
+                self.push(&format!("<b>{}</b>", inline(&args.join(" "), Font::Regular)));
+                self.break_line();
+            }
+            "PP" | "LP" | "P" | "HP" => {
+                self.paragraph();
+                self.indent = false;
+            }
+            "TP" | "TQ" => {
+                if name == "TP" {
+                    self.paragraph();
+                } else {
+                    self.break_line();
+                }
+                self.tag_next = true;
+            }
+            "IP" => {
+                self.paragraph();
+                self.indent = false;
+                if let Some(tag) = args.first().filter(|tag| !tag.is_empty()) {
+                    self.push(&inline(tag, Font::Regular));
+                    self.break_line();
+                }
+                self.indent = true;
+            }
+            "RS" => {
+                self.break_line();
+                self.indent = true;
+            }
+            "RE" => {
+                self.break_line();
+                self.indent = false;
+            }
+            "br" => self.break_line(),
+            "sp" => self.paragraph(),
+            "nf" | "EX" => {
+                self.break_line();
+                self.fill = false;
+            }
+            "fi" | "EE" => {
+                self.break_line();
+                self.fill = true;
+            }
+            "B" | "I" | "SB" | "SM" => {
+                let font = match name {
+                    "B" | "SB" => Font::Bold,
+                    "I" => Font::Italic,
+                    _ => Font::Regular,
+                };
+                if args.is_empty() {
+                    self.next_font = Some(font);
+                } else {
+                    self.text_line_html(&inline(&args.join(" "), font));
+                }
+            }
+            "BR" | "BI" | "IB" | "IR" | "RB" | "RI" => {
+                let fonts: Vec<Font> = name.chars().map(|c| Font::from_name(&c.to_string())).collect();
+                let html: String = args.iter().enumerate().map(|(i, arg)| inline(arg, fonts[i % 2])).collect();
+                self.text_line_html(&html);
+            }
+            "UR" | "MT" => {
+                if let Some(url) = args.first() {
+                    self.push(&inline(url, Font::Regular));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 字体宏生成的一行，与普通文字行一样处理 .TP 标签
+    fn text_line_html(&mut self, html: &str) {
+        if self.tag_next {
+            self.tag_next = false;
+            self.indent = false;
+            self.push(html);
+            self.break_line();
+            self.indent = true;
+        } else {
+            self.push(html);
+        }
+    }
+}
+
+// 解析 man 格式的 roff 源文件；name 取自文件名（.TH 的标题通常为大写，重定向页面的标题是目标页面的）
+pub fn parse(source: &str, name: &str) -> Result<ManPage, String> {
+    let mut parser = Parser { fill: true, ..Default::default() };
+    let mut skip_until_dots = false;
+    let mut saw_mdoc = false;
+
+    for line in source.lines() {
+        // 宏定义以 `..` 结束，嵌套在条件块中时也可能写成 `.  .`
+        if skip_until_dots {
+            skip_until_dots = line.strip_prefix('.').is_none_or(|rest| rest.trim() != ".");
+            continue;
+        }
+        let Some(request) = line.strip_prefix('.').or_else(|| line.strip_prefix('\'')) else {
+            if line.trim().is_empty() {
+                if parser.fill {
+                    parser.paragraph();
+                } else {
+                    parser.push("");
+                }
+            } else {
+                parser.text_line(line);
+            }
+            continue;
+        };
+        let request = request.trim_start();
+        if request.starts_with("\\\"") || request.is_empty() {
+            continue;
+        }
+        let (name, rest) = request.split_once(char::is_whitespace).unwrap_or((request, ""));
+        match name {
+            "de" | "de1" | "am" | "ig" => skip_until_dots = true,
+            "Dd" | "Sh" | "Nm" => saw_mdoc = true,
+            _ => parser.request(name, rest),
+        }
+    }
+    parser.finish_section();
+
+    if parser.sections.is_empty() {
+        return Err(if saw_mdoc {
+            "mdoc (BSD) pages are not supported".to_string()
+        } else {
+            "no .SH sections found".to_string()
+        });
+    }
+    let name_section = parser
+        .sections
+        .iter()
+        .find(|section| section.title.eq_ignore_ascii_case("NAME"))
+        .ok_or("no NAME section")?;
+    let summary = crate::tldr::strip_tags(&name_section.content.replace('\n', " "));
+    let description = match summary.split_once(" - ") {
+        Some((_, description)) => description.trim().to_string(),
+        None => summary.trim().to_string(),
+    };
+    if description.is_empty() {
+        return Err("empty NAME section".to_string());
+    }
+    Ok(ManPage { name: name.to_string(), description, sections: parser.sections })
+}
+
+// 读取手册页源文件，gzip 压缩的自动解压
+fn read_source(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let bytes = if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded).map_err(|e| format!("gzip: {}", e))?;
+        decoded
+    } else {
+        bytes
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// `.so man1/foo.1` 形式的重定向，相对于手册根目录（找不到时也尝试带 .gz 的文件）。
+// 目标规范化后必须仍在根目录内，`.so ../../etc/…` 这样的路径被拒绝
+fn resolve_redirects(root: &Path, path: &Path) -> Result<String, String> {
+    let root = root.canonicalize().map_err(|e| format!("{}: {}", root.display(), e))?;
+    let mut source = read_source(path)?;
+    for _ in 0..MAX_REDIRECTS {
+        let Some(target) = source.trim().strip_prefix(".so ").filter(|_| source.trim().lines().count() == 1) else {
+            return Ok(source);
+        };
+        let target = root.join(target.trim());
+        let gz = PathBuf::from(format!("{}.gz", target.display()));
+        let found = match (target.is_file(), gz.is_file()) {
+            (true, _) => target,
+            (false, true) => gz,
+            _ => return Err(format!("redirect target {} not found", target.display())),
+        };
+        let found = found.canonicalize().map_err(|e| format!("{}: {}", found.display(), e))?;
+        if !found.starts_with(&root) {
+            return Err(format!("redirect target {} is outside {}", found.display(), root.display()));
+        }
+        source = read_source(&found)?;
+    }
+    Err("too many .so redirects".to_string())
+}
+
+// 文件名中的命令名与章节，例如 grep.1.gz -> ("grep", "1")
+fn page_name(path: &Path) -> Option<(String, String)> {
+    let file = path.file_name()?.to_str()?;
+    let file = file.strip_suffix(".gz").unwrap_or(file);
+    let (name, section) = file.rsplit_once('.')?;
+    (!name.is_empty() && section.starts_with(|c: char| c.is_ascii_digit())).then(|| (name.to_string(), section.to_string()))
+}
+
+// 待导入的页面：名称 -> 文件；同名页面取章节顺序靠前的目录
+fn collect_pages(root: &Path, sections: &[String]) -> Result<(PathBuf, BTreeMap<String, PathBuf>), Box<dyn std::error::Error>> {
+    let is_section_dir = |path: &Path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("man"))
+            .is_some_and(|section| sections.iter().any(|s| section.starts_with(s.as_str())))
+    };
+    // 传入 man1 这样的单个目录时，手册根目录为其上一级
+    let (root, dirs) = if is_section_dir(root) {
+        (root.parent().unwrap_or(root).to_path_buf(), vec![root.to_path_buf()])
+    } else {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(root)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir() && is_section_dir(path))
+            .collect();
+        dirs.sort_by_key(|dir| {
+            let section = dir.file_name().unwrap_or_default().to_string_lossy().trim_start_matches("man").to_string();
+            (sections.iter().position(|s| section.starts_with(s.as_str())), section)
+        });
+        (root.to_path_buf(), dirs)
+    };
+    if dirs.is_empty() {
+        return Err(format!("no man{{{}}} directories in {}", sections.join(","), root.display()).into());
+    }
+
+    let mut pages = BTreeMap::new();
+    for dir in dirs {
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)?.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        files.sort();
+        for path in files {
+            if let Some((name, _)) = page_name(&path).filter(|_| path.is_file()) {
+                pages.entry(name).or_insert(path);
+            }
+        }
+    }
+    Ok((root, pages))
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub replaced: usize,
+    // 库中已有同名命令而跳过的页面
+    pub skipped: usize,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+// 写入一个命令；已存在时返回 None（未指定 replace）。替换上游命令时先复制到覆盖库，
+// 保留其分类与 TLDR；除 TLDR（来自 tldr-pages 或人工编写）外的章节都来自手册页，整体替换
+fn write_page(data: &AppState, page: &ManPage, replace: bool) -> Result<Option<bool>, AppError> {
+    let base = data.lock_db()?;
+    let overlay = data
+        .lock_overlay()?
+        .ok_or_else(|| AppError::InternalError("Overlay database is not configured".to_string()))?;
+    let base_id: Option<i64> = base
+        .query_row("SELECT id FROM Command WHERE name = ?1 ORDER BY id LIMIT 1", params![&page.name], |row| row.get(0))
+        .optional()?;
+    let existing: Option<i64> = overlay
+        .query_row("SELECT id FROM Command WHERE name = ?1 ORDER BY id LIMIT 1", params![&page.name], |row| row.get(0))
+        .optional()?;
+    let replaced = existing.is_some() || base_id.is_some();
+    if replaced && !replace {
+        return Ok(None);
+    }
+
+    let tx = overlay.unchecked_transaction()?;
+    let id = match (existing, base_id) {
+        (Some(id), _) => id,
+        (None, Some(base_id)) => copy_on_write(&base, &tx, base_id)?,
+        (None, None) => {
+            tx.execute(
+                "INSERT INTO Command (category, name, description) VALUES (?1, ?2, ?3)",
+                params![MISCELLANEOUS_CATEGORY, &page.name, &page.description],
+            )?;
+            tx.last_insert_rowid()
+        }
+    };
+    if replaced {
+        tx.execute("UPDATE Command SET description = ?1 WHERE id = ?2", params![&page.description, id])?;
+        tx.execute("DELETE FROM CommandSection WHERE command_id = ?1 AND title != ?2", params![id, tldr::SECTION])?;
+    }
+    for section in page.sections.iter().filter(|section| section.title != tldr::SECTION) {
+        tx.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![&section.title, &section.content, id],
+        )?;
+    }
+    revisions::record(&tx, id, AUTHOR, if replaced { "update" } else { "import" })?;
+    tx.commit()?;
+    Ok(Some(replaced))
+}
+
+// 导入 root 下 sections 章节的手册页；replace 时覆盖（或遮蔽）已有的同名命令
+pub fn import(data: &AppState, root: &Path, sections: &[String], replace: bool) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+    if data.lock_overlay()?.is_none() {
+        return Err("man import writes to the overlay database; set OVERLAY_DATABASE_PATH".into());
+    }
+    let (root, pages) = collect_pages(root, sections)?;
+    info!("Importing {} man pages from {}", pages.len(), root.display());
+
+    let mut summary = ImportSummary::default();
+    for (name, path) in pages {
+        let page = match resolve_redirects(&root, &path).and_then(|source| parse(&source, &name)) {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("Cannot import man page {}: {}", path.display(), e);
+                summary.failed.push((path, e));
+                continue;
+            }
+        };
+        match write_page(data, &page, replace)? {
+            Some(true) => summary.replaced += 1,
+            Some(false) => summary.imported += 1,
+            None => summary.skipped += 1,
+        }
+    }
+    info!(
+        "man import: {} imported, {} replaced, {} skipped (already present), {} failed",
+        summary.imported,
+        summary.replaced,
+        summary.skipped,
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个测试使用独立的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lcl-man-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("man/man1")).unwrap();
+        dir
+    }
+
+    fn section(page: &ManPage, title: &str) -> String {
+        page.sections.iter().find(|s| s.title == title).unwrap().content.clone()
+    }
+
+    #[test]
+    fn test_font_escapes() {
+        assert_eq!(inline("a \\fBb\\fR c", Font::Regular), "a <b>b</b> c");
+        // \fP 回到上一个字体，而不是起始字体
+        assert_eq!(inline("\\fBb\\fIi\\fPb\\fRr", Font::Regular), "<b>b</b><i>i</i><b>b</b>r");
+        assert_eq!(inline("\\fBb\\fIi\\fPb\\fPi", Font::Regular), "<b>b</b><i>i</i><b>b</b><i>i</i>");
+        assert_eq!(inline("x\\f(CBy\\f[]z", Font::Italic), "<i>x</i><b>y</b><i>z</i>");
+        // 行尾关闭未结束的字体，并转义 HTML
+        assert_eq!(inline("\\fI<file>", Font::Regular), "<i>&lt;file&gt;</i>");
+    }
+
+    #[test]
+    fn test_tagged_and_indented_paragraphs() {
+        let page = parse(
+            ".TH T 1\n.SH NAME\nt \\- test\n.SH OPTIONS\n.TP\n.B \\-a\nAll\nfiles.\n.TP\n\\fB\\-b\\fR \\fIn\\fR\nBlock.\n\
+             .IP \\(bu 2\nFirst.\n.IP\nMore.\n.PP\nDone.\n",
+            "t",
+        )
+        .unwrap();
+        assert_eq!(page.description, "test");
+        assert_eq!(
+            section(&page, "OPTIONS"),
+            "<b>-a</b>\n    All files.\n\n<b>-b</b> <i>n</i>\n    Block.\n\n•\n    First.\n\n    More.\n\nDone."
+        );
+    }
+
+    #[test]
+    fn test_gzip_source() {
+        use std::io::Write;
+
+        let dir = temp_dir("gz");
+        let path = dir.join("man/man1/z.1.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b".TH Z 1\n.SH NAME\nz \\- zipped\n").unwrap();
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+        fs::write(dir.join("man/man1/bad.1.gz"), b"not gzip").unwrap();
+
+        assert_eq!(page_name(&path), Some(("z".to_string(), "1".to_string())));
+        assert_eq!(parse(&read_source(&path).unwrap(), "z").unwrap().description, "zipped");
+        assert!(read_source(&dir.join("man/man1/bad.1.gz")).unwrap_err().starts_with("gzip:"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_redirects_stay_inside_root() {
+        let dir = temp_dir("so");
+        let root = dir.join("man");
+        fs::write(dir.join("secret.1"), ".TH SECRET 1\n.SH NAME\nsecret \\- outside\n").unwrap();
+        fs::write(root.join("man1/real.1"), ".TH REAL 1\n.SH NAME\nreal \\- inside\n").unwrap();
+        fs::write(root.join("man1/alias.1"), ".so man1/real.1\n").unwrap();
+        fs::write(root.join("man1/evil.1"), ".so ../secret.1\n").unwrap();
+        fs::write(root.join("man1/sneaky.1"), ".so man1/../../secret.1\n").unwrap();
+
+        assert!(resolve_redirects(&root, &root.join("man1/alias.1")).unwrap().contains("real \\- inside"));
+        for page in ["evil.1", "sneaky.1"] {
+            let err = resolve_redirects(&root, &root.join("man1").join(page)).unwrap_err();
+            assert!(err.contains("is outside"), "{}", err);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
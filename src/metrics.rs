@@ -0,0 +1,164 @@
+// Prometheus 指标
+//
+// /metrics 以 Prometheus 文本格式输出。请求计数与延迟按路由模板（而不是实际路径）
+// 和状态码分组，避免 id 等参数导致标签基数膨胀。数据库锁等待时间在
+// AppState::lock_db / lock_overlay 中记录，查询耗时来自 SQLite 的 profile 回调。
+// AppStats 中的数量在每次抓取时更新。
+
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse};
+use log::warn;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::{compute_stats, AppError, AppState};
+
+// 数据库操作在毫秒以下，延迟桶比默认值更细
+const DB_BUCKETS: &[f64] = &[0.0001, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    db_lock_wait: HistogramVec,
+    db_query_duration: HistogramVec,
+    pub cache_hits: IntCounter,
+    pub cache_misses: IntCounter,
+    pub search_zero_results: IntCounter,
+    pub rate_limited: IntCounterVec,
+    catalog_size: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route, method and status"),
+            &["route", "method", "status"],
+        )?;
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route, method and status"),
+            &["route", "method", "status"],
+        )?;
+        let db_lock_wait = HistogramVec::new(
+            HistogramOpts::new("db_lock_wait_seconds", "Time spent waiting for a database connection lock")
+                .buckets(DB_BUCKETS.to_vec()),
+            &["database"],
+        )?;
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "SQLite statement execution time by statement kind")
+                .buckets(DB_BUCKETS.to_vec()),
+            &["kind"],
+        )?;
+        let cache_hits = IntCounter::new("response_cache_hits_total", "Responses served from the in-process cache")?;
+        let cache_misses = IntCounter::new("response_cache_misses_total", "Cacheable requests not found in the in-process cache")?;
+        let search_zero_results = IntCounter::new("search_zero_results_total", "Searches that returned no commands")?;
+        let rate_limited = IntCounterVec::new(
+            Opts::new("rate_limited_requests_total", "Requests rejected by the rate limiter by route group"),
+            &["group"],
+        )?;
+        let catalog_size = IntGaugeVec::new(
+            Opts::new("catalog_items", "Number of commands, tips and categories served"),
+            &["kind"],
+        )?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_duration.clone()))?;
+        registry.register(Box::new(db_lock_wait.clone()))?;
+        registry.register(Box::new(db_query_duration.clone()))?;
+        registry.register(Box::new(cache_hits.clone()))?;
+        registry.register(Box::new(cache_misses.clone()))?;
+        registry.register(Box::new(search_zero_results.clone()))?;
+        registry.register(Box::new(rate_limited.clone()))?;
+        registry.register(Box::new(catalog_size.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_duration,
+            db_lock_wait,
+            db_query_duration,
+            cache_hits,
+            cache_misses,
+            search_zero_results,
+            rate_limited,
+            catalog_size,
+        })
+    }
+
+    pub fn observe_lock_wait(&self, database: &str, waited: Duration) {
+        self.db_lock_wait.with_label_values(&[database]).observe(waited.as_secs_f64());
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| Metrics::new().expect("metric definitions are valid"));
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+// SQLite profile 回调：按语句类型记录耗时
+pub fn profile_query(sql: &str, elapsed: Duration) {
+    let kind = match sql.split_whitespace().next().map(str::to_ascii_uppercase).as_deref() {
+        Some("SELECT" | "WITH") => "select",
+        Some("INSERT" | "REPLACE") => "insert",
+        Some("UPDATE") => "update",
+        Some("DELETE") => "delete",
+        _ => "other",
+    };
+    metrics().db_query_duration.with_label_values(&[kind]).observe(elapsed.as_secs_f64());
+}
+
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+
+    let res = next.call(req).await?;
+
+    // 未匹配任何路由的请求（例如扫描器）归为同一标签
+    let route = res.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let status = res.status().as_u16().to_string();
+    let labels = [route.as_str(), method.as_str(), status.as_str()];
+    let metrics = metrics();
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics.http_duration.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+
+    Ok(res)
+}
+
+// GET /metrics
+pub async fn export(data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let metrics = metrics();
+
+    let stats = match data.catalog() {
+        Some(catalog) => Ok(catalog.stats().clone()),
+        None => compute_stats(&data),
+    };
+    match stats {
+        Ok(stats) => {
+            metrics.catalog_size.with_label_values(&["commands"]).set(stats.total_commands);
+            metrics.catalog_size.with_label_values(&["tips"]).set(stats.total_tips);
+            metrics.catalog_size.with_label_values(&["categories"]).set(stats.total_basic_categories);
+        }
+        // 数据库不可用时仍然输出其余指标
+        Err(e) => warn!("Cannot refresh catalog gauges: {}", e),
+    }
+
+    let mut body = Vec::new();
+    let encoder = TextEncoder::new();
+    encoder
+        .encode(&metrics.registry.gather(), &mut body)
+        .map_err(|e| AppError::InternalError(format!("Failed to encode metrics: {}", e)))?;
+
+    Ok(HttpResponse::Ok().content_type(encoder.format_type()).body(body))
+}
@@ -0,0 +1,400 @@
+// 私人命令笔记
+//
+// 登录用户可以为任意命令添加只有自己可见的笔记（例如内部主机上的特殊参数）。
+// 笔记按命令名称关联，因此上游命令被覆盖库遮蔽后笔记依然有效。
+// 所有者请求命令详情时笔记会内联返回，也可以通过 /api/search?mine=true 搜索。
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::admin::{bearer_token, require_text};
+use crate::overlay;
+use crate::users::{lock_store, require_user, User};
+use crate::{find_command_by_name, resolve_command_detail, search_relevance, ApiResponse, AppError, AppState, Command, ErrorResponse};
+
+const MAX_NOTE_LEN: usize = 8192;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Note {
+    id: i64,
+    command: String,
+    content: String,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NoteInput {
+    // 命令 id 或名称二选一
+    command_id: Option<i64>,
+    command: Option<String>,
+    content: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NoteUpdate {
+    content: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NoteFilter {
+    /// Command name or id
+    command: Option<String>,
+}
+
+pub fn ensure_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS Note (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            command_name TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_note_user_command ON Note (user_id, command_name);",
+    )
+}
+
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        command: row.get(1)?,
+        content: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+const NOTE_COLUMNS: &str = "id, command_name, content, created_at, updated_at";
+
+fn user_notes(conn: &Connection, user: &User, command_name: Option<&str>) -> Result<Vec<Note>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM Note WHERE user_id = ?1 AND (?2 IS NULL OR command_name = ?2) ORDER BY id",
+        NOTE_COLUMNS
+    ))?;
+    let notes = stmt
+        .query_map(params![user.id, command_name], note_from_row)?
+        .collect::<Result<_, _>>()?;
+    Ok(notes)
+}
+
+// 读取当前用户拥有的笔记；不区分“不存在”和“无权限”
+fn owned_note(conn: &Connection, user: &User, note_id: i64) -> Result<Note, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM Note WHERE id = ?1 AND user_id = ?2", NOTE_COLUMNS),
+        params![note_id, user.id],
+        note_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Note {} not found", note_id)))
+}
+
+// 将命令 id 或名称解析为命令名称
+fn resolve_command_name(data: &AppState, key: &str) -> Result<String, AppError> {
+    let command = match key.parse::<i64>() {
+        Ok(id) => resolve_command_detail(data, id)?.map(|detail| detail.name),
+        Err(_) => find_command_by_name(data, key)?.map(|cmd| cmd.name),
+    };
+    command.ok_or(AppError::CommandNotFound)
+}
+
+// 命令详情中内联的笔记：只对携带有效用户 token 的请求返回，其余情况静默为空
+pub fn notes_for_request(req: &HttpRequest, data: &AppState, command_name: &str) -> Result<Vec<Note>, AppError> {
+    if bearer_token(req).is_none() {
+        return Ok(vec![]);
+    }
+    let conn = match data.lock_overlay()? {
+        Some(conn) if overlay::table_exists(&conn, "Note")? => conn,
+        _ => return Ok(vec![]),
+    };
+    match require_user(req, &conn) {
+        Ok(user) => user_notes(&conn, &user, Some(command_name)),
+        Err(AppError::Unauthorized) => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+// 与 search_in 相同的相关性规则，额外允许匹配笔记内容
+fn relevance(cmd: &Command, notes: &[String], term: &str) -> i64 {
+    match search_relevance(&cmd.name, &cmd.description, term) {
+        0 if notes.iter().any(|n| n.to_lowercase().contains(&term.to_lowercase())) => 5,
+        relevance => relevance,
+    }
+}
+
+// /api/search?mine=true：只在当前用户做过笔记的命令中搜索
+pub fn search_mine(
+    req: &HttpRequest,
+    data: &AppState,
+    term: &str,
+    category: Option<&String>,
+    limit: i64,
+) -> Result<Vec<Command>, AppError> {
+    let mut by_command: Vec<(String, Vec<String>)> = Vec::new();
+    {
+        let conn = lock_store(data)?;
+        let user = require_user(req, &conn)?;
+        for note in user_notes(&conn, &user, None)? {
+            match by_command.iter_mut().find(|(name, _)| *name == note.command) {
+                Some((_, contents)) => contents.push(note.content),
+                None => by_command.push((note.command, vec![note.content])),
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for (name, contents) in by_command {
+        // 命令已被删除时跳过
+        let cmd = match find_command_by_name(data, &name)? {
+            Some(cmd) => cmd,
+            None => continue,
+        };
+        if category.is_some_and(|c| c.parse::<i64>().ok() != Some(cmd.category)) {
+            continue;
+        }
+        let score = relevance(&cmd, &contents, term);
+        if score > 0 {
+            results.push((cmd, score));
+        }
+    }
+
+    results.sort_by(|(a, ra), (b, rb)| rb.cmp(ra).then_with(|| a.name.cmp(&b.name)));
+    results.truncate(limit.max(0) as usize);
+    Ok(results.into_iter().map(|(cmd, _)| cmd).collect())
+}
+
+// 列出当前用户的笔记，可按命令过滤
+#[utoipa::path(
+    get,
+    path = "/api/v1/notes",
+    tag = "notes",
+    params(NoteFilter),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The caller's notes, optionally limited to one command", body = ApiResponse<Vec<Note>>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_notes(
+    req: HttpRequest,
+    query: web::Query<NoteFilter>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let command_name = match &query.command {
+        Some(key) => Some(resolve_command_name(&data, key.trim())?),
+        None => None,
+    };
+
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    let notes = user_notes(&conn, &user, command_name.as_deref())?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(notes),
+        message: None,
+    }))
+}
+
+// 某个命令上的笔记
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands/{id}/notes",
+    tag = "notes",
+    params(("id" = i64, Path, description = "Command id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The caller's notes on this command", body = ApiResponse<Vec<Note>>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_command_notes(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let command = resolve_command_detail(&data, *path)?.ok_or(AppError::CommandNotFound)?;
+
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    let notes = user_notes(&conn, &user, Some(&command.name))?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(notes),
+        message: None,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/notes",
+    tag = "notes",
+    request_body = NoteInput,
+    security(("user_token" = [])),
+    responses(
+        (status = 201, description = "The new note", body = ApiResponse<Note>),
+        (status = 400, description = "Empty or too long content, or not exactly one of `command_id` and `command` (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn create_note(
+    req: HttpRequest,
+    body: web::Json<NoteInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_text("Note content", &body.content, MAX_NOTE_LEN)?;
+    let command_name = match (body.command_id, body.command.as_deref()) {
+        (Some(id), None) => resolve_command_name(&data, &id.to_string())?,
+        (None, Some(name)) => {
+            let name = name.trim();
+            find_command_by_name(&data, name)?.ok_or(AppError::CommandNotFound)?.name
+        }
+        _ => {
+            return Err(AppError::InvalidInput(
+                "Exactly one of 'command_id' or 'command' is required".to_string(),
+            ))
+        }
+    };
+
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    conn.execute(
+        "INSERT INTO Note (user_id, command_name, content) VALUES (?1, ?2, ?3)",
+        params![user.id, &command_name, body.content.trim()],
+    )?;
+    let note = owned_note(&conn, &user, conn.last_insert_rowid())?;
+
+    info!("User {} added note {} to command '{}'", user.id, note.id, command_name);
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(note),
+        message: None,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/notes/{id}",
+    tag = "notes",
+    params(("id" = i64, Path, description = "Note id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The note", body = ApiResponse<Note>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Note not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn get_note(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    let note = owned_note(&conn, &user, *path)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(note),
+        message: None,
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/notes/{id}",
+    tag = "notes",
+    params(("id" = i64, Path, description = "Note id")),
+    request_body = NoteUpdate,
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The updated note", body = ApiResponse<Note>),
+        (status = 400, description = "Empty or too long content (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Note not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn update_note(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<NoteUpdate>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_text("Note content", &body.content, MAX_NOTE_LEN)?;
+
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    owned_note(&conn, &user, *path)?;
+    conn.execute(
+        "UPDATE Note SET content = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now') WHERE id = ?2",
+        params![body.content.trim(), *path],
+    )?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(owned_note(&conn, &user, *path)?),
+        message: None,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/notes/{id}",
+    tag = "notes",
+    params(("id" = i64, Path, description = "Note id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Id of the deleted note", body = ApiResponse<i64>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Note not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn delete_note(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    let note = owned_note(&conn, &user, *path)?;
+    conn.execute("DELETE FROM Note WHERE id = ?1", params![note.id])?;
+
+    info!("User {} deleted note {}", user.id, note.id);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(note.id),
+        message: None,
+    }))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/notes", web::get().to(list_notes))
+        .route("/notes", web::post().to(create_note))
+        .route("/notes/{id}", web::get().to(get_note))
+        .route("/notes/{id}", web::put().to(update_note))
+        .route("/notes/{id}", web::delete().to(delete_note))
+        .route("/commands/{id}/notes", web::get().to(list_command_notes));
+}
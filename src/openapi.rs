@@ -0,0 +1,167 @@
+// OpenAPI 3 文档
+//
+// 规范由处理函数上的 #[utoipa::path] 注解和模型上的 ToSchema 派生生成，
+// 因此字段（例如可省略的 tldr、以字符串输出的 category）与实际序列化保持一致。
+// 用户接口使用 user_token（POST /api/users 获得），管理接口使用 admin_token（ADMIN_TOKEN）。
+// JSON 位于 /api/openapi.json，内置的 Swagger UI 位于 /api/docs/。
+
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::admin::{BasicCommandInput, CategoryInput, CommandInput, GroupInput, SectionInput, TipInput};
+use crate::health::{DatabaseCheck, Readiness};
+use crate::notes::{Note, NoteInput, NoteUpdate};
+use crate::overlay::Source;
+use crate::revisions::{CommandSnapshot, Revision, RevisionDiff};
+use crate::suggestions::{CommandExample, ReviewInput, Suggestion, SuggestionInput};
+use crate::users::{Collection, CollectionInput, CollectionItem, Favorite, ItemInput, ItemKind, RegisterInput, Registration, User};
+use crate::{
+    AppStats, BasicCategory, BasicCommand, BasicGroup, Command, CommandDetail, CommandSection, ErrorResponse, Tip,
+    TipSection,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Linux Command Library API", description = "Commands, categories and tips, with user accounts, notes, collections and the admin write API"),
+    paths(
+        crate::health_check,
+        crate::health::live,
+        crate::health::ready,
+        crate::get_stats,
+        crate::get_categories,
+        crate::get_categories_detailed,
+        crate::search_commands,
+        crate::get_command_suggestions,
+        crate::get_popular_commands,
+        crate::get_all_commands,
+        crate::get_command,
+        crate::get_commands_by_category,
+        crate::get_random_tip,
+        crate::export::export,
+        crate::revisions::list_revisions,
+        crate::revisions::get_revision,
+        crate::revisions::diff_revisions,
+        crate::suggestions::submit_suggestion,
+        crate::users::register,
+        crate::users::me,
+        crate::users::list_favorites,
+        crate::users::add_favorite,
+        crate::users::remove_favorite,
+        crate::users::list_collections,
+        crate::users::create_collection,
+        crate::users::get_collection,
+        crate::users::update_collection,
+        crate::users::delete_collection,
+        crate::users::add_item,
+        crate::users::remove_item,
+        crate::users::export_collection,
+        crate::users::get_shared_collection,
+        crate::users::export_shared_collection,
+        crate::notes::list_notes,
+        crate::notes::list_command_notes,
+        crate::notes::create_note,
+        crate::notes::get_note,
+        crate::notes::update_note,
+        crate::notes::delete_note,
+        crate::admin::create_command,
+        crate::admin::update_command,
+        crate::admin::delete_command,
+        crate::admin::put_section,
+        crate::admin::delete_section,
+        crate::revisions::revert_revision,
+        crate::suggestions::list_suggestions,
+        crate::suggestions::approve_suggestion,
+        crate::suggestions::reject_suggestion,
+        crate::admin::create_category,
+        crate::admin::update_category,
+        crate::admin::delete_category,
+        crate::admin::create_group,
+        crate::admin::update_group,
+        crate::admin::delete_group,
+        crate::admin::create_basic_command,
+        crate::admin::update_basic_command,
+        crate::admin::delete_basic_command,
+        crate::admin::create_tip,
+        crate::admin::update_tip,
+        crate::admin::delete_tip,
+        crate::catalog::reload,
+    ),
+    components(schemas(
+        AppStats,
+        BasicCategory,
+        BasicCommand,
+        BasicCommandInput,
+        BasicGroup,
+        CategoryInput,
+        Collection,
+        CollectionInput,
+        CollectionItem,
+        Command,
+        CommandDetail,
+        CommandExample,
+        CommandInput,
+        CommandSection,
+        CommandSnapshot,
+        DatabaseCheck,
+        ErrorResponse,
+        Favorite,
+        GroupInput,
+        ItemInput,
+        ItemKind,
+        Note,
+        NoteInput,
+        NoteUpdate,
+        Readiness,
+        RegisterInput,
+        Registration,
+        ReviewInput,
+        Revision,
+        RevisionDiff,
+        SectionInput,
+        Source,
+        Suggestion,
+        SuggestionInput,
+        Tip,
+        TipInput,
+        TipSection,
+        User,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "commands", description = "Command listing and details"),
+        (name = "categories", description = "Categories and their one-liners"),
+        (name = "search", description = "Search, autocomplete and popular commands"),
+        (name = "tips", description = "Tips"),
+        (name = "stats", description = "Application statistics"),
+        (name = "export", description = "Bulk export of the whole library"),
+        (name = "health", description = "Liveness and readiness checks"),
+        (name = "revisions", description = "Revision history of overlay commands"),
+        (name = "suggestions", description = "Example suggestions and their review queue"),
+        (name = "users", description = "User registration"),
+        (name = "favorites", description = "The caller's favorite commands"),
+        (name = "collections", description = "Named command collections and their read-only share links"),
+        (name = "notes", description = "Private notes on commands"),
+        (name = "admin", description = "Writes to the overlay database; requires the admin token"),
+    )
+)]
+pub struct ApiDoc;
+
+// 用户 token 与管理员 token 都以 bearer 方式传递
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        for name in ["user_token", "admin_token"] {
+            components.add_security_scheme(
+                name,
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+pub fn configure(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", ApiDoc::openapi()));
+}
@@ -0,0 +1,140 @@
+// 覆盖数据库（overlay）- 团队内部命令与上游 database.db 合并
+//
+// 覆盖库使用与上游相同的 Command / CommandSection / BasicCategory 表结构。
+// 覆盖库中的条目在 API 中带有 source = "overlay"，其 id 统一加上
+// OVERLAY_ID_OFFSET，避免与上游 id 冲突；同名条目会遮蔽上游条目。
+
+use std::collections::HashSet;
+
+use rusqlite::{Connection, Error as SqliteError};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{BasicCategory, Command, CommandDetail};
+
+/// 覆盖库 id 在 API 中的偏移量
+pub const OVERLAY_ID_OFFSET: i64 = 1_000_000_000;
+
+// 数据来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    #[default]
+    Base,
+    Overlay,
+}
+
+// 将 API 中的 id 解析为 (来源, 数据库内 id)
+pub fn resolve_id(public_id: i64) -> (Source, i64) {
+    if public_id >= OVERLAY_ID_OFFSET {
+        (Source::Overlay, public_id - OVERLAY_ID_OFFSET)
+    } else {
+        (Source::Base, public_id)
+    }
+}
+
+pub fn table_exists(conn: &Connection, table: &str) -> Result<bool, SqliteError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+// 标记覆盖库中的命令：设置来源并偏移 id
+pub fn mark_command(mut cmd: Command) -> Command {
+    cmd.id += OVERLAY_ID_OFFSET;
+    cmd.source = Source::Overlay;
+    cmd
+}
+
+pub fn mark_detail(mut detail: CommandDetail) -> CommandDetail {
+    detail.id += OVERLAY_ID_OFFSET;
+    detail.source = Source::Overlay;
+    detail
+}
+
+// 合并命令列表，`shadowed` 中的上游同名命令被覆盖库遮蔽
+pub fn merge_commands(base: Vec<Command>, overlay: Vec<Command>, shadowed: &HashSet<String>) -> Vec<Command> {
+    base.into_iter()
+        .filter(|c| !shadowed.contains(&c.name))
+        .chain(overlay.into_iter().map(mark_command))
+        .collect()
+}
+
+// 合并分类列表，覆盖库中的同名分类遮蔽上游分类，结果按 position 排序
+pub fn merge_categories(base: Vec<BasicCategory>, overlay: Vec<BasicCategory>) -> Vec<BasicCategory> {
+    let shadowed: HashSet<String> = overlay.iter().map(|c| c.title.clone()).collect();
+
+    let mut merged: Vec<BasicCategory> = base
+        .into_iter()
+        .filter(|c| !shadowed.contains(&c.title))
+        .chain(overlay.into_iter().map(|mut c| {
+            c.id += OVERLAY_ID_OFFSET;
+            c.source = Source::Overlay;
+            c
+        }))
+        .collect();
+
+    merged.sort_by_key(|c| c.position);
+    merged
+}
+
+// 创建可写覆盖库所需的表（与上游 schema 一致）
+pub fn ensure_schema(conn: &Connection) -> Result<(), SqliteError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS Command (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            category INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS CommandSection (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            command_id INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_command_name ON Command (name);
+        CREATE INDEX IF NOT EXISTS idx_command_section_command ON CommandSection (command_id);
+        CREATE TABLE IF NOT EXISTS BasicCategory (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            position INTEGER NOT NULL,
+            title TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS BasicGroup (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            position INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            category_id INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS BasicCommand (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command TEXT NOT NULL,
+            mans TEXT NOT NULL,
+            group_id INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS Tip (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            position INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS TipSection (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tip_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            type INTEGER NOT NULL,
+            data1 TEXT NOT NULL,
+            data2 TEXT NOT NULL,
+            extra TEXT NOT NULL
+        );
+        -- 复制到覆盖库的上游分组与一行命令：上游 id -> 覆盖库 id（kind 为表名）
+        CREATE TABLE IF NOT EXISTS UpstreamCopy (
+            kind TEXT NOT NULL,
+            base_id INTEGER NOT NULL,
+            overlay_id INTEGER NOT NULL,
+            PRIMARY KEY (kind, base_id)
+        );",
+    )
+}
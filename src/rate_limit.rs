@@ -0,0 +1,373 @@
+// 按客户端 IP 的令牌桶限流
+//
+// API 路由分为几组（search、commands、registration、submissions、export，以及其余的 api），每组有独立的速率
+// （每分钟补充的令牌数）和突发容量。令牌耗尽时返回 429，Retry-After 为
+// 下一个令牌可用前的秒数。只有来自受信任代理的请求才读取 X-Forwarded-For：
+// 从右向左跳过受信任的地址，取第一个不受信任的地址作为客户端 IP。
+// 桶的数量有上限，超出时淘汰最久未使用的桶；已回满的桶定期清理。
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ResourceDef, ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, ResponseError};
+use log::debug;
+
+use crate::config::{GroupLimit, RateLimitConfig};
+use crate::{metrics, AppError};
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+// 超过该数量的桶时清理已回满的桶，每个间隔最多清理一次
+const SWEEP_THRESHOLD: usize = 10_000;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// 桶数量的硬上限，超出时淘汰最久未使用的桶
+const MAX_BUCKETS: usize = 100_000;
+
+// 路由分组（相对于 /api/v1 或 /api），未列出的 API 路由属于 "api" 组；
+// registration 只包含注册用户（POST /users），限制匿名创建账号；
+// submissions 只包含匿名也可提交的示例建议；export 每次都会序列化整个命令库，限制最严
+const GROUPS: &[(&str, &str)] = &[
+    ("registration", "/users"),
+    ("submissions", "/commands/{id}/examples/suggestions"),
+    ("export", "/export"),
+    ("search", "/search"),
+    ("search", "/suggestions"),
+    ("commands", "/commands"),
+    ("commands", "/commands/{id}"),
+    ("commands", "/category/{name}"),
+];
+
+const PREFIXES: &[&str] = &["/api/v1", "/api"];
+
+static GROUP_DEFS: LazyLock<Vec<(&'static str, ResourceDef)>> =
+    LazyLock::new(|| GROUPS.iter().map(|(name, path)| (*name, ResourceDef::new(*path))).collect());
+
+pub fn group_for(path: &str) -> Option<&'static str> {
+    let relative = PREFIXES.iter().find_map(|prefix| path.strip_prefix(prefix))?;
+    if !(relative.is_empty() || relative.starts_with('/')) {
+        return None;
+    }
+    GROUP_DEFS
+        .iter()
+        .find(|(_, def)| def.is_match(relative))
+        .map(|(name, _)| *name)
+        .or(Some("api"))
+}
+
+// 受信任代理：单个地址或 CIDR 网段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| format!("invalid IP address {:?}", value))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("invalid prefix length in {:?}", value))?,
+            None => max,
+        };
+        Ok(IpNet { addr: addr.to_canonical(), prefix })
+    }
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// 客户端 IP；直连地址不受信任时忽略 X-Forwarded-For，防止伪造。
+// Unix 套接字连接没有对端地址，只能来自本机进程（通常是反向代理），直接读取该头
+pub fn client_ip(peer: Option<IpAddr>, forwarded_for: Option<&str>, trusted: &[IpNet]) -> Option<IpAddr> {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|net| net.contains(ip));
+    if peer.is_some_and(|peer| !is_trusted(peer)) {
+        return peer;
+    }
+
+    let mut client = peer;
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        // 无法解析的条目之前的地址都不可信，停在最后一个可信的位置
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else { break };
+        client = Some(ip);
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    client
+}
+
+type BucketKey = (&'static str, IpAddr);
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    used: u64,
+}
+
+#[derive(Default)]
+struct Buckets {
+    entries: HashMap<BucketKey, Bucket>,
+    // 最近使用序号 -> key，最小者最先淘汰
+    recency: BTreeMap<u64, BucketKey>,
+    tick: u64,
+    last_sweep: Option<Instant>,
+}
+
+impl Buckets {
+    // 清理已回满的桶（与新建的桶等价）
+    fn sweep(&mut self, limits: &HashMap<&'static str, Limit>, now: Instant) {
+        if self.entries.len() < SWEEP_THRESHOLD
+            || self.last_sweep.is_some_and(|at| now.saturating_duration_since(at) < SWEEP_INTERVAL)
+        {
+            return;
+        }
+        self.last_sweep = Some(now);
+        self.entries.retain(|(group, _), bucket| limits.get(group).is_some_and(|l| l.refill(bucket, now) < l.burst));
+        let entries = &self.entries;
+        self.recency.retain(|_, key| entries.contains_key(key));
+    }
+
+    // 取出桶并标记为最近使用；新建桶前淘汰最久未使用的桶
+    fn touch(&mut self, key: BucketKey, burst: f64, now: Instant) -> &mut Bucket {
+        self.tick += 1;
+        let used = self.tick;
+        match self.entries.get_mut(&key) {
+            Some(bucket) => {
+                self.recency.remove(&bucket.used);
+                bucket.used = used;
+            }
+            None => {
+                while self.entries.len() >= MAX_BUCKETS {
+                    match self.recency.pop_first() {
+                        Some((_, evicted)) => {
+                            self.entries.remove(&evicted);
+                        }
+                        None => break,
+                    }
+                }
+                self.entries.insert(key, Bucket { tokens: burst, updated: now, used });
+            }
+        }
+        self.recency.insert(used, key);
+        self.entries.get_mut(&key).expect("bucket was just inserted")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    // 每秒补充的令牌数
+    rate: f64,
+    burst: f64,
+}
+
+impl Limit {
+    fn new(limit: &GroupLimit) -> Option<Self> {
+        (limit.per_minute > 0).then(|| Limit {
+            rate: limit.per_minute as f64 / 60.0,
+            burst: limit.burst.max(1) as f64,
+        })
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+pub struct RateLimiter {
+    limits: HashMap<&'static str, Limit>,
+    trusted_proxies: Vec<IpNet>,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    // 配置已由 Config::check 校验，这里忽略无法解析的代理地址
+    pub fn from_config(config: &RateLimitConfig) -> Self {
+        let limits = if config.enabled {
+            config.groups().iter().filter_map(|(name, limit)| Some((*name, Limit::new(limit)?))).collect()
+        } else {
+            HashMap::new()
+        };
+        Self {
+            limits,
+            trusted_proxies: config.trusted_proxies.iter().filter_map(|p| p.parse().ok()).collect(),
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    // 消耗一个令牌；被限流时返回需要等待的秒数
+    pub fn check(&self, group: &'static str, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        let Some(limit) = self.limits.get(group) else { return Ok(()) };
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        buckets.sweep(&self.limits, now);
+
+        let bucket = buckets.touch((group, ip), limit.burst, now);
+        bucket.tokens = limit.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / limit.rate).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let group = group_for(req.path());
+    let peer = req.peer_addr().map(|addr| addr.ip());
+    let forwarded_for = req.headers().get(FORWARDED_FOR).and_then(|v| v.to_str().ok());
+    let ip = limiter.as_ref().and_then(|limiter| client_ip(peer, forwarded_for, &limiter.trusted_proxies));
+
+    let (limiter, group, ip) = match (limiter, group, ip) {
+        (Some(limiter), Some(group), Some(ip)) if req.method() != Method::OPTIONS => (limiter, group, ip),
+        _ => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    if let Err(retry_after) = limiter.check(group, ip, Instant::now()) {
+        debug!("Rate limited {} on {} for {}s", ip, group, retry_after);
+        metrics::metrics().rate_limited.with_label_values(&[group]).inc();
+        let res = AppError::RateLimited(retry_after).error_response();
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::from_config(&RateLimitConfig::default())
+    }
+
+    fn ipv6(n: usize) -> IpAddr {
+        IpAddr::V6(Ipv6Addr::from(0x2001_0db8_u128 << 96 | n as u128))
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted: Vec<IpNet> = ["10.0.0.0/8", "::1"].iter().map(|p| p.parse().unwrap()).collect();
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(client_ip(Some(ip("10.0.0.1")), Some("1.2.3.4, 10.9.9.9"), &trusted), Some(ip("1.2.3.4")));
+        assert_eq!(client_ip(Some(ip("::1")), Some("garbage, 10.9.9.9"), &trusted), Some(ip("10.9.9.9")));
+        assert_eq!(client_ip(Some(ip("::ffff:10.0.0.1")), None, &trusted), Some(ip("::ffff:10.0.0.1")));
+        assert_eq!(client_ip(Some(ip("1.2.3.4")), Some("10.0.0.1"), &trusted), Some(ip("1.2.3.4")));
+        // Unix 套接字连接
+        assert_eq!(client_ip(None, Some("1.2.3.4"), &[]), Some(ip("1.2.3.4")));
+        assert_eq!(client_ip(None, None, &trusted), None);
+    }
+
+    #[test]
+    fn test_group_for() {
+        assert_eq!(group_for("/api/commands/42"), Some("commands"));
+        assert_eq!(group_for("/api/v1/suggestions"), Some("search"));
+        assert_eq!(group_for("/apidocs"), None);
+        assert_eq!(group_for("/api/v1/users"), Some("registration"));
+        assert_eq!(group_for("/api/v1/users/me"), Some("api"));
+        assert_eq!(group_for("/api/v1/export"), Some("export"));
+        assert_eq!(group_for("/api/export"), Some("export"));
+    }
+
+    #[test]
+    fn test_tokens_refill_at_the_configured_rate() {
+        let limiter = RateLimiter::from_config(&RateLimitConfig {
+            search: GroupLimit { per_minute: 60, burst: 2 },
+            ..Default::default()
+        });
+        let ip: IpAddr = "1.2.3.4".parse().unwrap();
+        let now = Instant::now();
+        assert!(limiter.check("search", ip, now).is_ok());
+        assert!(limiter.check("search", ip, now).is_ok());
+        assert_eq!(limiter.check("search", ip, now), Err(1));
+        assert!(limiter.check("search", ip, now + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_submissions_have_their_own_group() {
+        assert_eq!(group_for("/api/v1/commands/7/examples/suggestions"), Some("submissions"));
+        assert_eq!(group_for("/api/commands/7/examples/suggestions"), Some("submissions"));
+        assert_eq!(group_for("/api/v1/commands/7"), Some("commands"));
+    }
+
+    #[test]
+    fn test_buckets_are_capped_and_evict_least_recently_used() {
+        let limiter = limiter();
+        let now = Instant::now();
+        // 被限流的客户端持续访问，不会被轮换 IP 的客户端挤出
+        for _ in 0..RateLimitConfig::default().submissions.burst {
+            limiter.check("submissions", ipv6(0), now).unwrap();
+        }
+        for n in 1..=MAX_BUCKETS {
+            limiter.check("api", ipv6(n), now).unwrap();
+            if n % 1000 == 0 {
+                assert!(limiter.check("submissions", ipv6(0), now).is_err());
+            }
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.entries.len(), MAX_BUCKETS);
+        assert_eq!(buckets.recency.len(), MAX_BUCKETS);
+        assert!(buckets.entries.contains_key(&("submissions", ipv6(0))));
+        assert!(!buckets.entries.contains_key(&("api", ipv6(1))));
+    }
+
+    #[test]
+    fn test_sweep_runs_at_most_once_per_interval() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for n in 0..SWEEP_THRESHOLD {
+            limiter.check("api", ipv6(n), now).unwrap();
+        }
+        // 第一次清理后，间隔内不再清理
+        limiter.check("api", ipv6(0), now + Duration::from_secs(3600)).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().entries.len(), 1);
+        for n in 1..SWEEP_THRESHOLD {
+            limiter.check("api", ipv6(n), now + Duration::from_secs(3600)).unwrap();
+        }
+        limiter.check("api", ipv6(0), now + Duration::from_secs(3600) + SWEEP_INTERVAL / 2).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().entries.len(), SWEEP_THRESHOLD);
+        limiter.check("api", ipv6(0), now + Duration::from_secs(3600) + SWEEP_INTERVAL).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().entries.len(), 1);
+    }
+}
@@ -0,0 +1,55 @@
+// 请求 id
+//
+// 每个请求分配一个 id（或沿用客户端传入的合法 X-Request-Id），写入响应头，
+// 并在处理期间保存在 task-local 中，供 AppError 写入错误响应体。
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use rand::RngCore;
+
+pub const HEADER: &str = "x-request-id";
+
+const MAX_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// 当前请求的 id；不在请求处理期间时为 None
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+fn generate() -> String {
+    let mut buf = [0u8; 8];
+    rand::rng().fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 只接受长度有限的可打印标识，避免日志注入
+fn accept(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_LEN
+        && value.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| accept(v))
+        .map(str::to_string)
+        .unwrap_or_else(generate);
+
+    let mut res = REQUEST_ID.scope(id.clone(), next.call(req)).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(HeaderName::from_static(HEADER), value);
+    }
+    Ok(res)
+}
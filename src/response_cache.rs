@@ -0,0 +1,215 @@
+// 进程内响应缓存
+//
+// 对可缓存路由（见 http_cache::cacheable_route）的成功响应按“路径 + 查询串”
+// 缓存序列化后的响应体，容量有限，按最近最少使用淘汰。
+// 数据库内容版本变化（写入或重新加载）时整个缓存失效。带 Authorization 的
+// 请求可能包含私人内容（笔记），不参与缓存；处理器自行按 Accept-Encoding
+// 选择的响应（预压缩的命令列表）也不缓存。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use actix_web::body::{self, EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::web::{self, Bytes};
+use actix_web::HttpResponse;
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::http_cache::{cacheable_route, content_version};
+use crate::metrics::metrics;
+use crate::{ApiResponse, AppError, AppState};
+
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+struct Entry {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    version: String,
+    entries: HashMap<String, Entry>,
+    // 最近使用时间 -> key，最小者最先淘汰
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Inner {
+    // 内容版本变化时清空缓存
+    fn sync_version(&mut self, version: &str) {
+        if self.version != version {
+            if !self.entries.is_empty() {
+                debug!("Response cache invalidated ({} entries)", self.entries.len());
+            }
+            self.entries.clear();
+            self.recency.clear();
+            self.version = version.to_string();
+        }
+    }
+
+    fn touch(&mut self, key: &str) -> Option<&Entry> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.used);
+        entry.used = tick;
+        self.recency.insert(tick, key.to_string());
+        self.entries.get(key)
+    }
+}
+
+pub struct ResponseCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    capacity: usize,
+    entries: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn get(&self, version: &str, key: &str) -> Option<(Option<HeaderValue>, Bytes)> {
+        let mut inner = self.lock();
+        inner.sync_version(version);
+        let found = inner.touch(key).map(|entry| (entry.content_type.clone(), entry.body.clone()));
+        match found {
+            Some(_) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                metrics().cache_hits.inc();
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                metrics().cache_misses.inc();
+            }
+        }
+        found
+    }
+
+    fn insert(&self, version: &str, key: String, content_type: Option<HeaderValue>, body: Bytes) {
+        let mut inner = self.lock();
+        // 处理期间内容已变化，结果可能已过期
+        if inner.version != version {
+            return;
+        }
+
+        inner.tick += 1;
+        let used = inner.tick;
+        if let Some(old) = inner.entries.insert(key.clone(), Entry { content_type, body, used }) {
+            inner.recency.remove(&old.used);
+        }
+        inner.recency.insert(used, key);
+
+        while inner.entries.len() > self.capacity {
+            match inner.recency.pop_first() {
+                Some((_, evicted)) => {
+                    inner.entries.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            capacity: self.capacity,
+            entries: self.lock().entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn varies_by_encoding(headers: &header::HeaderMap) -> bool {
+    headers.contains_key(header::CONTENT_ENCODING)
+        || headers
+            .get_all(header::VARY)
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.split(',').any(|name| name.trim().eq_ignore_ascii_case("accept-encoding")))
+}
+
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let cacheable = req.method() == Method::GET
+        && !req.headers().contains_key(header::AUTHORIZATION)
+        && cacheable_route(req.path()).is_some();
+    let cache = req.app_data::<web::Data<ResponseCache>>().cloned();
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+
+    let (cache, state) = match (cacheable, cache, state) {
+        (true, Some(cache), Some(state)) if cache.capacity > 0 => (cache, state),
+        _ => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    let version = match content_version(&req, &state) {
+        Ok(version) => version,
+        Err(e) => {
+            warn!("Skipping response cache, cannot read content version: {}", e);
+            return Ok(next.call(req).await?.map_into_left_body());
+        }
+    };
+    let key = format!("{}?{}", req.path(), req.query_string());
+
+    if let Some((content_type, body)) = cache.get(&version, &key) {
+        let mut res = HttpResponse::Ok();
+        if let Some(content_type) = content_type {
+            res.insert_header((header::CONTENT_TYPE, content_type));
+        }
+        return Ok(req.into_response(res.body(body)).map_into_right_body());
+    }
+
+    let res = next.call(req).await?;
+    if res.status() != StatusCode::OK || varies_by_encoding(res.headers()) {
+        return Ok(res.map_into_left_body());
+    }
+
+    // 读取完整响应体后缓存，再原样返回
+    let (req, res) = res.into_parts();
+    let (head, payload) = res.into_parts();
+    let bytes = body::to_bytes(payload).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(format!("Failed to buffer response: {}", e))
+    })?;
+    cache.insert(&version, key, head.headers().get(header::CONTENT_TYPE).cloned(), bytes.clone());
+
+    let res = head.set_body(bytes).map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+// 缓存命中统计
+pub async fn cache_stats(cache: Option<web::Data<ResponseCache>>) -> Result<HttpResponse, AppError> {
+    let stats = cache.map(|cache| cache.stats());
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: stats,
+        message: None,
+    }))
+}
@@ -0,0 +1,368 @@
+// 命令修订历史
+//
+// 对覆盖库中命令及其章节的每一次修改都会记录一条修订：作者、时间和完整快照。
+// 修订 id 全局递增，可在任意两条修订之间生成统一 diff，也可回滚到某条修订。
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::admin::{request_author, require_admin};
+use crate::overlay::{self, Source, OVERLAY_ID_OFFSET};
+use crate::{category_name, ApiResponse, AppError, AppState, CommandSection, ErrorResponse};
+
+// 命令在某一时刻的完整快照（包含 NAME 章节）
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CommandSnapshot {
+    name: String,
+    category: i64,
+    description: String,
+    sections: Vec<CommandSection>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Revision {
+    id: i64,
+    command_id: i64,
+    author: String,
+    action: String,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<CommandSnapshot>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevisionDiff {
+    from: i64,
+    to: i64,
+    diff: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiffQuery {
+    /// Older revision id
+    from: i64,
+    /// Newer revision id
+    to: i64,
+}
+
+pub fn ensure_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS CommandRevision (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command_id INTEGER NOT NULL,
+            author TEXT NOT NULL,
+            action TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            snapshot TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_command_revision_command ON CommandRevision (command_id);",
+    )
+}
+
+// 读取覆盖库中命令的当前快照
+pub fn snapshot(conn: &Connection, command_id: i64) -> Result<Option<CommandSnapshot>, AppError> {
+    let command: Option<(String, i64, String)> = conn
+        .query_row(
+            "SELECT name, category, description FROM Command WHERE id = ?1",
+            params![command_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    let (name, category, description) = match command {
+        Some(command) => command,
+        None => return Ok(None),
+    };
+
+    let mut stmt = conn.prepare("SELECT title, content FROM CommandSection WHERE command_id = ?1 ORDER BY id")?;
+    let sections = stmt
+        .query_map(params![command_id], |row| {
+            Ok(CommandSection {
+                title: row.get(0)?,
+                content: row.get(1)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some(CommandSnapshot {
+        name,
+        category,
+        description,
+        sections,
+    }))
+}
+
+// 记录命令的当前状态为一条新修订
+pub fn record(conn: &Connection, command_id: i64, author: &str, action: &str) -> Result<i64, AppError> {
+    let snapshot = snapshot(conn, command_id)?.ok_or(AppError::CommandNotFound)?;
+    let json = serde_json::to_string(&snapshot)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize snapshot: {}", e)))?;
+
+    conn.execute(
+        "INSERT INTO CommandRevision (command_id, author, action, snapshot) VALUES (?1, ?2, ?3, ?4)",
+        params![command_id, author, action, json],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// 不含快照的修订；快照由 get 单独解析
+fn revision_from_row(row: &rusqlite::Row) -> rusqlite::Result<Revision> {
+    Ok(Revision {
+        id: row.get(0)?,
+        command_id: row.get::<_, i64>(1)? + OVERLAY_ID_OFFSET,
+        author: row.get(2)?,
+        action: row.get(3)?,
+        created_at: row.get(4)?,
+        snapshot: None,
+    })
+}
+
+fn list(conn: &Connection, command_id: i64) -> Result<Vec<Revision>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, command_id, author, action, created_at FROM CommandRevision WHERE command_id = ?1 ORDER BY id DESC",
+    )?;
+    let revisions = stmt
+        .query_map(params![command_id], revision_from_row)?
+        .collect::<Result<_, _>>()?;
+    Ok(revisions)
+}
+
+fn get(conn: &Connection, command_id: i64, revision_id: i64) -> Result<Revision, AppError> {
+    let (mut revision, json) = conn
+        .query_row(
+            "SELECT id, command_id, author, action, created_at, snapshot FROM CommandRevision WHERE command_id = ?1 AND id = ?2",
+            params![command_id, revision_id],
+            |row| Ok((revision_from_row(row)?, row.get::<_, String>(5)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Revision {} not found", revision_id)))?;
+
+    let snapshot = serde_json::from_str(&json).map_err(|e| {
+        warn!("Revision {} of command {} has an unreadable snapshot: {}", revision_id, command_id, e);
+        AppError::InternalError(format!("Revision {} snapshot is corrupt", revision_id))
+    })?;
+    revision.snapshot = Some(snapshot);
+    Ok(revision)
+}
+
+// 将快照渲染为便于 diff 的纯文本
+fn render(snapshot: &CommandSnapshot) -> String {
+    let mut text = format!(
+        "name: {}\ncategory: {}\ndescription: {}\n",
+        snapshot.name,
+        category_name(snapshot.category),
+        snapshot.description
+    );
+    for section in &snapshot.sections {
+        text.push_str(&format!("\n## {}\n{}\n", section.title, section.content.trim_end()));
+    }
+    text
+}
+
+pub fn unified_diff(from: &Revision, to: &Revision) -> String {
+    let (old, new) = match (&from.snapshot, &to.snapshot) {
+        (Some(old), Some(new)) => (render(old), render(new)),
+        _ => return String::new(),
+    };
+
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("revision {}", from.id), &format!("revision {}", to.id))
+        .to_string()
+}
+
+// 用快照覆盖命令当前内容；命令已删除时按原 id 恢复
+fn restore(conn: &Connection, command_id: i64, snapshot: &CommandSnapshot) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO Command (id, category, name, description) VALUES (?1, ?2, ?3, ?4)",
+        params![command_id, snapshot.category, &snapshot.name, &snapshot.description],
+    )?;
+    conn.execute("DELETE FROM CommandSection WHERE command_id = ?1", params![command_id])?;
+    for section in &snapshot.sections {
+        conn.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![&section.title, &section.content, command_id],
+        )?;
+    }
+    Ok(())
+}
+
+// 将 API id 解析为覆盖库中记录修订的命令 id；上游命令对应其同名遮蔽副本
+fn revision_command_id(data: &AppState, public_id: i64) -> Result<Option<i64>, AppError> {
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => Ok(Some(id)),
+        (Source::Base, id) => {
+            let name: Option<String> = data
+                .lock_db()?
+                .query_row("SELECT name FROM Command WHERE id = ?1", params![id], |row| row.get(0))
+                .optional()?;
+            let name = name.ok_or(AppError::CommandNotFound)?;
+
+            match data.lock_overlay()? {
+                Some(overlay) => Ok(overlay
+                    .query_row("SELECT id FROM Command WHERE name = ?1 ORDER BY id LIMIT 1", params![&name], |row| row.get(0))
+                    .optional()?),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+fn revisions_available(conn: &Connection) -> Result<bool, AppError> {
+    Ok(overlay::table_exists(conn, "CommandRevision")?)
+}
+
+// 获取命令的修订列表（不含快照）
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands/{id}/revisions",
+    tag = "revisions",
+    params(("id" = i64, Path, description = "Command id")),
+    responses(
+        (status = 200, description = "Revisions of an overlay command, without snapshots; empty for upstream commands", body = ApiResponse<Vec<Revision>>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_revisions(
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let public_id = *path;
+    info!("Fetching revisions for command {}", public_id);
+
+    let revisions = match (revision_command_id(&data, public_id)?, data.lock_overlay()?) {
+        (Some(id), Some(overlay)) if revisions_available(&overlay)? => list(&overlay, id)?,
+        _ => vec![],
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(revisions),
+        message: None,
+    }))
+}
+
+fn load_revision(data: &AppState, public_id: i64, revision_id: i64) -> Result<Revision, AppError> {
+    let not_found = || AppError::NotFound(format!("Revision {} not found", revision_id));
+    let id = revision_command_id(data, public_id)?.ok_or_else(not_found)?;
+    match data.lock_overlay()? {
+        Some(overlay) if revisions_available(&overlay)? => get(&overlay, id, revision_id),
+        _ => Err(not_found()),
+    }
+}
+
+// 获取单条修订（含完整快照）
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands/{id}/revisions/{rev}",
+    tag = "revisions",
+    params(("id" = i64, Path, description = "Command id"), ("rev" = i64, Path, description = "Revision id")),
+    responses(
+        (status = 200, description = "Revision with its full snapshot", body = ApiResponse<Revision>),
+        (status = 404, description = "Revision not found for this command (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn get_revision(
+    path: web::Path<(i64, i64)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (public_id, revision_id) = path.into_inner();
+    let revision = load_revision(&data, public_id, revision_id)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(revision),
+        message: None,
+    }))
+}
+
+// 两条修订之间的统一 diff
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands/{id}/diff",
+    tag = "revisions",
+    params(("id" = i64, Path, description = "Command id"), DiffQuery),
+    responses(
+        (status = 200, description = "Unified diff between two revisions", body = ApiResponse<RevisionDiff>),
+        (status = 404, description = "Revision not found for this command (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn diff_revisions(
+    path: web::Path<i64>,
+    query: web::Query<DiffQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let public_id = *path;
+    let from = load_revision(&data, public_id, query.from)?;
+    let to = load_revision(&data, public_id, query.to)?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(RevisionDiff {
+            from: from.id,
+            to: to.id,
+            diff: unified_diff(&from, &to),
+        }),
+        message: None,
+    }))
+}
+
+// 回滚到指定修订，并记录一条新的 revert 修订
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/commands/{id}/revisions/{rev}/revert",
+    tag = "revisions",
+    params(("id" = i64, Path, description = "Command id"), ("rev" = i64, Path, description = "Revision id")),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "New revision recording the revert", body = ApiResponse<Revision>),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Revision not found for this command (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn revert_revision(
+    req: HttpRequest,
+    path: web::Path<(i64, i64)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let (public_id, revision_id) = path.into_inner();
+
+    let revision = load_revision(&data, public_id, revision_id)?;
+    // get 总是解析快照，解析失败时已返回错误
+    let snapshot = revision
+        .snapshot
+        .ok_or_else(|| AppError::InternalError(format!("Revision {} has no snapshot", revision_id)))?;
+    let command_id = revision.command_id - OVERLAY_ID_OFFSET;
+
+    let overlay = data
+        .lock_overlay()?
+        .ok_or_else(|| AppError::InternalError("Overlay database is not configured".to_string()))?;
+    let tx = overlay.unchecked_transaction()?;
+    restore(&tx, command_id, &snapshot)?;
+    let new_revision = record(&tx, command_id, &request_author(&req), &format!("revert:{}", revision_id))?;
+    tx.commit()?;
+
+    info!("Reverted command {} to revision {} (new revision {})", revision.command_id, revision_id, new_revision);
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(get(&overlay, command_id, new_revision)?),
+        message: None,
+    }))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/commands/{id}/revisions", web::get().to(list_revisions))
+        .route("/commands/{id}/revisions/{rev}", web::get().to(get_revision))
+        .route("/commands/{id}/diff", web::get().to(diff_revisions));
+}
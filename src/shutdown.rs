@@ -0,0 +1,123 @@
+// 优雅停机
+//
+// 收到 SIGTERM 或 SIGINT 后先将就绪检查置为失败，等待 shutdown_delay 让负载均衡
+// 摘除实例，然后停止接受新连接，并在 shutdown_timeout 内等待处理中的请求完成。
+// 服务器停止后按注册顺序执行停机钩子（刷新并关闭可写存储）。
+// 停机期间再次收到信号时立即停止。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServerHandle;
+use log::{error, info, warn};
+use rusqlite::Connection;
+
+use crate::{AppError, AppState};
+
+type Hook = Box<dyn FnOnce(&AppState) -> Result<(), AppError> + Send>;
+
+#[derive(Default)]
+pub struct Shutdown {
+    shutting_down: AtomicBool,
+    hooks: Mutex<Vec<(&'static str, Hook)>>,
+}
+
+impl Shutdown {
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    pub fn begin(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn add_hook(&self, name: &'static str, hook: impl FnOnce(&AppState) -> Result<(), AppError> + Send + 'static) {
+        self.hooks.lock().unwrap_or_else(|e| e.into_inner()).push((name, Box::new(hook)));
+    }
+
+    // 执行并移除全部钩子；单个钩子失败不影响其余钩子，返回失败的数量
+    pub fn run_hooks(&self, state: &AppState) -> usize {
+        let hooks = std::mem::take(&mut *self.hooks.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut failed = 0;
+        for (name, hook) in hooks {
+            let started = Instant::now();
+            match hook(state) {
+                Ok(()) => info!("Shutdown hook '{}' finished in {:.1}ms", name, started.elapsed().as_secs_f64() * 1000.0),
+                Err(e) => {
+                    failed += 1;
+                    error!("Shutdown hook '{}' failed: {}", name, e);
+                }
+            }
+        }
+        failed
+    }
+}
+
+// 用内存连接替换并显式关闭，关闭失败（例如仍有未完成的语句）时返回错误
+fn close(conn: &mut Connection) -> Result<(), AppError> {
+    let conn = std::mem::replace(conn, Connection::open_in_memory()?);
+    conn.close().map_err(|(_, e)| AppError::DatabaseError(e))
+}
+
+// 注册数据库的停机钩子：覆盖库先写回 WAL 并更新查询规划统计，再关闭连接
+pub fn register_database_hooks(state: &AppState) {
+    if state.overlay.is_some() {
+        state.shutdown.add_hook("overlay database", |state| {
+            let Some(mut conn) = state.lock_overlay()? else { return Ok(()) };
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            conn.execute_batch("PRAGMA optimize")?;
+            close(&mut conn)
+        });
+    }
+    state.shutdown.add_hook("database", |state| close(&mut *state.lock_db()?));
+}
+
+// 将就绪检查置为失败，等待 delay 后优雅停止服务器
+pub async fn drain(server: &ServerHandle, state: &AppState, delay: Duration) {
+    state.shutdown.begin();
+    if !delay.is_zero() {
+        info!("Readiness now failing, waiting {:?} before closing listeners", delay);
+        actix_web::rt::time::sleep(delay).await;
+    }
+    info!("Closing listeners and draining in-flight requests");
+    server.stop(true).await;
+}
+
+#[cfg(unix)]
+async fn next_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut term = signal(SignalKind::terminate())?;
+    let mut int = signal(SignalKind::interrupt())?;
+    Ok(tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        _ = int.recv() => "SIGINT",
+    })
+}
+
+#[cfg(not(unix))]
+async fn next_signal() -> std::io::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
+
+// 代替 actix 内置的信号处理（需配合 HttpServer::disable_signals）
+pub fn handle_signals(server: ServerHandle, state: actix_web::web::Data<AppState>, delay: Duration) {
+    actix_web::rt::spawn(async move {
+        match next_signal().await {
+            Ok(signal) => info!("Received {}, shutting down", signal),
+            Err(e) => {
+                error!("Cannot listen for shutdown signals: {}", e);
+                return;
+            }
+        }
+        tokio::select! {
+            _ = drain(&server, &state, delay) => {}
+            signal = next_signal() => {
+                warn!("Received {} during shutdown, stopping immediately", signal.unwrap_or("signal"));
+                server.stop(false).await;
+            }
+        }
+    });
+}
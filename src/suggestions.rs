@@ -0,0 +1,430 @@
+// 用户提交的示例建议与审核队列
+//
+// 任何人都可以为命令提交新示例或修改已有示例，建议先以 pending 状态保存，
+// 作者取自 bearer token 对应的用户，未携带 token 时记为 anonymous。
+// 管理员审核通过后写入 CommandExample，并在 CommandDetail.examples 中返回。
+// 示例按命令名称关联，因此同时适用于上游命令和覆盖库命令。
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::admin::{bearer_token, constant_time_eq, request_author, require_admin, require_text};
+use crate::{overlay, users};
+use crate::{resolve_command_detail, ApiResponse, AppError, AppState, ErrorResponse};
+
+const MAX_DESCRIPTION_LEN: usize = 1024;
+const MAX_EXAMPLE_LEN: usize = 4096;
+const MAX_REVIEW_NOTE_LEN: usize = 1024;
+
+// 未携带 token 的建议的作者
+const ANONYMOUS: &str = "anonymous";
+
+// 审核通过的团队示例
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommandExample {
+    id: i64,
+    pub description: String,
+    pub command: String,
+    author: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Suggestion {
+    id: i64,
+    command_name: String,
+    description: String,
+    command: String,
+    author: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replaces: Option<i64>,
+    status: String,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reviewer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    review_note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SuggestionInput {
+    description: String,
+    command: String,
+    // 要修改的已有示例 id；为空表示新增示例
+    replaces: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestionFilter {
+    /// `pending` (default), `approved` or `rejected`
+    status: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReviewInput {
+    note: Option<String>,
+}
+
+impl SuggestionInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Example description", &self.description, MAX_DESCRIPTION_LEN)?;
+        require_text("Example command", &self.command, MAX_EXAMPLE_LEN)?;
+        Ok(())
+    }
+}
+
+pub fn ensure_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS CommandExample (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command_name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            command TEXT NOT NULL,
+            author TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_command_example_name ON CommandExample (command_name);
+        CREATE TABLE IF NOT EXISTS ExampleSuggestion (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command_name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            command TEXT NOT NULL,
+            author TEXT NOT NULL,
+            replaces INTEGER,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            reviewer TEXT,
+            review_note TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_example_suggestion_status ON ExampleSuggestion (status);",
+    )
+}
+
+// 读取命令已审核通过的示例
+pub fn examples_for(conn: &Connection, command_name: &str) -> Result<Vec<CommandExample>, AppError> {
+    if !overlay::table_exists(conn, "CommandExample")? {
+        return Ok(vec![]);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, description, command, author, created_at FROM CommandExample WHERE command_name = ?1 ORDER BY id",
+    )?;
+    let examples = stmt
+        .query_map(params![command_name], |row| {
+            Ok(CommandExample {
+                id: row.get(0)?,
+                description: row.get(1)?,
+                command: row.get(2)?,
+                author: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(examples)
+}
+
+// 读取全部已审核示例，按命令名称分组（供目录快照使用）
+pub fn all_examples(conn: &Connection) -> Result<HashMap<String, Vec<CommandExample>>, AppError> {
+    let mut by_name: HashMap<String, Vec<CommandExample>> = HashMap::new();
+    if !overlay::table_exists(conn, "CommandExample")? {
+        return Ok(by_name);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT command_name, id, description, command, author, created_at FROM CommandExample ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            CommandExample {
+                id: row.get(1)?,
+                description: row.get(2)?,
+                command: row.get(3)?,
+                author: row.get(4)?,
+                created_at: row.get(5)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (name, example) = row?;
+        by_name.entry(name).or_default().push(example);
+    }
+    Ok(by_name)
+}
+
+fn suggestion_from_row(row: &rusqlite::Row) -> rusqlite::Result<Suggestion> {
+    Ok(Suggestion {
+        id: row.get(0)?,
+        command_name: row.get(1)?,
+        description: row.get(2)?,
+        command: row.get(3)?,
+        author: row.get(4)?,
+        replaces: row.get(5)?,
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+        reviewer: row.get(8)?,
+        review_note: row.get(9)?,
+    })
+}
+
+const SUGGESTION_COLUMNS: &str =
+    "id, command_name, description, command, author, replaces, status, created_at, reviewer, review_note";
+
+fn get_suggestion(conn: &Connection, id: i64) -> Result<Suggestion, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM ExampleSuggestion WHERE id = ?1", SUGGESTION_COLUMNS),
+        params![id],
+        suggestion_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Suggestion {} not found", id)))
+}
+
+fn lock_store(data: &AppState) -> Result<std::sync::MutexGuard<'_, rusqlite::Connection>, AppError> {
+    users::lock_feature_store(data, "ExampleSuggestion", "Example suggestions")
+}
+
+// 建议的作者：管理员 token 取 X-Author，用户 token 取用户名，未携带 token 时为 anonymous；
+// 不接受请求体中的作者，避免冒用他人名义
+fn suggestion_author(req: &HttpRequest, data: &AppState, conn: &Connection) -> Result<String, AppError> {
+    let Some(token) = bearer_token(req) else {
+        return Ok(ANONYMOUS.to_string());
+    };
+    if let Some(admin_token) = &data.admin_token
+        && constant_time_eq(token.as_bytes(), admin_token.as_bytes())
+    {
+        return Ok(request_author(req));
+    }
+    if !overlay::table_exists(conn, "User")? {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(users::require_user(req, conn)?.name)
+}
+
+// 提交示例建议
+#[utoipa::path(
+    post,
+    path = "/api/v1/commands/{id}/examples/suggestions",
+    tag = "suggestions",
+    params(("id" = i64, Path, description = "Command id")),
+    request_body = SuggestionInput,
+    security((), ("user_token" = []), ("admin_token" = [])),
+    responses(
+        (status = 201, description = "Pending suggestion; anonymous when no token is given", body = ApiResponse<Suggestion>),
+        (status = 400, description = "Empty or too long text, or `replaces` names an example of another command (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Unknown bearer token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 503, description = "Example suggestions are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn submit_suggestion(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<SuggestionInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+
+    let command = resolve_command_detail(&data, *path)?.ok_or(AppError::CommandNotFound)?;
+
+    let conn = lock_store(&data)?;
+    let author = suggestion_author(&req, &data, &conn)?;
+    if let Some(example_id) = body.replaces {
+        let target: Option<String> = conn
+            .query_row("SELECT command_name FROM CommandExample WHERE id = ?1", params![example_id], |row| row.get(0))
+            .optional()?;
+        if target.as_deref() != Some(command.name.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Example {} does not belong to command '{}'",
+                example_id, command.name
+            )));
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO ExampleSuggestion (command_name, description, command, author, replaces) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![&command.name, body.description.trim(), body.command.trim(), &author, body.replaces],
+    )?;
+    let suggestion = get_suggestion(&conn, conn.last_insert_rowid())?;
+
+    info!("Received example suggestion {} for command '{}'", suggestion.id, command.name);
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(suggestion),
+        message: Some("Suggestion submitted for review".to_string()),
+    }))
+}
+
+// 审核队列（默认只列出 pending）
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/suggestions",
+    tag = "suggestions",
+    params(SuggestionFilter),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Suggestions with the given status, oldest first", body = ApiResponse<Vec<Suggestion>>),
+        (status = 400, description = "Unknown status (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "Example suggestions are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_suggestions(
+    req: HttpRequest,
+    query: web::Query<SuggestionFilter>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+
+    let status = query.status.as_deref().unwrap_or("pending");
+    if !matches!(status, "pending" | "approved" | "rejected") {
+        return Err(AppError::InvalidInput(format!("Unknown suggestion status '{}'", status)));
+    }
+
+    let conn = lock_store(&data)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM ExampleSuggestion WHERE status = ?1 ORDER BY id",
+        SUGGESTION_COLUMNS
+    ))?;
+    let suggestions: Vec<Suggestion> = stmt
+        .query_map(params![status], suggestion_from_row)?
+        .collect::<Result<_, _>>()?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(suggestions),
+        message: None,
+    }))
+}
+
+fn review(
+    conn: &Connection,
+    id: i64,
+    reviewer: &str,
+    note: Option<&str>,
+    status: &str,
+) -> Result<Suggestion, AppError> {
+    if let Some(note) = note {
+        require_text("Review note", note, MAX_REVIEW_NOTE_LEN)?;
+    }
+    let suggestion = get_suggestion(conn, id)?;
+    if suggestion.status != "pending" {
+        return Err(AppError::InvalidInput(format!(
+            "Suggestion {} has already been {}",
+            id, suggestion.status
+        )));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    if status == "approved" {
+        match suggestion.replaces {
+            Some(example_id) => {
+                let updated = tx.execute(
+                    "UPDATE CommandExample SET description = ?1, command = ?2, author = ?3 WHERE id = ?4",
+                    params![&suggestion.description, &suggestion.command, &suggestion.author, example_id],
+                )?;
+                if updated == 0 {
+                    return Err(AppError::NotFound(format!("Example {} not found", example_id)));
+                }
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO CommandExample (command_name, description, command, author) VALUES (?1, ?2, ?3, ?4)",
+                    params![&suggestion.command_name, &suggestion.description, &suggestion.command, &suggestion.author],
+                )?;
+            }
+        }
+    }
+    tx.execute(
+        "UPDATE ExampleSuggestion SET status = ?1, reviewer = ?2, review_note = ?3 WHERE id = ?4",
+        params![status, reviewer, note, id],
+    )?;
+    tx.commit()?;
+
+    info!("Suggestion {} for '{}' {} by {}", id, suggestion.command_name, status, reviewer);
+    get_suggestion(conn, id)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/suggestions/{id}/approve",
+    tag = "suggestions",
+    params(("id" = i64, Path, description = "Suggestion id")),
+    request_body = Option<ReviewInput>,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Approved suggestion; its example is now part of the command", body = ApiResponse<Suggestion>),
+        (status = 400, description = "Suggestion already reviewed, or the review note is too long (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Suggestion, or the example it replaces, not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "Example suggestions are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn approve_suggestion(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: Option<web::Json<ReviewInput>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let note = body.as_ref().and_then(|b| b.note.as_deref());
+
+    let conn = lock_store(&data)?;
+    let suggestion = review(&conn, *path, &request_author(&req), note, "approved")?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(suggestion),
+        message: None,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/suggestions/{id}/reject",
+    tag = "suggestions",
+    params(("id" = i64, Path, description = "Suggestion id")),
+    request_body = Option<ReviewInput>,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Rejected suggestion", body = ApiResponse<Suggestion>),
+        (status = 400, description = "Suggestion already reviewed, or the review note is too long (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid admin token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Suggestion, or the example it replaces, not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "Example suggestions are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn reject_suggestion(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: Option<web::Json<ReviewInput>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req, &data)?;
+    let note = body.as_ref().and_then(|b| b.note.as_deref());
+
+    let conn = lock_store(&data)?;
+    let suggestion = review(&conn, *path, &request_author(&req), note, "rejected")?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(suggestion),
+        message: None,
+    }))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/commands/{id}/examples/suggestions", web::post().to(submit_suggestion));
+}
@@ -0,0 +1,422 @@
+// tldr-pages 格式的导入与导出
+//
+// TLDR 章节按前端直接渲染的 HTML 保存，每个 tldr 元素一行，行之间用 <br> 分隔：
+// `> 描述` 为文本行，`- 示例说明:` 为 <b>…</b>，`` `命令` `` 为 <code>…</code>，
+// 描述与各示例之间空一行。导入读取 tldr-pages 仓库中的 pages/common 与
+// pages/linux（同名时 linux 优先），内容与现有 TLDR 不同时写入覆盖库（上游命令
+// 先复制到覆盖库），并记录修订。导出把 TLDR 章节与已审核的团队示例写回 tldr
+// Markdown 页面。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+use rusqlite::params;
+
+use crate::admin::copy_on_write;
+use crate::overlay::{self, Source, OVERLAY_ID_OFFSET};
+use crate::{
+    export, find_command_by_name, resolve_command_detail, revisions, suggestions, AppError, AppState, MISCELLANEOUS_CATEGORY,
+};
+
+pub const SECTION: &str = "TLDR";
+
+// 导入的平台目录，后面的优先
+const PLATFORMS: &[&str] = &["common", "linux"];
+
+// 导入写入修订时的作者
+const AUTHOR: &str = "tldr-import";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub description: String,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub name: String,
+    // `>` 行，包括 "More information: <url>."
+    pub description: Vec<String>,
+    pub examples: Vec<Example>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// 去掉 HTML 标签后的纯文本
+pub fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    unescape(text.trim())
+}
+
+impl Page {
+    pub fn parse_markdown(text: &str) -> Result<Self, String> {
+        let mut name = None;
+        let mut description = Vec::new();
+        let mut examples = Vec::new();
+        let mut pending: Option<String> = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(title) = line.strip_prefix("# ") {
+                name.get_or_insert_with(|| title.trim().to_string());
+            } else if let Some(text) = line.strip_prefix('>') {
+                description.push(text.trim().to_string());
+            } else if let Some(text) = line.strip_prefix("- ") {
+                if pending.replace(text.trim().to_string()).is_some() {
+                    return Err(format!("line {}: example description without a command", n + 1));
+                }
+            } else if line.len() >= 2 && line.starts_with('`') && line.ends_with('`') {
+                let description = pending.take().ok_or_else(|| format!("line {}: command without a description", n + 1))?;
+                examples.push(Example { description, command: line[1..line.len() - 1].to_string() });
+            } else if !line.is_empty() {
+                return Err(format!("line {}: unexpected {:?}", n + 1, line));
+            }
+        }
+        if pending.is_some() {
+            return Err("last example has no command".to_string());
+        }
+        let name = name.ok_or("missing `# name` title")?;
+        if examples.is_empty() {
+            return Err("page has no examples".to_string());
+        }
+        Ok(Self { name, description, examples })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.name);
+        for line in &self.description {
+            out.push_str(&format!("> {}\n", line));
+        }
+        for example in &self.examples {
+            let description = match example.description.trim_end() {
+                "" => "Example:".to_string(),
+                d if d.ends_with(':') => d.to_string(),
+                d => format!("{}:", d),
+            };
+            out.push_str(&format!("\n- {}\n\n`{}`\n", description, example.command));
+        }
+        out
+    }
+
+    // 解析已保存的 TLDR 章节；同时兼容 <br> 或换行分隔、带或不带 tldr 标记的纯文本
+    pub fn from_section(name: &str, content: &str) -> Self {
+        let mut normalized = content.replace("\r\n", "\n");
+        for br in ["<br>", "<br/>", "<br />"] {
+            normalized = normalized.replace(br, "\n");
+        }
+        let mut description = Vec::new();
+        let mut examples = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        for line in normalized.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let is_command = line.starts_with("<code>") || (line.len() >= 2 && line.starts_with('`') && line.ends_with('`'));
+            let text = strip_tags(line);
+            if is_command {
+                let command = text.trim_matches('`').to_string();
+                let example = pending.pop().unwrap_or_default();
+                description.append(&mut pending);
+                examples.push(Example { description: example, command });
+            } else {
+                let text = text.strip_prefix("- ").or_else(|| text.strip_prefix("> ")).unwrap_or(&text);
+                pending.push(text.trim_end_matches(':').to_string());
+            }
+        }
+        description.append(&mut pending);
+        Self { name: name.to_string(), description, examples }
+    }
+
+    pub fn to_section(&self) -> String {
+        let mut blocks = Vec::new();
+        if !self.description.is_empty() {
+            blocks.push(self.description.iter().map(|line| escape(line)).collect::<Vec<_>>().join("<br>"));
+        }
+        for example in &self.examples {
+            blocks.push(format!(
+                "<b>{}</b><br><code>{}</code>",
+                escape(example.description.trim_end_matches(':')),
+                escape(&example.command)
+            ));
+        }
+        blocks.join("<br><br>")
+    }
+
+    // 比较时忽略示例说明末尾的冒号
+    fn same_content(&self, other: &Page) -> bool {
+        let examples = |page: &Page| -> Vec<(String, String)> {
+            page.examples
+                .iter()
+                .map(|e| (e.description.trim_end_matches(':').to_string(), e.command.clone()))
+                .collect()
+        };
+        self.description == other.description && examples(self) == examples(other)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub updated: usize,
+    pub created: usize,
+    pub unchanged: usize,
+    // 库中没有对应命令的页面
+    pub unmatched: Vec<String>,
+    // 无法解析的页面及原因
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+// pages/<platform>/*.md，同名页面后面的平台覆盖前面的
+fn collect_pages(checkout: &Path) -> Result<BTreeMap<String, PathBuf>, Box<dyn std::error::Error>> {
+    let pages = checkout.join("pages");
+    if !pages.is_dir() {
+        return Err(format!("{} is not a tldr-pages checkout (no pages/ directory)", checkout.display()).into());
+    }
+    let mut found = BTreeMap::new();
+    for platform in PLATFORMS {
+        let dir = pages.join(platform);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "md")
+                && let Some(stem) = path.file_stem()
+            {
+                found.insert(stem.to_string_lossy().into_owned(), path);
+            }
+        }
+    }
+    Ok(found)
+}
+
+enum Outcome {
+    Updated,
+    Created,
+    Unchanged,
+    Unmatched,
+}
+
+fn writable_overlay(data: &AppState) -> Result<std::sync::MutexGuard<'_, rusqlite::Connection>, AppError> {
+    data.lock_overlay()?
+        .ok_or_else(|| AppError::InternalError("Overlay database is not configured".to_string()))
+}
+
+// 写入一个命令的 TLDR 章节
+fn import_page(data: &AppState, page: &Page, file_name: &str, create_missing: bool) -> Result<Outcome, AppError> {
+    let command = match find_command_by_name(data, file_name)? {
+        Some(command) => Some(command),
+        None if page.name != file_name => find_command_by_name(data, &page.name)?,
+        None => None,
+    };
+    let content = page.to_section();
+
+    let Some(command) = command else {
+        if !create_missing {
+            return Ok(Outcome::Unmatched);
+        }
+        let overlay = writable_overlay(data)?;
+        let description = page.description.first().cloned().unwrap_or_default();
+        let tx = overlay.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO Command (category, name, description) VALUES (?1, ?2, ?3)",
+            params![MISCELLANEOUS_CATEGORY, &page.name, description],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![SECTION, &content, id],
+        )?;
+        revisions::record(&tx, id, AUTHOR, "create")?;
+        tx.commit()?;
+        info!("Created command '{}' ({}) from tldr page", page.name, id + OVERLAY_ID_OFFSET);
+        return Ok(Outcome::Created);
+    };
+
+    let existing = resolve_command_detail(data, command.id)?.and_then(|detail| detail.tldr);
+    if existing.is_some_and(|existing| Page::from_section(&command.name, &existing).same_content(page)) {
+        return Ok(Outcome::Unchanged);
+    }
+
+    let base = data.lock_db()?;
+    let overlay = writable_overlay(data)?;
+    let tx = overlay.unchecked_transaction()?;
+    let id = match overlay::resolve_id(command.id) {
+        (Source::Overlay, id) => id,
+        (Source::Base, id) => copy_on_write(&base, &tx, id)?,
+    };
+    let updated = tx.execute(
+        "UPDATE CommandSection SET content = ?1 WHERE command_id = ?2 AND title = ?3",
+        params![&content, id, SECTION],
+    )?;
+    if updated == 0 {
+        tx.execute(
+            "INSERT INTO CommandSection (title, content, command_id) VALUES (?1, ?2, ?3)",
+            params![SECTION, &content, id],
+        )?;
+    }
+    revisions::record(&tx, id, AUTHOR, &format!("section:{}", SECTION))?;
+    tx.commit()?;
+    info!("Imported tldr page for '{}' ({})", command.name, id + OVERLAY_ID_OFFSET);
+    Ok(Outcome::Updated)
+}
+
+// 从本地 tldr-pages 仓库导入；create_missing 时为库中没有的命令在覆盖库中新建命令
+pub fn import(data: &AppState, checkout: &Path, create_missing: bool) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+    if data.lock_overlay()?.is_none() {
+        return Err("tldr import writes to the overlay database; set OVERLAY_DATABASE_PATH".into());
+    }
+    let mut summary = ImportSummary::default();
+    for (file_name, path) in collect_pages(checkout)? {
+        let page = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| Page::parse_markdown(&text)) {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("Skipping tldr page {}: {}", path.display(), e);
+                summary.failed.push((path, e));
+                continue;
+            }
+        };
+        match import_page(data, &page, &file_name, create_missing)? {
+            Outcome::Updated => summary.updated += 1,
+            Outcome::Created => summary.created += 1,
+            Outcome::Unchanged => summary.unchanged += 1,
+            Outcome::Unmatched => summary.unmatched.push(file_name),
+        }
+    }
+    info!(
+        "tldr import: {} updated, {} created, {} unchanged, {} without a matching command, {} failed",
+        summary.updated,
+        summary.created,
+        summary.unchanged,
+        summary.unmatched.len(),
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+// 页面文件名：tldr 用连字符代替空格；无法作为文件名的命令跳过
+fn page_file_name(name: &str) -> Option<String> {
+    let file = name.trim().replace(' ', "-");
+    let valid = !file.is_empty() && !file.starts_with('.') && !file.contains(['/', '\\', '\0']);
+    valid.then(|| format!("{}.md", file))
+}
+
+// 导出有 TLDR 章节或团队示例的命令到 <output>/pages/<platform>/；同名文件被覆盖
+pub fn export(data: &AppState, output: &Path, platform: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let examples = match data.lock_overlay()? {
+        Some(conn) => suggestions::all_examples(&conn)?,
+        None => Default::default(),
+    };
+    let dir = output.join("pages").join(platform);
+    fs::create_dir_all(&dir)?;
+
+    let mut written = 0;
+    export::for_each_command(data, |command, sections| {
+        let tldr = sections.iter().find(|section| section.title == SECTION);
+        let team = examples.get(&command.name).map(Vec::as_slice).unwrap_or_default();
+        if tldr.is_none() && team.is_empty() {
+            return Ok(());
+        }
+        let Some(file) = page_file_name(&command.name) else {
+            warn!("Skipping tldr export of '{}': not a valid file name", command.name);
+            return Ok(());
+        };
+
+        let mut page = match tldr {
+            Some(section) => Page::from_section(&command.name, &section.content),
+            None => Page { name: command.name.clone(), description: vec![], examples: vec![] },
+        };
+        if page.description.is_empty() {
+            page.description.push(command.description.clone());
+        }
+        for example in team {
+            if !page.examples.iter().any(|e| e.command == example.command) {
+                page.examples.push(Example { description: example.description.clone(), command: example.command.clone() });
+            }
+        }
+        // 无法识别出示例的 TLDR（例如纯文本）不是有效的 tldr 页面
+        if page.examples.is_empty() {
+            debug!("Skipping tldr export of '{}': no examples", command.name);
+            return Ok(());
+        }
+        fs::write(dir.join(file), page.to_markdown())?;
+        written += 1;
+        Ok(())
+    })?;
+    info!("Exported {} tldr pages to {}", written, dir.display());
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "# tar\n\n> Archiving utility.\n> Often combined with gzip.\n> More information: <https://www.gnu.org/software/tar>.\n\n\
+                        - Create an archive from files:\n\n`tar cf {{target.tar}} {{file1 file2 ...}}`\n\n\
+                        - Extract into a directory & list files:\n\n`tar xvf {{source.tar}} -C {{path/to/directory}} > {{log}}`\n";
+
+    #[test]
+    fn test_markdown_round_trip_keeps_placeholders() {
+        let page = Page::parse_markdown(PAGE).unwrap();
+        assert_eq!(page.name, "tar");
+        assert_eq!(page.examples[0].command, "tar cf {{target.tar}} {{file1 file2 ...}}");
+        assert_eq!(page.examples[1].description, "Extract into a directory & list files:");
+        assert_eq!(page.to_markdown(), PAGE);
+    }
+
+    #[test]
+    fn test_multi_line_descriptions() {
+        let page = Page::parse_markdown(PAGE).unwrap();
+        assert_eq!(
+            page.description,
+            ["Archiving utility.", "Often combined with gzip.", "More information: <https://www.gnu.org/software/tar>."]
+        );
+        let section = page.to_section();
+        assert!(section.starts_with(
+            "Archiving utility.<br>Often combined with gzip.<br>More information: &lt;https://www.gnu.org/software/tar&gt;.<br><br>"
+        ));
+        assert_eq!(Page::from_section("tar", &section).description, page.description);
+    }
+
+    #[test]
+    fn test_html_escaping_both_ways() {
+        let page = Page::parse_markdown(PAGE).unwrap();
+        let section = page.to_section();
+        assert!(section.ends_with(
+            "<b>Extract into a directory &amp; list files</b><br><code>tar xvf {{source.tar}} -C {{path/to/directory}} &gt; {{log}}</code>"
+        ));
+
+        let parsed = Page::from_section("tar", &section);
+        assert!(parsed.same_content(&page));
+        assert_eq!(parsed.examples[1].command, "tar xvf {{source.tar}} -C {{path/to/directory}} > {{log}}");
+        assert_eq!(parsed.examples[1].description, "Extract into a directory & list files");
+
+        // 上游 TLDR 中的其他实体与标签
+        let upstream = Page::from_section("x", "<b>Quote &quot;it&quot;</b><br><code>echo &#39;a&nbsp;b&#39;</code>");
+        assert_eq!(upstream.examples, [Example { description: "Quote \"it\"".to_string(), command: "echo 'a b'".to_string() }]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Page::parse_markdown("# x\n\n`cmd`\n").unwrap_err(), "line 3: command without a description");
+        assert_eq!(Page::parse_markdown("# x\n\n- a:\n- b:\n").unwrap_err(), "line 4: example description without a command");
+        assert_eq!(Page::parse_markdown("# x\n\n> Only text.\n").unwrap_err(), "page has no examples");
+    }
+}
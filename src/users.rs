@@ -0,0 +1,977 @@
+// 轻量用户身份、收藏与合集
+//
+// 用户通过 POST /api/users 注册并获得一个 bearer token（只返回一次，库中只保存其 SHA-256）。
+// 注册按 IP 单独限流（rate_limit 的 registration 组），关闭 open_registration 后只有管理员可以注册。
+// 收藏和合集保存在可写存储中，合集可通过只读分享链接访问并导出为 Markdown。
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use log::{info, warn};
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::MutexGuard;
+use utoipa::ToSchema;
+
+use crate::admin::{bearer_token, ok, require_admin, require_text};
+use crate::http_cache::hex;
+use crate::overlay::{self, Source};
+use crate::tldr::strip_tags;
+use crate::{resolve_command_detail, ApiResponse, AppError, AppState, ErrorResponse};
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_COLLECTION_NAME_LEN: usize = 128;
+const MAX_DESCRIPTION_LEN: usize = 1024;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Registration {
+    user: User,
+    // 仅在注册时返回一次
+    token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Favorite {
+    command_id: i64,
+    name: String,
+    description: String,
+    added_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemKind {
+    Command,
+    OneLiner,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionItem {
+    id: i64,
+    kind: ItemKind,
+    ref_id: i64,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Collection {
+    id: i64,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    owner: String,
+    // 只对所有者返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    share_url: Option<String>,
+    items: Vec<CollectionItem>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterInput {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CollectionInput {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ItemInput {
+    kind: ItemKind,
+    id: i64,
+}
+
+impl CollectionInput {
+    pub fn validate(&self) -> Result<(), AppError> {
+        require_text("Collection name", &self.name, MAX_COLLECTION_NAME_LEN)?;
+        if let Some(description) = &self.description {
+            require_text("Collection description", description, MAX_DESCRIPTION_LEN)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn ensure_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS User (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+        CREATE TABLE IF NOT EXISTS Favorite (
+            user_id INTEGER NOT NULL,
+            command_id INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            PRIMARY KEY (user_id, command_id)
+        );
+        CREATE TABLE IF NOT EXISTS Collection (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            share_token TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+        );
+        CREATE TABLE IF NOT EXISTS CollectionItem (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            collection_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            ref_id INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_collection_item_collection ON CollectionItem (collection_id);",
+    )
+}
+
+// token 与哈希
+
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::rng().fill_bytes(&mut buf);
+    hex(&buf)
+}
+
+fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+pub fn lock_store(data: &AppState) -> Result<MutexGuard<'_, Connection>, AppError> {
+    lock_feature_store(data, "User", "User accounts")
+}
+
+// 可写存储中存在 table 时返回连接；否则说明 feature 未启用
+pub fn lock_feature_store<'a>(
+    data: &'a AppState,
+    table: &str,
+    feature: &str,
+) -> Result<MutexGuard<'a, Connection>, AppError> {
+    match data.lock_overlay()? {
+        Some(conn) if overlay::table_exists(&conn, table)? => Ok(conn),
+        _ => Err(AppError::FeatureDisabled(feature.to_string())),
+    }
+}
+
+// 根据 bearer token 识别当前用户
+pub fn require_user(req: &HttpRequest, conn: &Connection) -> Result<User, AppError> {
+    let token = bearer_token(req).ok_or_else(|| {
+        warn!("User request rejected: missing bearer token");
+        AppError::Unauthorized
+    })?;
+
+    conn.query_row(
+        "SELECT id, name, created_at FROM User WHERE token_hash = ?1",
+        params![hash_token(token)],
+        |row| {
+            Ok(User {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| {
+        warn!("User request rejected: unknown token");
+        AppError::Unauthorized
+    })
+}
+
+// 条目解析
+
+// 读取一行命令（BasicCommand）及其分组描述
+fn load_one_liner(data: &AppState, public_id: i64) -> Result<Option<(String, String)>, AppError> {
+    let sql = "SELECT bc.command, bg.description
+               FROM BasicCommand bc
+               JOIN BasicGroup bg ON bc.group_id = bg.id
+               WHERE bc.id = ?1";
+    let read = |conn: &Connection, id: i64| -> Result<Option<(String, String)>, AppError> {
+        if !overlay::table_exists(conn, "BasicCommand")? {
+            return Ok(None);
+        }
+        Ok(conn
+            .query_row(sql, params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?)
+    };
+
+    match overlay::resolve_id(public_id) {
+        (Source::Overlay, id) => match data.lock_overlay()? {
+            Some(conn) => read(&conn, id),
+            None => Ok(None),
+        },
+        (Source::Base, id) => {
+            let conn = data.lock_db()?;
+            read(&conn, id)
+        }
+    }
+}
+
+// TLDR 章节是前端直接渲染的 HTML（<br> 分隔、<b> 示例说明、实体转义），转换为可放进
+// bash 代码块的纯文本：命令行原样输出，说明行变为注释，示例之间空一行。
+// 有 <code> 标记时只有 <code> 行是命令，否则除 <b> 说明外的行都是命令
+fn tldr_plain_text(html: &str) -> String {
+    let mut normalized = html.replace("\r\n", "\n");
+    for br in ["<br>", "<br/>", "<br />"] {
+        normalized = normalized.replace(br, "\n");
+    }
+    let marked = normalized.contains("<code>");
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut gap = false;
+    for line in normalized.lines().map(str::trim) {
+        if line.is_empty() {
+            gap = !lines.is_empty();
+            continue;
+        }
+        let is_command = if marked { line.starts_with("<code>") } else { !line.starts_with("<b>") };
+        let text = strip_tags(line);
+        if gap {
+            lines.push(String::new());
+            gap = false;
+        }
+        lines.push(if is_command { text } else { format!("# {}", text) });
+    }
+    lines.join("\n")
+}
+
+fn resolve_item(data: &AppState, id: i64, kind: ItemKind, ref_id: i64) -> Result<Option<CollectionItem>, AppError> {
+    let item = match kind {
+        ItemKind::Command => resolve_command_detail(data, ref_id)?.map(|detail| CollectionItem {
+            id,
+            kind,
+            ref_id,
+            title: detail.name,
+            description: Some(detail.description),
+            command: detail.tldr.as_deref().map(tldr_plain_text).filter(|text| !text.is_empty()),
+        }),
+        ItemKind::OneLiner => load_one_liner(data, ref_id)?.map(|(command, description)| CollectionItem {
+            id,
+            kind,
+            ref_id,
+            title: description,
+            description: None,
+            command: Some(command),
+        }),
+    };
+    Ok(item)
+}
+
+fn kind_from_str(kind: &str) -> ItemKind {
+    match kind {
+        "one-liner" => ItemKind::OneLiner,
+        _ => ItemKind::Command,
+    }
+}
+
+fn kind_to_str(kind: ItemKind) -> &'static str {
+    match kind {
+        ItemKind::Command => "command",
+        ItemKind::OneLiner => "one-liner",
+    }
+}
+
+// 读取合集及其条目；条目解析需要数据库锁，因此先释放存储锁
+fn load_collection(data: &AppState, collection_id: i64, owner_view: bool) -> Result<Collection, AppError> {
+    let (name, description, owner, share_token, raw_items) = {
+        let conn = lock_store(data)?;
+        let header: Option<(String, Option<String>, String, String)> = conn
+            .query_row(
+                "SELECT c.name, c.description, u.name, c.share_token
+                 FROM Collection c JOIN User u ON c.user_id = u.id
+                 WHERE c.id = ?1",
+                params![collection_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+        let (name, description, owner, share_token) =
+            header.ok_or_else(|| AppError::NotFound(format!("Collection {} not found", collection_id)))?;
+
+        let mut stmt = conn.prepare("SELECT id, kind, ref_id FROM CollectionItem WHERE collection_id = ?1 ORDER BY id")?;
+        let raw_items: Vec<(i64, String, i64)> = stmt
+            .query_map(params![collection_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+
+        (name, description, owner, share_token, raw_items)
+    };
+
+    let mut items = Vec::with_capacity(raw_items.len());
+    for (id, kind, ref_id) in raw_items {
+        // 已删除的命令不再出现在合集中
+        if let Some(item) = resolve_item(data, id, kind_from_str(&kind), ref_id)? {
+            items.push(item);
+        }
+    }
+
+    Ok(Collection {
+        id: collection_id,
+        name,
+        description,
+        owner,
+        share_url: owner_view.then(|| format!("/api/v1/shared/collections/{}", share_token)),
+        items,
+    })
+}
+
+// 校验当前用户拥有该合集
+fn owned_collection(conn: &Connection, user: &User, collection_id: i64) -> Result<(), AppError> {
+    let owner: Option<i64> = conn
+        .query_row("SELECT user_id FROM Collection WHERE id = ?1", params![collection_id], |row| row.get(0))
+        .optional()?;
+    match owner {
+        Some(owner) if owner == user.id => Ok(()),
+        // 不区分“不存在”和“无权限”，避免泄露合集 id
+        _ => Err(AppError::NotFound(format!("Collection {} not found", collection_id))),
+    }
+}
+
+// 代码块围栏比内容中最长的连续反引号多一个（至少三个），内容里的 ``` 不会提前结束代码块
+fn code_fence(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat((longest + 1).max(3))
+}
+
+pub fn collection_markdown(collection: &Collection) -> String {
+    let mut md = format!("# {}\n\n", collection.name);
+    if let Some(description) = &collection.description {
+        md.push_str(&format!("{}\n\n", description));
+    }
+    for item in &collection.items {
+        md.push_str(&format!("## {}\n\n", item.title));
+        if let Some(description) = &item.description {
+            md.push_str(&format!("{}\n\n", description));
+        }
+        if let Some(command) = &item.command {
+            let fence = code_fence(command);
+            md.push_str(&format!("{}bash\n{}\n{}\n\n", fence, command.trim_end(), fence));
+        }
+    }
+    md.truncate(md.trim_end().len());
+    md.push('\n');
+    md
+}
+
+fn markdown_response(collection: &Collection) -> HttpResponse {
+    let filename: String = collection
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+
+    HttpResponse::Ok()
+        .content_type("text/markdown; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.md\"", filename.trim_matches('-')),
+        ))
+        .body(collection_markdown(collection))
+}
+
+// 用户
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users",
+    tag = "users",
+    request_body = RegisterInput,
+    security((), ("admin_token" = [])),
+    responses(
+        (status = 201, description = "Registered user and its token, shown only once", body = ApiResponse<Registration>),
+        (status = 400, description = "Empty or too long user name (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Registration is closed and no valid admin token was given (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 429, description = "Rate limit exceeded; retry after the `Retry-After` seconds", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn register(
+    req: HttpRequest,
+    body: web::Json<RegisterInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    if !data.open_registration {
+        require_admin(&req, &data)?;
+    }
+    require_text("User name", &body.name, MAX_NAME_LEN)?;
+
+    let token = random_token(32);
+    let conn = lock_store(&data)?;
+    conn.execute(
+        "INSERT INTO User (name, token_hash) VALUES (?1, ?2)",
+        params![body.name.trim(), hash_token(&token)],
+    )?;
+    let user = require_user_by_id(&conn, conn.last_insert_rowid())?;
+
+    info!("Registered user {} ({})", user.name, user.id);
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(Registration { user, token }),
+        message: Some("Store this token; it will not be shown again".to_string()),
+    }))
+}
+
+fn require_user_by_id(conn: &Connection, id: i64) -> Result<User, AppError> {
+    Ok(conn.query_row(
+        "SELECT id, name, created_at FROM User WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(User {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        },
+    )?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me",
+    tag = "users",
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The user owning the token", body = ApiResponse<User>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn me(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let conn = lock_store(&data)?;
+    Ok(ok(require_user(&req, &conn)?))
+}
+
+// 收藏
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/favorites",
+    tag = "favorites",
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Favorite commands in the order they were added", body = ApiResponse<Vec<Favorite>>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_favorites(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let rows: Vec<(i64, String)> = {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        let mut stmt = conn.prepare("SELECT command_id, created_at FROM Favorite WHERE user_id = ?1 ORDER BY created_at, command_id")?;
+        stmt.query_map(params![user.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut favorites = Vec::with_capacity(rows.len());
+    for (command_id, added_at) in rows {
+        if let Some(detail) = resolve_command_detail(&data, command_id)? {
+            favorites.push(Favorite {
+                command_id,
+                name: detail.name,
+                description: detail.description,
+                added_at,
+            });
+        }
+    }
+
+    Ok(ok(favorites))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/favorites/{command_id}",
+    tag = "favorites",
+    params(("command_id" = i64, Path, description = "Command id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Id of the favorited command; adding it twice is a no-op", body = ApiResponse<i64>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Command not found (`COMMAND_NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn add_favorite(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let command_id = *path;
+    resolve_command_detail(&data, command_id)?.ok_or(AppError::CommandNotFound)?;
+
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO Favorite (user_id, command_id) VALUES (?1, ?2)",
+        params![user.id, command_id],
+    )?;
+
+    info!("User {} added favorite {}", user.id, command_id);
+    Ok(ok(command_id))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/favorites/{command_id}",
+    tag = "favorites",
+    params(("command_id" = i64, Path, description = "Command id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Id of the removed command", body = ApiResponse<i64>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn remove_favorite(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let command_id = *path;
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    conn.execute(
+        "DELETE FROM Favorite WHERE user_id = ?1 AND command_id = ?2",
+        params![user.id, command_id],
+    )?;
+
+    info!("User {} removed favorite {}", user.id, command_id);
+    Ok(ok(command_id))
+}
+
+// 合集
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections",
+    tag = "collections",
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The caller's collections with their share URLs", body = ApiResponse<Vec<Collection>>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn list_collections(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let ids: Vec<i64> = {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        let mut stmt = conn.prepare("SELECT id FROM Collection WHERE user_id = ?1 ORDER BY id")?;
+        stmt.query_map(params![user.id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?
+    };
+
+    let collections = ids
+        .into_iter()
+        .map(|id| load_collection(&data, id, true))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ok(collections))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections",
+    tag = "collections",
+    request_body = CollectionInput,
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "The new, empty collection", body = ApiResponse<Collection>),
+        (status = 400, description = "Empty or too long name or description (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn create_collection(
+    req: HttpRequest,
+    body: web::Json<CollectionInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+
+    let id = {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        conn.execute(
+            "INSERT INTO Collection (user_id, name, description, share_token) VALUES (?1, ?2, ?3, ?4)",
+            params![user.id, body.name.trim(), body.description.as_deref().map(str::trim), random_token(16)],
+        )?;
+        info!("User {} created collection '{}'", user.id, body.name);
+        conn.last_insert_rowid()
+    };
+
+    Ok(ok(load_collection(&data, id, true)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Collection with its items", body = ApiResponse<Collection>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn get_collection(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        owned_collection(&conn, &user, *path)?;
+    }
+
+    Ok(ok(load_collection(&data, *path, true)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/collections/{id}",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id")),
+    request_body = CollectionInput,
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Updated collection", body = ApiResponse<Collection>),
+        (status = 400, description = "Empty or too long name or description (`INVALID_INPUT`)", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn update_collection(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<CollectionInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    body.validate()?;
+
+    {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        owned_collection(&conn, &user, *path)?;
+        conn.execute(
+            "UPDATE Collection SET name = ?1, description = ?2 WHERE id = ?3",
+            params![body.name.trim(), body.description.as_deref().map(str::trim), *path],
+        )?;
+    }
+
+    Ok(ok(load_collection(&data, *path, true)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/collections/{id}",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Collection and its items deleted", body = ApiResponse<String>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn delete_collection(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let conn = lock_store(&data)?;
+    let user = require_user(&req, &conn)?;
+    owned_collection(&conn, &user, *path)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM CollectionItem WHERE collection_id = ?1", params![*path])?;
+    tx.execute("DELETE FROM Collection WHERE id = ?1", params![*path])?;
+    tx.commit()?;
+
+    info!("User {} deleted collection {}", user.id, *path);
+    Ok(HttpResponse::Ok().json(ApiResponse::<String> {
+        success: true,
+        data: None,
+        message: Some(format!("Collection {} deleted", *path)),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections/{id}/items",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id")),
+    request_body = ItemInput,
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Collection with the new item appended", body = ApiResponse<Collection>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection, command or one-liner not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn add_item(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<ItemInput>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    if resolve_item(&data, 0, body.kind, body.id)?.is_none() {
+        return Err(AppError::NotFound(format!("{} {} not found", kind_to_str(body.kind), body.id)));
+    }
+
+    {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        owned_collection(&conn, &user, *path)?;
+        conn.execute(
+            "INSERT INTO CollectionItem (collection_id, kind, ref_id) VALUES (?1, ?2, ?3)",
+            params![*path, kind_to_str(body.kind), body.id],
+        )?;
+    }
+
+    Ok(ok(load_collection(&data, *path, true)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/collections/{id}/items/{item_id}",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id"), ("item_id" = i64, Path, description = "Collection item id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Collection without the removed item", body = ApiResponse<Collection>),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection or item not found (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn remove_item(
+    req: HttpRequest,
+    path: web::Path<(i64, i64)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (collection_id, item_id) = path.into_inner();
+
+    {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        owned_collection(&conn, &user, collection_id)?;
+        let removed = conn.execute(
+            "DELETE FROM CollectionItem WHERE id = ?1 AND collection_id = ?2",
+            params![item_id, collection_id],
+        )?;
+        if removed == 0 {
+            return Err(AppError::NotFound(format!("Item {} not found", item_id)));
+        }
+    }
+
+    Ok(ok(load_collection(&data, collection_id, true)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/export",
+    tag = "collections",
+    params(("id" = i64, Path, description = "Collection id")),
+    security(("user_token" = [])),
+    responses(
+        (status = 200, description = "Collection rendered as Markdown", body = String, content_type = "text/markdown"),
+        (status = 401, description = "Missing or unknown user token (`UNAUTHORIZED`)", body = ErrorResponse),
+        (status = 404, description = "Collection not found or owned by another user (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn export_collection(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    {
+        let conn = lock_store(&data)?;
+        let user = require_user(&req, &conn)?;
+        owned_collection(&conn, &user, *path)?;
+    }
+
+    Ok(markdown_response(&load_collection(&data, *path, true)?))
+}
+
+// 只读分享
+
+fn shared_collection_id(data: &AppState, share_token: &str) -> Result<i64, AppError> {
+    let conn = lock_store(data)?;
+    conn.query_row(
+        "SELECT id FROM Collection WHERE share_token = ?1",
+        params![share_token],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Shared collection not found".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/shared/collections/{token}",
+    tag = "collections",
+    params(("token" = String, Path, description = "Share token from the owner's `share_url`")),
+    responses(
+        (status = 200, description = "Read-only view of the collection, without its share URL", body = ApiResponse<Collection>),
+        (status = 404, description = "Unknown share token (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn get_shared_collection(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let id = shared_collection_id(&data, &path)?;
+    Ok(ok(load_collection(&data, id, false)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/shared/collections/{token}/export",
+    tag = "collections",
+    params(("token" = String, Path, description = "Share token from the owner's `share_url`")),
+    responses(
+        (status = 200, description = "Shared collection rendered as Markdown", body = String, content_type = "text/markdown"),
+        (status = 404, description = "Unknown share token (`NOT_FOUND`)", body = ErrorResponse),
+        (status = 503, description = "User accounts are not enabled (`FEATURE_DISABLED`)", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+pub async fn export_shared_collection(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let id = shared_collection_id(&data, &path)?;
+    Ok(markdown_response(&load_collection(&data, id, false)?))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/users", web::post().to(register))
+        .route("/users/me", web::get().to(me))
+        .route("/favorites", web::get().to(list_favorites))
+        .route("/favorites/{command_id}", web::put().to(add_favorite))
+        .route("/favorites/{command_id}", web::delete().to(remove_favorite))
+        .route("/collections", web::get().to(list_collections))
+        .route("/collections", web::post().to(create_collection))
+        .route("/collections/{id}", web::get().to(get_collection))
+        .route("/collections/{id}", web::put().to(update_collection))
+        .route("/collections/{id}", web::delete().to(delete_collection))
+        .route("/collections/{id}/items", web::post().to(add_item))
+        .route("/collections/{id}/items/{item_id}", web::delete().to(remove_item))
+        .route("/collections/{id}/export", web::get().to(export_collection))
+        .route("/shared/collections/{token}", web::get().to(get_shared_collection))
+        .route("/shared/collections/{token}/export", web::get().to(export_shared_collection));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tldr_plain_text_strips_html() {
+        assert_eq!(
+            tldr_plain_text("<b>Give the owner execute permission</b><br>chmod u+x &lt;file&gt;<br><br><b>Remove write &amp; read</b><br>chmod go-rw file"),
+            "# Give the owner execute permission\nchmod u+x <file>\n\n# Remove write & read\nchmod go-rw file"
+        );
+        // tldr 导入的格式：只有 <code> 行是命令
+        assert_eq!(
+            tldr_plain_text("Do things.<br><br><b>Search</b><br><code>grep {{pattern}} &gt; out</code>"),
+            "# Do things.\n\n# Search\ngrep {{pattern}} > out"
+        );
+        assert_eq!(tldr_plain_text("grep pattern file"), "grep pattern file");
+        assert_eq!(tldr_plain_text("<br><br>"), "");
+    }
+
+    #[test]
+    fn test_collection_markdown() {
+        let collection = Collection {
+            id: 1,
+            name: "Permissions".to_string(),
+            description: Some("Handy commands".to_string()),
+            owner: "carol".to_string(),
+            share_url: None,
+            items: vec![
+                CollectionItem {
+                    id: 1,
+                    kind: ItemKind::Command,
+                    ref_id: 2,
+                    title: "chmod".to_string(),
+                    description: Some("Change file permissions".to_string()),
+                    command: Some(tldr_plain_text("<b>Make executable</b><br>chmod +x &lt;file&gt;")),
+                },
+                CollectionItem {
+                    id: 2,
+                    kind: ItemKind::OneLiner,
+                    ref_id: 5,
+                    title: "List files".to_string(),
+                    description: None,
+                    command: None,
+                },
+            ],
+        };
+        assert_eq!(
+            collection_markdown(&collection),
+            "# Permissions\n\nHandy commands\n\n## chmod\n\nChange file permissions\n\n```bash\n# Make executable\nchmod +x <file>\n```\n\n## List files\n"
+        );
+    }
+
+    #[test]
+    fn test_code_fence_outgrows_backtick_runs() {
+        assert_eq!(code_fence("ls -l"), "```");
+        assert_eq!(code_fence("echo `date`"), "```");
+        assert_eq!(code_fence("cat <<EOF\n```\nEOF"), "````");
+        assert_eq!(code_fence("printf '`````'"), "``````");
+
+        let collection = Collection {
+            id: 1,
+            name: "Docs".to_string(),
+            description: None,
+            owner: "carol".to_string(),
+            share_url: None,
+            items: vec![CollectionItem {
+                id: 1,
+                kind: ItemKind::Command,
+                ref_id: 2,
+                title: "heredoc".to_string(),
+                description: None,
+                command: Some("cat > README.md <<EOF\n```\nmake\n```\nEOF".to_string()),
+            }],
+        };
+        assert_eq!(
+            collection_markdown(&collection),
+            "# Docs\n\n## heredoc\n\n````bash\ncat > README.md <<EOF\n```\nmake\n```\nEOF\n````\n"
+        );
+    }
+}